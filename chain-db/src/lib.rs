@@ -1,12 +1,16 @@
 use byteorder::{ByteOrder, BE};
 use bytes::BytesMut;
 use chain::{BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, Transaction};
+use kvdb::{KeyValueDB, MemoryDB, RocksDB, WriteBatch};
 use log::{error, info, warn};
 use primitive_types::H256;
 use prost::Message;
 use proto2::chain::ContractType;
 use rand::Rng;
-use rocks::prelude::*;
+use rocks::prelude::{
+    ColumnFamilyDescriptor, ColumnFamilyOptions, CompressionType, CuckooTableOptions, DBOptions,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
 use std::collections::{HashMap, HashSet, LinkedList};
 use std::error::Error;
 use std::fs::OpenOptions;
@@ -17,6 +21,13 @@ use std::path::Path;
 
 pub type BoxError = Box<dyn Error>;
 
+// * Column indices.
+const COL_DEFAULT: usize = 0;
+const COL_BLOCK_HEADER: usize = 1;
+const COL_TRANSACTION: usize = 2;
+const COL_TRANSACTION_BLOCK: usize = 3;
+const NUM_OF_COLUMNS: usize = 4;
+
 #[derive(Debug)]
 pub enum CheckResult {
     Ok,
@@ -25,11 +36,7 @@ pub enum CheckResult {
 }
 
 pub struct ChainDB {
-    db: DB,
-    default: ColumnFamily,
-    block_header: ColumnFamily,
-    transaction: ColumnFamily,
-    transaction_block: ColumnFamily,
+    db: Box<dyn KeyValueDB>,
 }
 
 impl Drop for ChainDB {
@@ -38,6 +45,39 @@ impl Drop for ChainDB {
     }
 }
 
+fn col_descs_for_chain_db() -> Vec<ColumnFamilyDescriptor> {
+    vec![
+        ColumnFamilyDescriptor::new(
+            DEFAULT_COLUMN_FAMILY_NAME,
+            ColumnFamilyOptions::default()
+                .optimize_for_small_db()
+                .optimize_for_point_lookup(32)
+                .num_levels(2)
+                .compression(CompressionType::NoCompression),
+        ),
+        // block_hash => BlockHeader
+        ColumnFamilyDescriptor::new("block-header", ColumnFamilyOptions::default().max_write_buffer_number(6)),
+        // [block_hash, transaction_index: u64, transaction_hash] => Transaction
+        ColumnFamilyDescriptor::new(
+            "transaction",
+            ColumnFamilyOptions::default()
+                .prefix_extractor_fixed(32)
+                .optimize_level_style_compaction(512 * 1024 * 1024)
+                .max_write_buffer_number(6),
+        ),
+        // transaction_hash => [block_hash, transaction_index: u64]
+        // Key and value lengths are fixed
+        ColumnFamilyDescriptor::new(
+            "transaction-block",
+            ColumnFamilyOptions::default()
+                .table_factory_cuckoo(CuckooTableOptions::default())
+                // .optimize_level_style_compaction(512 * 1024 * 1024)
+                // .optimize_for_point_lookup(32)
+                .max_write_buffer_number(6),
+        ),
+    ]
+}
+
 impl ChainDB {
     pub fn new<P: AsRef<Path>>(db_path: P) -> ChainDB {
         let db_options = DBOptions::default()
@@ -47,79 +87,76 @@ impl ChainDB {
             .allow_mmap_reads(true) // for Cuckoo table
             .max_open_files(1024);
 
-        let column_families = vec![
-            ColumnFamilyDescriptor::new(
-                DEFAULT_COLUMN_FAMILY_NAME,
-                ColumnFamilyOptions::default()
-                    .optimize_for_small_db()
-                    .optimize_for_point_lookup(32)
-                    .num_levels(2)
-                    .compression(CompressionType::NoCompression),
-            ),
-            // block_hash => BlockHeader
-            ColumnFamilyDescriptor::new(
-                "block-header",
-                ColumnFamilyOptions::default().max_write_buffer_number(6),
-            ),
-            // [block_hash, transaction_index: u64, transaction_hash] => Transaction
-            ColumnFamilyDescriptor::new(
-                "transaction",
-                ColumnFamilyOptions::default()
-                    .prefix_extractor_fixed(32)
-                    .optimize_level_style_compaction(512 * 1024 * 1024)
-                    .max_write_buffer_number(6),
-            ),
-            // transaction_hash => [block_hash, transaction_index: u64]
-            // Key and value lengths are fixed
-            ColumnFamilyDescriptor::new(
-                "transaction-block",
-                ColumnFamilyOptions::default()
-                    .table_factory_cuckoo(CuckooTableOptions::default())
-                    // .optimize_level_style_compaction(512 * 1024 * 1024)
-                    // .optimize_for_point_lookup(32)
-                    .max_write_buffer_number(6),
-            ),
-        ];
-
-        let (db, mut handles) = DB::open_with_column_families(&db_options, db_path, column_families).unwrap();
-        let txn_blk = handles.pop().unwrap();
-        let txn = handles.pop().unwrap();
-        let blk = handles.pop().unwrap();
-        let default = handles.pop().unwrap();
-
-        assert!(handles.is_empty());
+        let db = RocksDB::open(&db_options, db_path, col_descs_for_chain_db()).unwrap();
+
+        ChainDB { db: Box::new(db) }
+    }
 
+    /// Chain-db kept in memory, for tests and throwaway dev chains.
+    pub fn new_in_memory() -> ChainDB {
         ChainDB {
-            db: db,
-            default: default,
-            block_header: blk,
-            transaction: txn,
-            transaction_block: txn_blk,
+            db: Box::new(MemoryDB::new(NUM_OF_COLUMNS)),
         }
     }
 
+    /// Open chain-db with the storage engine, `rocksdb` or `memory`.
+    pub fn open<P: AsRef<Path>>(engine: &str, db_path: P) -> Result<ChainDB, BoxError> {
+        match engine {
+            "rocksdb" => Ok(ChainDB::new(db_path)),
+            "memory" => Ok(ChainDB::new_in_memory()),
+            _ => Err(format!("unknown storage engine: {:?}", engine).into()),
+        }
+    }
+
+    /// Chain-db of a running node, opened as a RocksDB secondary instance, see `catch_up_with_primary`.
+    pub fn new_secondary<P1: AsRef<Path>, P2: AsRef<Path>>(db_path: P1, tmp_path: P2) -> ChainDB {
+        let db_options = DBOptions::default()
+            .increase_parallelism(num_cpus::get() as _)
+            .allow_mmap_reads(true) // for Cuckoo table
+            .max_open_files(-1); // required by secondary instances
+
+        let db = RocksDB::open_as_secondary(&db_options, db_path, tmp_path, col_descs_for_chain_db()).unwrap();
+
+        ChainDB { db: Box::new(db) }
+    }
+
+    pub fn catch_up_with_primary(&self) -> Result<(), BoxError> {
+        Ok(self.db.try_catch_up_with_primary()?)
+    }
+
+    fn get(&self, col: usize, key: &[u8]) -> Result<Vec<u8>, BoxError> {
+        match self.db.get(col, key)? {
+            Some(val) => Ok(val),
+            None => Err(Box::new(io::Error::new(io::ErrorKind::NotFound, "not found"))),
+        }
+    }
+
+    fn put(&self, col: usize, key: &[u8], value: &[u8]) -> Result<(), BoxError> {
+        let mut wb = WriteBatch::new();
+        wb.put(col, key, value);
+        self.db.write(&wb)?;
+        Ok(())
+    }
+
     pub fn reset_node_id(&self) -> Vec<u8> {
         let mut rng = rand::thread_rng();
         let mut node_id = vec![b'A'; 64];
         rng.fill(&mut node_id[32..]);
-        self.default
-            .put(WriteOptions::default_instance(), b"NODE_ID", &node_id)
-            .unwrap();
+        self.put(COL_DEFAULT, b"NODE_ID", &node_id).unwrap();
         node_id
     }
 
     pub fn get_node_id(&self) -> Vec<u8> {
-        if let Ok(node_id) = self.default.get(ReadOptions::default_instance(), b"NODE_ID") {
-            node_id.to_vec()
+        if let Ok(node_id) = self.get(COL_DEFAULT, b"NODE_ID") {
+            node_id
         } else {
             self.reset_node_id()
         }
     }
 
     pub fn get_block_height(&self) -> i64 {
-        self.default
-            .get(ReadOptions::default_instance(), b"BLOCK_HEIGHT")
-            .map(|val| BE::read_u64(&*val) as i64)
+        self.get(COL_DEFAULT, b"BLOCK_HEIGHT")
+            .map(|val| BE::read_u64(&val) as i64)
             .unwrap_or(0)
     }
 
@@ -128,18 +165,14 @@ impl ChainDB {
         if height > self.get_block_height() {
             let mut val = [0u8; 8];
             BE::write_u64(&mut val, height as u64);
-            self.default
-                .put(WriteOptions::default_instance(), b"BLOCK_HEIGHT", &val)
-                .unwrap();
+            self.put(COL_DEFAULT, b"BLOCK_HEIGHT", &val).unwrap();
         }
     }
 
     pub fn force_update_block_height(&self, height: i64) -> Result<(), BoxError> {
         let mut val = [0u8; 8];
         BE::write_u64(&mut val, height as u64);
-        self.default
-            .put(WriteOptions::default_instance(), b"BLOCK_HEIGHT", &val)
-            .map_err(From::from)
+        self.put(COL_DEFAULT, b"BLOCK_HEIGHT", &val)
     }
 
     /// Highest block id, counted from 0
@@ -148,11 +181,11 @@ impl ChainDB {
     }
 
     pub fn insert_block(&self, block: &IndexedBlock) -> Result<(), Box<dyn Error>> {
-        let mut batch = WriteBatch::with_reserved_bytes(1024);
+        let mut batch = WriteBatch::with_capacity(1 + block.transactions.len() * 2);
 
         let mut buf = BytesMut::with_capacity(block.header.raw.encoded_len());
         block.header.raw.encode(&mut buf)?;
-        batch.put_cf(&self.block_header, block.header.hash.as_bytes(), &buf);
+        batch.put(COL_BLOCK_HEADER, block.header.hash.as_bytes(), &buf);
 
         for (index, txn) in block.transactions.iter().enumerate() {
             buf.clear();
@@ -162,28 +195,18 @@ impl ChainDB {
             let mut idx_key = [0u8; 8];
             BE::write_u64(&mut idx_key[..], index as u64);
 
-            batch.putv_cf(
-                &self.transaction,
-                &[block.hash().as_bytes(), &idx_key, txn.hash.as_bytes()],
-                &[&buf],
-            );
+            batch.put(COL_TRANSACTION, &[block.hash().as_bytes(), &idx_key, txn.hash.as_bytes()].concat(), &buf);
             // reverse index
             // transaction_hash => [block_hash, transaction_index: u64]
-            batch.putv_cf(
-                &self.transaction_block,
-                &[txn.hash.as_bytes()],
-                &[block.hash().as_bytes(), &idx_key],
-            );
+            batch.put(COL_TRANSACTION_BLOCK, txn.hash.as_bytes(), &[block.hash().as_bytes(), &idx_key].concat());
         }
 
-        self.db.write(WriteOptions::default_instance(), &batch)?;
+        self.db.write(&batch)?;
         Ok(())
     }
 
     pub fn has_block_id(&self, id: &H256) -> bool {
-        self.block_header
-            .get(ReadOptions::default_instance(), id.as_bytes())
-            .is_ok()
+        self.get(COL_BLOCK_HEADER, id.as_bytes()).is_ok()
     }
 
     pub fn has_block(&self, block: &IndexedBlock) -> bool {
@@ -191,32 +214,18 @@ impl ChainDB {
     }
 
     pub fn has_block_number(&self, num: u64) -> bool {
-        let mut lower_bound = [0u8; 32];
-        BE::write_u64(&mut lower_bound[..8], num);
-        let mut upper_bound = [0xffu8; 32];
-        BE::write_u64(&mut upper_bound[..8], num);
-
-        let it = self.block_header.new_iterator(
-            &ReadOptions::default()
-                .iterate_lower_bound(&lower_bound[..])
-                .iterate_upper_bound(&upper_bound[..]),
-        );
-        it.count() > 0
+        self.db
+            .iter_with_prefix(COL_BLOCK_HEADER, &num.to_be_bytes())
+            .next()
+            .is_some()
     }
 
     pub fn get_block_from_header(&self, header: IndexedBlockHeader) -> Result<IndexedBlock, BoxError> {
-        let mut upper_bound = header.hash.as_bytes().to_vec();
-        upper_bound.push(0xFF); // [0xcafebabe00 .. 0xcafebabeff]
-
         let transactions = self
-            .transaction
-            .new_iterator(
-                &ReadOptions::default()
-                    .iterate_lower_bound(&header.hash.as_bytes())
-                    .iterate_upper_bound(&upper_bound),
-            )
+            .db
+            .iter_with_prefix(COL_TRANSACTION, header.hash.as_bytes())
             .map(|(key, val)| {
-                let txn = Transaction::decode(val)?;
+                let txn = Transaction::decode(&*val)?;
                 Ok(IndexedTransaction::new(H256::from_slice(&key[32 + 8..]), txn))
             })
             .collect::<Result<Vec<_>, BoxError>>();
@@ -225,58 +234,31 @@ impl ChainDB {
     }
 
     pub fn get_block_transactions(&self, hash: &H256) -> Result<Vec<IndexedTransaction>, BoxError> {
-        let mut upper_bound = hash.as_bytes().to_vec();
-        upper_bound.push(0xFF); // [0xcafebabe00 .. 0xcafebabeff]
-
-        let ropts = ReadOptions::default()
-            .iterate_lower_bound(&hash.as_bytes())
-            .iterate_upper_bound(&upper_bound);
-        let txns = self
-            .transaction
-            .new_iterator(&ropts)
+        self.db
+            .iter_with_prefix(COL_TRANSACTION, hash.as_bytes())
             .map(|(key, val)| {
-                let txn = Transaction::decode(val)?;
+                let txn = Transaction::decode(&*val)?;
                 Ok(IndexedTransaction::new(H256::from_slice(&key[32 + 8..]), txn))
             })
-            .collect::<Result<Vec<_>, BoxError>>();
-        drop(ropts);
-        txns
+            .collect::<Result<Vec<_>, BoxError>>()
     }
 
     pub fn get_transaction_hashes_by_block_number(&self, num: i64) -> Result<Vec<H256>, BoxError> {
-        let mut lower_bound = [0u8; 8];
-        BE::write_u64(&mut lower_bound[..], num as u64);
-        let mut upper_bound = [0u8; 8];
-        BE::write_u64(&mut upper_bound[..], num as u64 + 1);
-
-        let ropts = ReadOptions::default()
-            .iterate_lower_bound(&lower_bound)
-            .iterate_upper_bound(&upper_bound);
         let txn_hashes = self
-            .transaction
-            .new_iterator(&ropts)
-            .keys()
-            .map(|key| Ok(H256::from_slice(&key[32 + 8..])))
-            .collect::<Result<Vec<_>, BoxError>>();
-        drop(ropts);
-        txn_hashes
+            .db
+            .iter_with_prefix(COL_TRANSACTION, &(num as u64).to_be_bytes())
+            .map(|(key, _)| H256::from_slice(&key[32 + 8..]))
+            .collect();
+        Ok(txn_hashes)
     }
 
     pub fn get_transaction_hashes_by_block_hash(&self, hash: &H256) -> Result<Vec<H256>, BoxError> {
-        let mut upper_bound = hash.as_bytes().to_vec();
-        upper_bound.push(0xFF); // [0xcafebabe00 .. 0xcafebabeff]
-
-        let ropts = ReadOptions::default()
-            .iterate_lower_bound(&hash.as_bytes())
-            .iterate_upper_bound(&upper_bound);
         let txn_hashes = self
-            .transaction
-            .new_iterator(&ropts)
-            .keys()
-            .map(|key| Ok(H256::from_slice(&key[32 + 8..])))
-            .collect::<Result<Vec<_>, BoxError>>();
-        drop(ropts);
-        txn_hashes
+            .db
+            .iter_with_prefix(COL_TRANSACTION, hash.as_bytes())
+            .map(|(key, _)| H256::from_slice(&key[32 + 8..]))
+            .collect();
+        Ok(txn_hashes)
     }
 
     pub fn get_block_header_by_number(&self, num: i64) -> Result<IndexedBlockHeader, BoxError> {
@@ -292,49 +274,24 @@ impl ChainDB {
     }
 
     pub fn get_block_header(&self, hash: &H256) -> Result<IndexedBlockHeader, BoxError> {
-        self.block_header
-            .get(ReadOptions::default_instance(), hash.as_bytes())
-            .map_err(From::from)
+        self.get(COL_BLOCK_HEADER, hash.as_bytes())
             .and_then(|raw_header| BlockHeader::decode(&*raw_header).map_err(From::from))
             .map(|header| IndexedBlockHeader::new(hash.clone(), header))
     }
 
     /// handles fork
     pub fn get_block_headers_by_number(&self, num: u64) -> Vec<IndexedBlockHeader> {
-        let mut lower_bound = [0u8; 32];
-        BE::write_u64(&mut lower_bound[..8], num);
-        let mut upper_bound = [0xff_u8; 32];
-        BE::write_u64(&mut upper_bound[..8], num);
-        let ropt = ReadOptions::default()
-            .iterate_lower_bound(&lower_bound[..])
-            .iterate_upper_bound(&upper_bound[..])
-            .pin_data(true);
-
-        self.block_header
-            .new_iterator(&ropt)
-            .map(|(key, val)| IndexedBlockHeader::new(H256::from_slice(key), BlockHeader::decode(val).unwrap()))
+        self.db
+            .iter_with_prefix(COL_BLOCK_HEADER, &num.to_be_bytes())
+            .map(|(key, val)| IndexedBlockHeader::new(H256::from_slice(&key), BlockHeader::decode(&*val).unwrap()))
             .collect()
     }
 
     pub fn get_block_by_number(&self, num: u64) -> Result<IndexedBlock, BoxError> {
-        let mut lower_bound = [0u8; 32];
-        BE::write_u64(&mut lower_bound[..8], num);
-        let mut upper_bound = [0xff_u8; 32];
-        BE::write_u64(&mut upper_bound[..8], num);
-
-        let ropts = ReadOptions::default()
-            .iterate_lower_bound(&lower_bound[..])
-            .iterate_upper_bound(&upper_bound[..])
-            .pin_data(true);
-        let it = self.block_header.new_iterator(&ropts);
-
-        // FIXME: iterator key lifetime leaks, key might becomes same key
-        // ref: https://github.com/bh1xuw/rust-rocks/issues/15
-        let found = it
-            // .take_while(|(key, _)| &key[..8] == lower_bound)
-            .map(|(key, val)| (key.to_vec(), val.to_vec()))
+        let found = self
+            .db
+            .iter_with_prefix(COL_BLOCK_HEADER, &num.to_be_bytes())
             .collect::<Vec<_>>();
-        drop(ropts); // holds lifetime of bound slice.
 
         if found.is_empty() {
             return Err(Box::new(io::Error::new(io::ErrorKind::NotFound, "block not found")));
@@ -357,9 +314,7 @@ impl ChainDB {
     }
 
     pub fn get_block_by_id(&self, id: &H256) -> Result<IndexedBlock, BoxError> {
-        self.block_header
-            .get(ReadOptions::default_instance(), id.as_bytes())
-            .map_err(From::from)
+        self.get(COL_BLOCK_HEADER, id.as_bytes())
             .and_then(|raw_header| BlockHeader::decode(&*raw_header).map_err(From::from))
             .map(|header| IndexedBlockHeader::new(id.clone(), header))
             .and_then(|header| self.get_block_from_header(header))
@@ -370,55 +325,39 @@ impl ChainDB {
     }
 
     pub fn get_transaction_by_id(&self, id: &H256) -> Result<IndexedTransaction, BoxError> {
-        let mut key = self
-            .transaction_block
-            .get(ReadOptions::default_instance(), id.as_bytes())?
-            .to_vec();
+        let mut key = self.get(COL_TRANSACTION_BLOCK, id.as_bytes())?;
         key.extend_from_slice(id.as_bytes());
         let txn = self
-            .transaction
-            .get(ReadOptions::default_instance(), &key)
+            .get(COL_TRANSACTION, &key)
             .map(|raw| Transaction::decode(&*raw).unwrap())
             .map(|txn| IndexedTransaction::new(id.clone(), txn))?;
         Ok(txn)
     }
 
     pub fn get_transaction_index(&self, id: &H256) -> Result<i32, BoxError> {
-        let key = self
-            .transaction_block
-            .get(ReadOptions::default_instance(), id.as_bytes())?;
+        let key = self.get(COL_TRANSACTION_BLOCK, id.as_bytes())?;
         Ok(BE::read_u64(&key[32..]) as i32)
     }
 
     pub fn get_transaction_block_hash(&self, id: &H256) -> Result<H256, BoxError> {
-        let key = self
-            .transaction_block
-            .get(ReadOptions::default_instance(), id.as_bytes())?;
+        let key = self.get(COL_TRANSACTION_BLOCK, id.as_bytes())?;
         Ok(H256::from_slice(&key[..32]))
     }
 
     pub fn get_block_header_by_transaction_hash(&self, txn_hash: &H256) -> Result<IndexedBlockHeader, BoxError> {
-        let block_key = self
-            .transaction_block
-            .get(ReadOptions::default_instance(), txn_hash.as_bytes())?;
-        self.block_header
-            .get(ReadOptions::default_instance(), &block_key[..32])
+        let block_key = self.get(COL_TRANSACTION_BLOCK, txn_hash.as_bytes())?;
+        self.get(COL_BLOCK_HEADER, &block_key[..32])
             .map(|raw| BlockHeader::decode(&*raw).unwrap())
             .map(|header| IndexedBlockHeader::new(H256::from_slice(&block_key[..32]), header))
-            .map_err(From::from)
     }
 
     pub fn delete_transaction(&self, txn: &IndexedTransaction, wb: &mut WriteBatch) -> Result<(), BoxError> {
-        let block_key = self
-            .transaction_block
-            .get(ReadOptions::default_instance(), txn.hash.as_bytes())?;
-
-        if let Err(e) = self.block_header.get(ReadOptions::default_instance(), &block_key[..32]) {
-            if e.is_not_found() {
-                wb.deletev_cf(&self.transaction, &[&*block_key, txn.hash.as_bytes()]);
-                wb.delete_cf(&self.transaction_block, txn.hash.as_bytes());
-                return Ok(());
-            }
+        let block_key = self.get(COL_TRANSACTION_BLOCK, txn.hash.as_bytes())?;
+
+        if self.db.get(COL_BLOCK_HEADER, &block_key[..32])?.is_none() {
+            wb.delete(COL_TRANSACTION, &[&*block_key, txn.hash.as_bytes()].concat());
+            wb.delete(COL_TRANSACTION_BLOCK, txn.hash.as_bytes());
+            return Ok(());
         }
 
         Err(Box::new(io::Error::new(
@@ -428,62 +367,49 @@ impl ChainDB {
     }
 
     pub fn delete_block_by_number(&self, num: u64) -> Result<(), BoxError> {
-        let mut lower_bound = [0u8; 8];
-        BE::write_u64(&mut lower_bound[..], num);
-
-        let mut wb = WriteBatch::with_reserved_bytes(1024);
-
-        self.block_header
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(&lower_bound))
-            .keys()
-            .take_while(|key| &key[..8] == &lower_bound)
-            .for_each(|key| {
-                info!("delete block {}", hex::encode(key));
-                wb.delete_cf(&self.block_header, key);
-            });
-        self.transaction
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(&lower_bound))
-            .keys()
-            .take_while(|key| &key[..8] == &lower_bound)
-            .for_each(|key| {
-                info!("delete transaction {}", hex::encode(&key[32 + 8..]));
-                wb.delete_cf(&self.transaction, key);
-                wb.delete_cf(&self.transaction_block, &key[32 + 8..]);
+        let prefix = num.to_be_bytes();
+
+        let mut wb = WriteBatch::new();
+
+        self.db
+            .iter_with_prefix(COL_BLOCK_HEADER, &prefix)
+            .for_each(|(key, _)| {
+                info!("delete block {}", hex::encode(&key));
+                wb.delete(COL_BLOCK_HEADER, &key);
             });
+        self.db.iter_with_prefix(COL_TRANSACTION, &prefix).for_each(|(key, _)| {
+            info!("delete transaction {}", hex::encode(&key[32 + 8..]));
+            wb.delete(COL_TRANSACTION, &key);
+            wb.delete(COL_TRANSACTION_BLOCK, &key[32 + 8..]);
+        });
 
-        self.db.write(WriteOptions::default_instance(), &wb)?;
+        self.db.write(&wb)?;
 
         Ok(())
     }
 
     pub fn delete_block(&self, block: &IndexedBlock) -> bool {
-        let mut wb = WriteBatch::with_reserved_bytes(1024);
-
-        wb.delete_cf(&self.block_header, block.hash().as_bytes());
-
-        let header = &block.header;
-        self.transaction
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(&header.hash.as_bytes()))
-            .keys()
-            .take_while(|key| &key[..32] == header.hash.as_bytes())
-            .for_each(|key| {
-                wb.delete_cf(&self.transaction, &key);
-                wb.delete_cf(&self.transaction_block, &key[32 + 8..]);
+        let mut wb = WriteBatch::new();
+
+        wb.delete(COL_BLOCK_HEADER, block.hash().as_bytes());
+
+        self.db
+            .iter_with_prefix(COL_TRANSACTION, block.header.hash.as_bytes())
+            .for_each(|(key, _)| {
+                wb.delete(COL_TRANSACTION, &key);
+                wb.delete(COL_TRANSACTION_BLOCK, &key[32 + 8..]);
             });
 
-        self.db.write(WriteOptions::default_instance(), &wb).is_ok()
+        self.db.write(&wb).is_ok()
     }
 
     fn delete_block_without_reverse_index(&self, block: &IndexedBlock, wb: &mut WriteBatch) {
-        wb.delete_cf(&self.block_header, block.hash().as_bytes());
-
-        let header = &block.header;
-        self.transaction
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(&header.hash.as_bytes()))
-            .keys()
-            .take_while(|key| &key[..32] == header.hash.as_bytes())
-            .for_each(|key| {
-                wb.delete_cf(&self.transaction, &key);
+        wb.delete(COL_BLOCK_HEADER, block.hash().as_bytes());
+
+        self.db
+            .iter_with_prefix(COL_TRANSACTION, block.header.hash.as_bytes())
+            .for_each(|(key, _)| {
+                wb.delete(COL_TRANSACTION, &key);
             });
     }
 
@@ -497,29 +423,25 @@ impl ChainDB {
             (&mut corrent_reverse_index[..32]).copy_from_slice(block.hash().as_bytes());
             BE::write_u64(&mut corrent_reverse_index[32..], i as u64);
 
-            let reverse_index = self
-                .transaction_block
-                .get(ReadOptions::default_instance(), txn.hash.as_bytes())
-                .unwrap();
+            let reverse_index = self.get(COL_TRANSACTION_BLOCK, txn.hash.as_bytes()).unwrap();
 
-            if corrent_reverse_index != &*reverse_index {
+            if corrent_reverse_index != reverse_index {
                 println!(
                     "! wrong reverse index {:?}\n=> {}\n=> {}",
                     txn.hash,
-                    hex::encode(&*reverse_index),
+                    hex::encode(&reverse_index),
                     hex::encode(&corrent_reverse_index),
                 );
-                wb.put_cf(&self.transaction_block, txn.hash.as_ref(), &corrent_reverse_index);
+                wb.put(COL_TRANSACTION_BLOCK, txn.hash.as_ref(), &corrent_reverse_index);
             }
         });
     }
 
     pub fn block_hashes_from(&self, start_block_hash: &[u8], count: usize) -> Vec<Vec<u8>> {
-        self.block_header
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(start_block_hash))
-            .keys()
+        self.db
+            .iter_from(COL_BLOCK_HEADER, start_block_hash)
             .take(count)
-            .map(|key| key.to_vec())
+            .map(|(key, _)| key.into_vec())
             .collect()
     }
 
@@ -568,7 +490,7 @@ impl ChainDB {
             }
         }
 
-        let mut wb = WriteBatch::with_reserved_bytes(1024);
+        let mut wb = WriteBatch::new();
 
         let mut txn_whitelist = HashSet::new();
         let mut orphan_txns = HashSet::new();
@@ -583,7 +505,7 @@ impl ChainDB {
 
         for fork in tobe_purged_forks {
             for header in fork.iter() {
-                // wb.delete_cf(&self.block_header, header.hash.as_bytes());
+                // wb.delete(COL_BLOCK_HEADER, header.hash.as_bytes());
                 let block = self.get_block_from_header(header.clone()).unwrap();
                 self.delete_block_without_reverse_index(&block, &mut wb);
                 println!("! delete block {:?}", header.hash);
@@ -598,7 +520,7 @@ impl ChainDB {
         if dry_run {
             return Ok(());
         }
-        self.db.write(WriteOptions::default_instance(), &wb)?;
+        self.db.write(&wb)?;
 
         if !orphan_txns.is_empty() {
             let mut f = OpenOptions::new()
@@ -615,10 +537,8 @@ impl ChainDB {
     }
 
     pub fn visit(&self) -> Result<(), Box<dyn Error>> {
-        let it = self.transaction.new_iterator(ReadOptions::default_instance());
-
-        for (key, raw) in it {
-            let txn = Transaction::decode(raw)?;
+        for (key, raw) in self.db.iter(COL_TRANSACTION) {
+            let txn = Transaction::decode(&*raw)?;
             match ContractType::from_i32(txn.raw_data.as_ref().unwrap().contract.as_ref().unwrap().r#type) {
                 Some(ContractType::TransferContract) => {
                     println!("txn id: {} => {:?}", hex::encode(key), txn.result);
@@ -633,18 +553,16 @@ impl ChainDB {
     }
 
     pub fn block_headers<'a>(&'a self) -> impl Iterator<Item = IndexedBlockHeader> + 'a {
-        self.block_header
-            .new_iterator(ReadOptions::default_instance())
-            .map(|(blk_id, raw_header)| {
-                IndexedBlockHeader::new(H256::from_slice(blk_id), BlockHeader::decode(raw_header).unwrap())
-            })
+        self.db.iter(COL_BLOCK_HEADER).map(|(blk_id, raw_header)| {
+            IndexedBlockHeader::new(H256::from_slice(&blk_id), BlockHeader::decode(&*raw_header).unwrap())
+        })
     }
 
     pub fn blocks<'a>(&'a self) -> impl Iterator<Item = IndexedBlock> + 'a {
-        self.block_header
-            .new_iterator(ReadOptions::default_instance())
+        self.db
+            .iter(COL_BLOCK_HEADER)
             .map(|(blk_id, raw_header)| {
-                IndexedBlockHeader::new(H256::from_slice(blk_id), BlockHeader::decode(raw_header).unwrap())
+                IndexedBlockHeader::new(H256::from_slice(&blk_id), BlockHeader::decode(&*raw_header).unwrap())
             })
             .map(move |header| self.get_block_from_header(header).unwrap())
     }
@@ -656,20 +574,13 @@ impl ChainDB {
                 .map(|head| head.hash)
                 .collect()
         } else {
-            let mut lower_bound = [0u8; 32];
-            BE::write_u64(&mut lower_bound[..8], num as u64 - 65535);
-            let mut upper_bound = [0xff_u8; 32];
-            BE::write_u64(&mut upper_bound[..8], num as u64);
+            let lower_bound = (num as u64 - 65535).to_be_bytes();
 
             let mut ref_hashes = self
-                .block_header
-                .new_iterator(
-                    &ReadOptions::default()
-                        .iterate_lower_bound(&lower_bound[..])
-                        .iterate_upper_bound(&upper_bound[..]),
-                )
-                .keys()
-                .map(|raw_hash| H256::from_slice(raw_hash))
+                .db
+                .iter_from(COL_BLOCK_HEADER, &lower_bound)
+                .take_while(|(raw_hash, _)| BE::read_u64(&raw_hash[..8]) <= num as u64)
+                .map(|(raw_hash, _)| H256::from_slice(&raw_hash))
                 .collect::<Vec<_>>();
 
             let wrap_pos = 65536 - (num + 1) % 65536;
@@ -680,18 +591,15 @@ impl ChainDB {
     }
 
     pub fn get_parent_hash_verified_block_number(&self) -> u64 {
-        self.default
-            .get(ReadOptions::default_instance(), b"PARENT_HASH_VERIFIED")
-            .map(|raw| BE::read_u64(&*raw))
+        self.get(COL_DEFAULT, b"PARENT_HASH_VERIFIED")
+            .map(|raw| BE::read_u64(&raw))
             .unwrap_or(0)
     }
 
     pub fn update_parent_hash_verified_block_number(&self, num: u64) -> Result<(), BoxError> {
         let mut raw = [0u8; 8];
         BE::write_u64(&mut raw[..], num);
-        self.default
-            .put(WriteOptions::default_instance(), b"PARENT_HASH_VERIFIED", &raw)
-            .map_err(From::from)
+        self.put(COL_DEFAULT, b"PARENT_HASH_VERIFIED", &raw)
     }
 
     pub fn verify_parent_hashes(&self) -> Result<CheckResult, BoxError> {
@@ -707,10 +615,10 @@ impl ChainDB {
         );
 
         for header in self
-            .block_header
-            .new_iterator(&ReadOptions::default().iterate_lower_bound(start_block.hash().as_bytes()))
+            .db
+            .iter_from(COL_BLOCK_HEADER, start_block.hash().as_bytes())
             .map(|(blk_id, raw_header)| {
-                IndexedBlockHeader::new(H256::from_slice(blk_id), BlockHeader::decode(raw_header).unwrap())
+                IndexedBlockHeader::new(H256::from_slice(&blk_id), BlockHeader::decode(&*raw_header).unwrap())
             })
         {
             if header.raw.raw_data.as_ref().unwrap().parent_hash != parent_hash {
@@ -749,27 +657,23 @@ impl ChainDB {
     }
 
     pub fn get_merkle_tree_verified_block_number(&self) -> u64 {
-        self.default
-            .get(ReadOptions::default_instance(), b"MERKLE_TREE_VERIFIED")
-            .map(|raw| BE::read_u64(&*raw))
+        self.get(COL_DEFAULT, b"MERKLE_TREE_VERIFIED")
+            .map(|raw| BE::read_u64(&raw))
             .unwrap_or(0)
     }
 
     pub fn update_merkle_tree_verified_block_number(&self, num: u64) -> Result<(), BoxError> {
         let mut raw = [0u8; 8];
         BE::write_u64(&mut raw[..], num);
-        self.default
-            .put(WriteOptions::default_instance(), b"MERKLE_TREE_VERIFIED", &raw)
-            .map_err(From::from)
+        self.put(COL_DEFAULT, b"MERKLE_TREE_VERIFIED", &raw)
     }
 
     pub fn verify_merkle_tree(&self, patch: &HashMap<H256, H256>) -> Result<bool, Box<dyn Error>> {
         let start_block = self.get_block_by_number(self.get_merkle_tree_verified_block_number())?;
-        let ropt = ReadOptions::default().iterate_lower_bound(start_block.hash().as_bytes());
         info!("verify merkle tree from {}", start_block.number());
 
-        for (blk_id, raw_header) in self.block_header.new_iterator(&ropt) {
-            let header = IndexedBlockHeader::new(H256::from_slice(blk_id), BlockHeader::decode(raw_header).unwrap());
+        for (blk_id, raw_header) in self.db.iter_from(COL_BLOCK_HEADER, start_block.hash().as_bytes()) {
+            let header = IndexedBlockHeader::new(H256::from_slice(&blk_id), BlockHeader::decode(&*raw_header).unwrap());
             let block = self.get_block_from_header(header).unwrap();
 
             if !block.verify_merkle_root_hash() {
@@ -793,15 +697,9 @@ impl ChainDB {
     }
 
     pub fn get_accumulated_db_property(&self, key: &str) -> u64 {
-        [
-            &self.default,
-            &self.block_header,
-            &self.transaction,
-            &self.transaction_block,
-        ]
-        .iter()
-        .map(|cf| cf.get_int_property(key).unwrap_or_default())
-        .sum()
+        (0..NUM_OF_COLUMNS)
+            .map(|col| self.db.get_column_int_property(col, key).unwrap_or_default())
+            .sum()
     }
 
     pub fn report_status(&self) {
@@ -841,18 +739,14 @@ impl ChainDB {
     }
 
     pub fn compact_db(&self) -> Result<(), BoxError> {
-        self.default.compact_range(&Default::default(), ..)?;
-        self.block_header.compact_range(&Default::default(), ..)?;
-        self.transaction.compact_range(&Default::default(), ..)?;
-        self.transaction_block.compact_range(&Default::default(), ..)?;
+        for col in 0..NUM_OF_COLUMNS {
+            self.db.compact(col)?;
+        }
         Ok(())
     }
 
     pub unsafe fn prepare_close(&self) {
-        info!("flush db ... {:?}", self.db.flush(&FlushOptions::default()));
-        self.db.cancel_background_work(/* wait: */ true);
-        info!("cancel background work");
-        info!("syncing WAL ... {:?}", self.db.sync_wal());
+        self.db.prepare_close();
         // eprintln!("Close DB ... {:?}", self.db.close());
     }
 }
@@ -31,6 +31,12 @@ pub struct ChainParameterConfig {
     pub allow_tvm_solidity_059_upgrade: bool,
     #[serde(default = "Default::default")]
     pub allow_tvm_shielded_upgrade: bool,
+    #[serde(default = "Default::default")]
+    pub allow_tvm_istanbul_upgrade: bool,
+    #[serde(default = "Default::default")]
+    pub allow_tvm_stake_upgrade: bool,
+    #[serde(default = "Default::default")]
+    pub allow_tvm_asset_issue_upgrade: bool,
     // forbid-transfer-to-contract = false
     /// Default energy price is 100 SUN/unit. While in Mainnet/Testnet, it's 10 SUN/unit.
     #[serde(default = "default_energy_fee")]
@@ -70,20 +76,29 @@ pub struct StorageConfig {
     /// Path to ChainDB.
     #[serde(default = "default_data_dir")]
     pub data_dir: String,
-    // TODO: impl a different engine
-    #[serde(default = "Default::default")]
+    /// Storage engine of ChainDB and StateDB, `rocksdb` or `memory`.
+    ///
+    /// Nothing is written to disk with `memory`, for throwaway dev chains.
+    #[serde(default = "default_engine")]
     pub engine: String,
     /// Path to StateDB.
     #[serde(default = "default_state_data_dir")]
     pub state_data_dir: String,
     #[serde(default = "default_state_cache_dir")]
     pub state_cache_dir: String,
+    /// Save change sets of every block, to query historical state.
+    #[serde(default = "Default::default")]
+    pub archive: bool,
 }
 
 fn default_data_dir() -> String {
     "./data/chaindb".into()
 }
 
+fn default_engine() -> String {
+    "rocksdb".into()
+}
+
 fn default_state_data_dir() -> String {
     "./data/statedb".into()
 }
@@ -133,6 +148,15 @@ pub struct GraphQLConfig {
     pub endpoint: String,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct WitnessConfig {
+    /// Private key of the witness, in hex. Block producing is disabled when empty.
+    #[serde(default = "Default::default")]
+    pub private_key: String,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
@@ -140,6 +164,8 @@ pub struct Config {
     pub storage: StorageConfig,
     pub protocol: ProtocolConfig,
     pub graphql: GraphQLConfig,
+    #[serde(default = "Default::default")]
+    pub witness: WitnessConfig,
 }
 
 impl Config {
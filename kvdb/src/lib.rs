@@ -0,0 +1,114 @@
+//! The storage engine of state-db and chain-db.
+
+use std::io;
+
+pub use self::memorydb::MemoryDB;
+pub use self::rocksdb::{RocksDB, RocksDBSnapshot};
+
+mod memorydb;
+mod rocksdb;
+
+/// A key/value pair read from a `KeyValueDB`.
+pub type KeyValue = (Box<[u8]>, Box<[u8]>);
+
+/// Write operation of a `WriteBatch`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DBOp {
+    Put { col: usize, key: Vec<u8>, value: Vec<u8> },
+    Delete { col: usize, key: Vec<u8> },
+}
+
+/// A batch of changes, written to a `KeyValueDB` atomically.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    pub ops: Vec<DBOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        WriteBatch { ops: vec![] }
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        WriteBatch {
+            ops: Vec::with_capacity(cap),
+        }
+    }
+
+    pub fn put(&mut self, col: usize, key: &[u8], value: &[u8]) {
+        self.ops.push(DBOp::Put {
+            col,
+            key: key.to_owned(),
+            value: value.to_owned(),
+        });
+    }
+
+    pub fn delete(&mut self, col: usize, key: &[u8]) {
+        self.ops.push(DBOp::Delete {
+            col,
+            key: key.to_owned(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// The storage engine. Data is organized in columns, indexed from 0, and keys of a column are sorted.
+pub trait KeyValueDB: Send + Sync {
+    /// Get a value by key.
+    fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+
+    /// Write a batch of changes to the backing store.
+    fn write(&self, batch: &WriteBatch) -> io::Result<()>;
+
+    /// Iterate over the data for a given column, starting from the first key not less than `lower_bound`.
+    fn iter_from<'a>(&'a self, col: usize, lower_bound: &[u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a>;
+
+    /// Iterate over the data for a given column.
+    fn iter<'a>(&'a self, col: usize) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        self.iter_from(col, &[])
+    }
+
+    /// Iterate over the data for a given column, returning all key/value pairs
+    /// where the key starts with the given prefix.
+    fn iter_with_prefix<'a>(&'a self, col: usize, prefix: &[u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        let prefix = prefix.to_owned();
+        Box::new(
+            self.iter_from(col, &prefix)
+                .take_while(move |(key, _)| key.starts_with(&prefix)),
+        )
+    }
+
+    /// Get the first value matching the given prefix.
+    fn get_by_prefix(&self, col: usize, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.iter_with_prefix(col, prefix).next().map(|(_, value)| value)
+    }
+
+    /// Integer property of the engine, e.g. `rocksdb.num-running-flushes`.
+    fn get_int_property(&self, _name: &str) -> Option<u64> {
+        None
+    }
+
+    /// Integer property of a column.
+    fn get_column_int_property(&self, _col: usize, _name: &str) -> Option<u64> {
+        None
+    }
+
+    /// Compact the whole key range of a column.
+    fn compact(&self, _col: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// A consistent read-only view of current data, not affected by later writes.
+    fn snapshot(&self) -> Box<dyn KeyValueDB>;
+
+    /// Catch up with the primary instance, for secondary instances.
+    fn try_catch_up_with_primary(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Flush all data and stop background jobs, the db must not be used afterwards.
+    unsafe fn prepare_close(&self) {}
+}
@@ -0,0 +1,139 @@
+//! The in-memory storage engine.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Bound;
+use std::sync::{Arc, RwLock};
+
+use super::{DBOp, KeyValue, KeyValueDB, WriteBatch};
+
+/// A database kept in memory, with a sorted map per column. All data is lost when dropped.
+pub struct MemoryDB {
+    // Columns are copied on write while shared with snapshots.
+    cols: RwLock<Vec<Arc<BTreeMap<Vec<u8>, Vec<u8>>>>>,
+    read_only: bool,
+}
+
+impl MemoryDB {
+    pub fn new(num_of_columns: usize) -> Self {
+        MemoryDB {
+            cols: RwLock::new(vec![Arc::new(BTreeMap::new()); num_of_columns]),
+            read_only: false,
+        }
+    }
+}
+
+impl KeyValueDB for MemoryDB {
+    fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.cols.read().unwrap()[col].get(key).cloned())
+    }
+
+    fn write(&self, batch: &WriteBatch) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "snapshot is read-only"));
+        }
+        let mut cols = self.cols.write().unwrap();
+        for op in &batch.ops {
+            match *op {
+                DBOp::Put {
+                    col,
+                    ref key,
+                    ref value,
+                } => {
+                    Arc::make_mut(&mut cols[col]).insert(key.clone(), value.clone());
+                }
+                DBOp::Delete { col, ref key } => {
+                    Arc::make_mut(&mut cols[col]).remove(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn iter_from<'a>(&'a self, col: usize, lower_bound: &[u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        Box::new(MemoryDBIterator {
+            db: self,
+            col,
+            next_key: Bound::Included(lower_bound.to_owned()),
+        })
+    }
+
+    fn snapshot(&self) -> Box<dyn KeyValueDB> {
+        Box::new(MemoryDB {
+            cols: RwLock::new(self.cols.read().unwrap().clone()),
+            read_only: true,
+        })
+    }
+}
+
+/// Reads an entry at a time, so the lock is not held between items.
+struct MemoryDBIterator<'a> {
+    db: &'a MemoryDB,
+    col: usize,
+    next_key: Bound<Vec<u8>>,
+}
+
+impl Iterator for MemoryDBIterator<'_> {
+    type Item = KeyValue;
+
+    fn next(&mut self) -> Option<KeyValue> {
+        let cols = self.db.cols.read().unwrap();
+        let lower_bound = match self.next_key {
+            Bound::Included(ref key) => Bound::Included(&key[..]),
+            Bound::Excluded(ref key) => Bound::Excluded(&key[..]),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let (key, value) = cols[self.col]
+            .range::<[u8], _>((lower_bound, Bound::Unbounded))
+            .next()?;
+        self.next_key = Bound::Excluded(key.clone());
+        Some((key.clone().into_boxed_slice(), value.clone().into_boxed_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_db() {
+        let db = MemoryDB::new(2);
+        let mut wb = WriteBatch::new();
+        wb.put(0, b"ab", b"1");
+        wb.put(0, b"b", b"2");
+        wb.put(0, b"aa", b"3");
+        wb.put(1, b"ab", b"4");
+        wb.delete(0, b"b");
+        db.write(&wb).unwrap();
+
+        assert_eq!(db.get(0, b"ab").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(0, b"b").unwrap(), None);
+        assert_eq!(db.get(1, b"ab").unwrap(), Some(b"4".to_vec()));
+
+        let keys = db.iter(0).map(|(key, _)| key.into_vec()).collect::<Vec<_>>();
+        assert_eq!(keys, vec![b"aa".to_vec(), b"ab".to_vec()]);
+        assert_eq!(db.iter_from(0, b"ab").count(), 1);
+        assert_eq!(db.iter_with_prefix(0, b"a").count(), 2);
+        assert_eq!(db.get_by_prefix(1, b"a").as_deref(), Some(&b"4"[..]));
+        assert!(db.get_by_prefix(1, b"b").is_none());
+    }
+
+    #[test]
+    fn test_memory_db_snapshot() {
+        let db = MemoryDB::new(1);
+        let mut wb = WriteBatch::new();
+        wb.put(0, b"a", b"1");
+        db.write(&wb).unwrap();
+
+        let snapshot = db.snapshot();
+        let mut wb = WriteBatch::new();
+        wb.put(0, b"a", b"2");
+        wb.put(0, b"b", b"3");
+        db.write(&wb).unwrap();
+
+        assert_eq!(db.get(0, b"a").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(snapshot.get(0, b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(snapshot.iter(0).count(), 1);
+        assert!(snapshot.write(&wb).is_err());
+    }
+}
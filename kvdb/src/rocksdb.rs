@@ -0,0 +1,196 @@
+//! The RocksDB storage engine.
+
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+use log::info;
+use rocks::prelude::{ColumnFamily, ColumnFamilyDescriptor, DBOptions, FlushOptions, ReadOptions, WriteOptions, DB};
+use rocks::snapshot::Snapshot;
+
+use super::{DBOp, KeyValue, KeyValueDB, WriteBatch};
+
+struct Inner {
+    db: DB,
+    cols: Vec<ColumnFamily>,
+}
+
+impl Inner {
+    fn get(&self, ropts: &ReadOptions, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        match self.db.get_cf(ropts, &self.cols[col], key) {
+            Ok(val) => Ok(Some(val.to_vec())),
+            Err(e) if e.is_not_found() => Ok(None),
+            Err(e) => Err(other_error(e)),
+        }
+    }
+
+    fn iter_from<'a>(
+        &'a self,
+        ropts: &ReadOptions,
+        col: usize,
+        lower_bound: &[u8],
+    ) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        // NOTE: Seek instead of `iterate_lower_bound`, which requires the bound slice to outlive the iterator.
+        let mut it = self.db.new_iterator_cf(ropts, &self.cols[col]);
+        it.seek(lower_bound);
+        Box::new(it.map(|(key, value)| (key.to_vec().into_boxed_slice(), value.to_vec().into_boxed_slice())))
+    }
+}
+
+/// A RocksDB database, columns are column families in the order of opening.
+pub struct RocksDB {
+    inner: Arc<Inner>,
+}
+
+impl RocksDB {
+    pub fn open<P: AsRef<Path>>(
+        db_options: &DBOptions,
+        db_path: P,
+        column_families: Vec<ColumnFamilyDescriptor>,
+    ) -> io::Result<RocksDB> {
+        let (db, cols) = DB::open_with_column_families(db_options, db_path, column_families).map_err(other_error)?;
+        Ok(RocksDB {
+            inner: Arc::new(Inner { db, cols }),
+        })
+    }
+
+    /// Open as a secondary instance, which follows the primary by `try_catch_up_with_primary`.
+    pub fn open_as_secondary<P1: AsRef<Path>, P2: AsRef<Path>>(
+        db_options: &DBOptions,
+        db_path: P1,
+        tmp_path: P2,
+        column_families: Vec<ColumnFamilyDescriptor>,
+    ) -> io::Result<RocksDB> {
+        let (db, cols) = DB::open_as_secondary_with_column_families(db_options, db_path, tmp_path, column_families)
+            .map_err(other_error)?;
+        Ok(RocksDB {
+            inner: Arc::new(Inner { db, cols }),
+        })
+    }
+}
+
+impl KeyValueDB for RocksDB {
+    fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.inner.get(ReadOptions::default_instance(), col, key)
+    }
+
+    fn write(&self, batch: &WriteBatch) -> io::Result<()> {
+        let mut wb = rocks::prelude::WriteBatch::new();
+        for op in &batch.ops {
+            match *op {
+                DBOp::Put {
+                    col,
+                    ref key,
+                    ref value,
+                } => {
+                    wb.put_cf(&self.inner.cols[col], key, value);
+                }
+                DBOp::Delete { col, ref key } => {
+                    wb.delete_cf(&self.inner.cols[col], key);
+                }
+            }
+        }
+        self.inner
+            .db
+            .write(WriteOptions::default_instance(), &wb)
+            .map_err(other_error)
+    }
+
+    fn iter_from<'a>(&'a self, col: usize, lower_bound: &[u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        self.inner.iter_from(ReadOptions::default_instance(), col, lower_bound)
+    }
+
+    fn snapshot(&self) -> Box<dyn KeyValueDB> {
+        // NOTE: The snapshot borrows the db, which is kept alive by `inner` until the snapshot is released.
+        let snapshot = self
+            .inner
+            .db
+            .get_snapshot()
+            .map(|snapshot| unsafe { mem::transmute::<Snapshot<'_>, Snapshot<'static>>(snapshot) });
+        Box::new(RocksDBSnapshot {
+            guard: Arc::new(SnapshotGuard {
+                snapshot,
+                inner: self.inner.clone(),
+            }),
+        })
+    }
+
+    fn try_catch_up_with_primary(&self) -> io::Result<()> {
+        self.inner.db.try_catch_up_with_primary().map_err(other_error)
+    }
+
+    fn get_int_property(&self, name: &str) -> Option<u64> {
+        self.inner.db.get_int_property(name)
+    }
+
+    fn get_column_int_property(&self, col: usize, name: &str) -> Option<u64> {
+        self.inner.cols[col].get_int_property(name)
+    }
+
+    fn compact(&self, col: usize) -> io::Result<()> {
+        self.inner.cols[col]
+            .compact_range(&Default::default(), ..)
+            .map_err(other_error)
+    }
+
+    unsafe fn prepare_close(&self) {
+        let db = &self.inner.db;
+        info!("flush db ... {:?}", db.flush(&FlushOptions::default()));
+        db.cancel_background_work(/* wait: */ true);
+        info!("cancel background work");
+        info!("syncing WAL ... {:?}", db.sync_wal());
+    }
+}
+
+struct SnapshotGuard {
+    snapshot: Option<Snapshot<'static>>,
+    inner: Arc<Inner>,
+}
+
+// Snapshots are immutable, and can be shared between threads.
+unsafe impl Send for SnapshotGuard {}
+unsafe impl Sync for SnapshotGuard {}
+
+impl Drop for SnapshotGuard {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.inner.db.release_snapshot(snapshot);
+        }
+    }
+}
+
+/// A consistent read-only view of a `RocksDB`, as of the time it's taken.
+pub struct RocksDBSnapshot {
+    guard: Arc<SnapshotGuard>,
+}
+
+impl RocksDBSnapshot {
+    fn read_options(&self) -> ReadOptions {
+        ReadOptions::default().snapshot(self.guard.snapshot.as_ref())
+    }
+}
+
+impl KeyValueDB for RocksDBSnapshot {
+    fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.guard.inner.get(&self.read_options(), col, key)
+    }
+
+    fn write(&self, _batch: &WriteBatch) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "snapshot is read-only"))
+    }
+
+    fn iter_from<'a>(&'a self, col: usize, lower_bound: &[u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
+        self.guard.inner.iter_from(&self.read_options(), col, lower_bound)
+    }
+
+    fn snapshot(&self) -> Box<dyn KeyValueDB> {
+        Box::new(RocksDBSnapshot {
+            guard: self.guard.clone(),
+        })
+    }
+}
+
+fn other_error<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
@@ -13,6 +13,7 @@ use tokio::prelude::*;
 use tokio_util::codec::{Decoder, Encoder, Framed, FramedRead, FramedWrite};
 
 /// Channel message variations.
+#[derive(Clone)]
 pub enum ChannelMessage {
     Block(Block),
     Transactions(Transactions),
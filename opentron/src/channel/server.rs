@@ -1,5 +1,5 @@
 use super::protocol::{ChannelMessage, ChannelMessageCodec};
-use chain::IndexedBlock;
+use chain::{IndexedBlock, IndexedTransaction};
 use chrono::Utc;
 use futures::channel::oneshot;
 use futures::future::FutureExt;
@@ -10,6 +10,7 @@ use futures::stream::Stream;
 use keys::b58encode_check;
 use log::{debug, error, info, warn};
 use primitive_types::H256;
+use proto2::chain::Transaction;
 use proto2::channel::{
     BlockInventory, ChainInventory, HandshakeDisconnect, HandshakeHello, Inventory, ReasonCode as DisconnectReasonCode,
     Transactions,
@@ -342,6 +343,8 @@ async fn sync_channel_handler(
     let mut syncing_block_ids: Vec<Vec<u8>> = vec![];
     let mut pinged = false;
     let (mut tx, mut rx) = mpsc::channel::<ChannelMessage>(1000);
+    let mut import_queue = ctx.block_import_queue.clone();
+    let mut gossip = ctx.gossip.subscribe();
 
     loop {
         let mut next_packet = reader.next().fuse();
@@ -389,26 +392,51 @@ async fn sync_channel_handler(
                         debug!("pong");
                     },
                     Ok(ChannelMessage::TransactionInventory(inv)) => {
-                        let Inventory { mut ids, r#type } = inv;
-                        for id in &ids {
-                            debug!("transaction inventory, txn_id={}", hex::encode(id));
+                        if ctx.importing.load(Ordering::Relaxed) {
+                            continue;
                         }
-                        /*
-                        if ids.len() == 1 {
-                            // ids[0][0] = b'A';
-                            let fake_inv = Inventory { ids, r#type };
-                            info!("request inv");
-                            // writer.send(ChannelMessage::TransactionInventory(fake_inv)).await?;
-                            writer.send(ChannelMessage::FetchBlockInventory(fake_inv)).await?;
+                        let Inventory { ids, r#type } = inv;
+                        let ids: Vec<_> = {
+                            let mempool = ctx.mempool.read().unwrap();
+                            ids.into_iter()
+                                .filter(|txn_id| {
+                                    debug!("transaction inventory, txn_id={}", hex::encode(txn_id));
+                                    txn_id.len() == 32 && !mempool.contains(&H256::from_slice(txn_id))
+                                })
+                                .collect()
+                        };
+                        if !ids.is_empty() {
+                            writer
+                                .send(ChannelMessage::FetchTransactionInventory(Inventory { ids, r#type }))
+                                .await?;
                         }
-                        */
                     }
-                    Ok(ChannelMessage::FetchTransactionInventory(inv)) => {
-                        info!("fetch transactions {:?}", inv);
+                    Ok(ChannelMessage::FetchTransactionInventory(Inventory { ids, .. })) => {
+                        let transactions: Vec<_> = {
+                            let mempool = ctx.mempool.read().unwrap();
+                            ids.iter()
+                                .filter(|txn_id| txn_id.len() == 32)
+                                .filter_map(|txn_id| mempool.get(&H256::from_slice(txn_id)))
+                                .map(|txn| txn.raw.clone())
+                                .collect()
+                        };
+                        debug!("fetch transactions, len={}, found={}", ids.len(), transactions.len());
+                        if !transactions.is_empty() {
+                            writer.send(ChannelMessage::Transactions(Transactions { transactions })).await?;
+                        }
                     }
                     Ok(ChannelMessage::Transactions(Transactions { transactions })) => {
-                        for txn in &transactions {
-                            info!("got txn {:?}", txn);
+                        if ctx.importing.load(Ordering::Relaxed) {
+                            debug!("ignore {} transactions while importing blocks", transactions.len());
+                            continue;
+                        }
+                        let ids = tokio::task::block_in_place(|| add_transactions_to_pool(&ctx, transactions));
+                        if !ids.is_empty() {
+                            let inv = Inventory {
+                                r#type: 0, // TRX
+                                ids,
+                            };
+                            let _ = ctx.gossip.send(ChannelMessage::TransactionInventory(inv));
                         }
                     }
                     Ok(ChannelMessage::BlockInventory(inv)) => {
@@ -496,6 +524,10 @@ async fn sync_channel_handler(
                             } else {
                                 warn!("block exists in db");
                             }
+                            // NOTE: Waits when the import queue is full, which pauses reading from this peer.
+                            if import_queue.send(block.clone()).await.is_err() {
+                                warn!("block import queue closed");
+                            }
                         }
                         if syncing {
                             if block.number() == last_block_number {
@@ -595,6 +627,13 @@ async fn sync_channel_handler(
                     writer.send(msg).await?;
                 }
             }
+            gossip_msg = gossip.recv().fuse() => {
+                match gossip_msg {
+                    Ok(msg) => writer.send(msg).await?,
+                    Err(broadcast::RecvError::Lagged(n)) => warn!("gossip lagged, {} messages skipped", n),
+                    Err(broadcast::RecvError::Closed) => {},
+                }
+            }
         }
     }
 
@@ -602,3 +641,27 @@ async fn sync_channel_handler(
 
     Ok(())
 }
+
+/// Validate and add transactions to the transaction pool, returns ids of newly added ones.
+fn add_transactions_to_pool(ctx: &AppContext, transactions: Vec<Transaction>) -> Vec<Vec<u8>> {
+    let mut manager = ctx.manager.write().unwrap();
+    let mut mempool = ctx.mempool.write().unwrap();
+
+    let mut added = vec![];
+    for txn in transactions {
+        let txn = match IndexedTransaction::from_raw(txn) {
+            Some(txn) => txn,
+            None => {
+                warn!("malformed transaction");
+                continue;
+            }
+        };
+        let txn_id = txn.hash;
+        match mempool.add(&mut manager, txn) {
+            Ok(true) => added.push(txn_id.as_bytes().to_vec()),
+            Ok(false) => {}
+            Err(e) => debug!("reject transaction {:?}: {}", txn_id, e),
+        }
+    }
+    added
+}
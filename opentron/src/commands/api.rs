@@ -0,0 +1,104 @@
+//! API node, serving GraphQL from the dbs of a running node on the same host.
+
+use std::error::Error;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use futures::future::FutureExt;
+use futures::join;
+use futures::select;
+use log::{debug, info, warn};
+use slog::o;
+use slog_scope_futures::FutureExt as SlogFutureExt;
+use tokio::sync::broadcast;
+use tokio::time::{delay_for, Duration};
+
+use crate::context::AppContext;
+use crate::graphql::server::graphql_server;
+
+/// Interval of catching up with the running node.
+const CATCH_UP_INTERVAL: Duration = Duration::from_secs(1);
+
+pub async fn main(ctx: AppContext) -> Result<(), Box<dyn Error>> {
+    let ctx = Arc::new(ctx);
+    info!("api node started, following solid block #{}", ctx.manager.read().unwrap().latest_block_number());
+
+    let (done, _) = broadcast::channel::<()>(1);
+    let termination_handler = {
+        let ctx = ctx.clone();
+        let done = done.clone();
+        move || {
+            ctx.running.store(false, Ordering::SeqCst);
+            let _ = done.send(());
+        }
+    };
+
+    let f = Mutex::new(Some(termination_handler));
+    ctrlc::set_handler(move || {
+        eprintln!("\nCtrl-C pressed...");
+        if let Ok(mut guard) = f.lock() {
+            let f = guard.take().expect("f can only be taken once");
+            f();
+        }
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    let graphql_service = {
+        let ctx = ctx.clone();
+        let done_signal = done.subscribe();
+        let logger = slog_scope::logger().new(o!("service" => "graphql"));
+        graphql_server(ctx, done_signal).with_logger(logger)
+    };
+
+    let catch_up_service = {
+        let ctx = ctx.clone();
+        let done_signal = done.subscribe();
+        let logger = slog_scope::logger().new(o!("service" => "catch-up"));
+        catch_up_server(ctx, done_signal).with_logger(logger)
+    };
+
+    let _ = join!(graphql_service, catch_up_service);
+
+    Ok(())
+}
+
+async fn catch_up_server(ctx: Arc<AppContext>, mut signal: broadcast::Receiver<()>) {
+    loop {
+        select! {
+            _ = signal.recv().fuse() => {
+                warn!("catch-up service closed");
+                break;
+            }
+            _ = delay_for(CATCH_UP_INTERVAL).fuse() => {}
+        }
+
+        if !ctx.running.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if let Err(e) = tokio::task::block_in_place(|| catch_up(&ctx)) {
+            warn!("catch up with primary failed: {}", e);
+        }
+    }
+}
+
+/// Catch up with the running node, and switch the manager to the new solid state.
+fn catch_up(ctx: &AppContext) -> Result<(), Box<dyn Error>> {
+    let solid_state_db = ctx.solid_state_db.as_ref().ok_or("not an API node")?;
+
+    ctx.chain_db.catch_up_with_primary()?;
+    solid_state_db.catch_up_with_primary()?;
+
+    let state_db = solid_state_db.snapshot();
+    let mut manager = ctx.manager.write().unwrap();
+    let old_block_number = manager.latest_block_number();
+    manager.set_state_db(state_db);
+
+    let block_number = manager.latest_block_number();
+    if block_number != old_block_number {
+        let ref_block_hashes = ctx.chain_db.ref_block_hashes_of_block_num(block_number);
+        manager.init_ref_blocks(ref_block_hashes);
+        debug!("caught up with solid block #{}", block_number);
+    }
+    Ok(())
+}
@@ -4,6 +4,7 @@ use clap::ArgMatches;
 use log::info;
 
 use crate::context::AppContext;
+use crate::manager::invariant::StateChecker;
 
 pub async fn main(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
     let ref db = ctx.chain_db;
@@ -24,6 +25,18 @@ pub async fn main(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<d
                 db.handle_chain_fork_at(pos, /* dry_run */ false)?;
             }
         }
+        Some("state") => {
+            let ref manager = ctx.manager.read().unwrap();
+            info!("check state at block #{}", manager.latest_block_number());
+            let violations = StateChecker::new(manager).check();
+            for violation in &violations {
+                println!("{}", violation);
+            }
+            if !violations.is_empty() {
+                return Err(format!("found {} state invariant violations", violations.len()).into());
+            }
+            info!("state check passed");
+        }
         _ => (),
     }
 
@@ -0,0 +1,43 @@
+//! Migrate the state-db schema, without starting the node.
+
+use std::error::Error;
+use std::path::Path;
+
+use clap::ArgMatches;
+use config::Config;
+use log::info;
+use state::db::StateDB;
+use state::migration::CURRENT_DB_VERSION;
+
+/// Runs before `AppContext` is created, since opening the manager already migrates the state-db.
+pub async fn main<P: AsRef<Path>>(config_file: P, matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
+    let config = Config::load_from_file(config_file)?;
+    let dry_run = matches.is_present("dry-run");
+
+    let mut state_db = StateDB::open(&config.storage.engine, &config.storage.state_data_dir)?;
+    match state_db.db_version()? {
+        Some(version) => info!("state-db schema version {}, current version {}", version, CURRENT_DB_VERSION),
+        None => {
+            info!("state-db is empty, nothing to migrate");
+            return Ok(());
+        }
+    }
+
+    let all_stats = state_db.migrate(&config.chain, dry_run)?;
+    if all_stats.is_empty() {
+        info!("state-db schema is up to date");
+    }
+    for stats in all_stats {
+        info!(
+            "{} version {} => {}, visited={} rewritten={} deleted={} inserted={}",
+            if dry_run { "would migrate" } else { "migrated" },
+            stats.from_version,
+            stats.from_version + 1,
+            stats.visited,
+            stats.rewritten,
+            stats.deleted,
+            stats.inserted
+        );
+    }
+    Ok(())
+}
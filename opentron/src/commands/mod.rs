@@ -1,3 +1,7 @@
+pub mod api;
 pub mod check;
 pub mod dev;
 pub mod fix;
+pub mod migrate;
+pub mod replay;
+pub mod snapshot;
@@ -0,0 +1,84 @@
+use std::time::Instant;
+
+use clap::ArgMatches;
+use log::{error, info};
+
+use crate::context::AppContext;
+use crate::manager::executor::ResultMismatch;
+
+const REPORT_INTERVAL: i64 = 10_000;
+
+pub async fn main(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let ref mut manager = ctx.manager.write().unwrap();
+    let state_height = manager.latest_block_number();
+
+    // NOTE: state-db only keeps the latest state, replay always resumes from the last good height.
+    let from = match matches.value_of("from") {
+        Some(val) => val.parse()?,
+        None => state_height + 1,
+    };
+    if from != state_height + 1 {
+        return Err(format!(
+            "state-db is at block #{}, replay can only start from block #{}",
+            state_height,
+            state_height + 1
+        )
+        .into());
+    }
+    let to = match matches.value_of("to") {
+        Some(val) => val.parse()?,
+        None => ctx.chain_db.get_block_height(),
+    };
+    let strict = matches.is_present("strict");
+
+    info!("replaying block #{} to #{}, strict={}", from, to, strict);
+    manager.set_strict_result_check(strict);
+
+    let started_at = Instant::now();
+    for num in from..=to {
+        let block = ctx.chain_db.get_block_by_number(num as u64)?;
+        let result = match manager.push_block(&block) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(format!("block #{} is not applied", num).into()),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            // Keep all good blocks, so the next replay resumes right before the bad one.
+            manager.solidify_all_blocks()?;
+            if let Some(mismatch) = manager.take_result_mismatch() {
+                print_report(&mismatch);
+            }
+            error!("replay halted at block #{}: {}", num, e);
+            info!("state-db is at block #{}, run replay again to resume", manager.latest_block_number());
+            return Err(e);
+        }
+
+        if num % REPORT_INTERVAL == 0 {
+            info!("replayed to block #{}, elapsed={:?}", num, started_at.elapsed());
+        }
+    }
+
+    manager.solidify_all_blocks()?;
+    info!(
+        "replayed {} blocks, state-db is at block #{}, elapsed={:?}",
+        to - from + 1,
+        manager.latest_block_number(),
+        started_at.elapsed()
+    );
+    Ok(())
+}
+
+fn print_report(mismatch: &ResultMismatch) {
+    println!("==== transaction result mismatch ====");
+    println!("block:         #{}", mismatch.block_number);
+    println!("transaction:   {:?}", mismatch.transaction_hash);
+    println!("contract type: {:?}", mismatch.contract_type);
+    println!("expected:      {:?}", mismatch.expected);
+    println!("actual:        {:?}", mismatch.actual);
+    println!("energy:        {} (fee={})", mismatch.energy, mismatch.energy_fee);
+    println!("touched keys:");
+    for (col, key) in &mismatch.touched_keys {
+        println!("  col={:<2} key={}", col, hex::encode(key));
+    }
+}
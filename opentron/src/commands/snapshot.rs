@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::ArgMatches;
+use log::info;
+use state::snapshot::SnapshotReader;
+
+use crate::context::AppContext;
+
+pub async fn main(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        ("export", Some(arg_matches)) => export(ctx, arg_matches),
+        ("import", Some(arg_matches)) => import(ctx, arg_matches),
+        _ => Err("missing subcommand, use `snapshot export` or `snapshot import`".into()),
+    }
+}
+
+fn export(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let ref manager = ctx.manager.read().unwrap();
+    let height = manager.latest_block_number();
+
+    // NOTE: state-db only keeps the latest solidified state, which is what gets exported.
+
+    let path = matches
+        .value_of("output")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| format!("./snapshot-{}.bin", height).into());
+    info!("exporting state-db at block #{} to {:?}", height, path);
+
+    let started_at = Instant::now();
+    let writer = BufWriter::new(File::create(&path)?);
+    let num_of_entries = manager.state().export_snapshot(writer)?;
+    info!(
+        "exported {} entries, block #{}, elapsed={:?}",
+        num_of_entries,
+        height,
+        started_at.elapsed()
+    );
+    Ok(())
+}
+
+fn import(ctx: AppContext, matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = matches.value_of("FILE").expect("required in cli.yml; qed");
+    let ref mut manager = ctx.manager.write().unwrap();
+
+    if manager.latest_block_number() != 0 {
+        return Err(format!(
+            "state-db is already at block #{}, remove state-data-dir before importing",
+            manager.latest_block_number()
+        )
+        .into());
+    }
+
+    // . check consistency with chain-db before touching state-db
+    let header = SnapshotReader::new(BufReader::new(File::open(path)?))?.header().clone();
+    let block_header = ctx
+        .chain_db
+        .get_block_header_by_number(header.block_number)
+        .map_err(|_| {
+            format!("block #{} not found in chain-db, sync chain-db to the snapshot height first", header.block_number)
+        })?;
+    if block_header.hash != header.block_hash {
+        return Err(format!(
+            "snapshot is inconsistent with chain-db at block #{}, snapshot={:?}, chain-db={:?}",
+            header.block_number, header.block_hash, block_header.hash
+        )
+        .into());
+    }
+
+    info!("importing state-db snapshot of block #{} from {:?}", header.block_number, path);
+    let started_at = Instant::now();
+    let reader = BufReader::new(File::open(path)?);
+    let header = manager.state_mut().import_snapshot(reader)?;
+    info!(
+        "imported state-db, block #{} {:?}, elapsed={:?}",
+        header.block_number,
+        header.block_hash,
+        started_at.elapsed()
+    );
+    Ok(())
+}
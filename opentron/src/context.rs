@@ -2,8 +2,9 @@ use std::collections::HashSet;
 use std::error::Error;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU32};
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
 
+use chain::IndexedBlock;
 use chain_db::ChainDB;
 use config::genesis::GenesisConfig;
 use config::Config;
@@ -11,8 +12,13 @@ use futures::channel::oneshot;
 use log::info;
 use primitive_types::H256;
 use proto2::common::BlockId;
+use state::db::ReadOnlySolidStateDB;
+use tokio::sync::{broadcast, mpsc};
 
+use crate::channel::protocol::ChannelMessage;
+use crate::importer::server::IMPORT_QUEUE_SIZE;
 use crate::manager::Manager;
+use crate::mempool::TransactionPool;
 use crate::util::get_my_ip;
 
 pub struct AppContext {
@@ -24,11 +30,22 @@ pub struct AppContext {
     pub chain_db: ChainDB,
     pub running: AtomicBool,
     pub syncing: AtomicBool,
+    /// State-db is behind chain-db, blocks are being imported by the block import service.
+    pub importing: AtomicBool,
     pub num_active_connections: AtomicU32,
     pub num_passive_connections: AtomicU32,
     pub recent_blk_ids: RwLock<HashSet<H256>>,
     pub peers: RwLock<Vec<oneshot::Sender<()>>>,
     pub manager: RwLock<Manager>,
+    /// State-db followed by API nodes, `None` for full nodes.
+    pub solid_state_db: Option<ReadOnlySolidStateDB>,
+    /// Blocks received from channel, to be imported into the manager.
+    pub block_import_queue: mpsc::Sender<IndexedBlock>,
+    /// Taken by the block import service.
+    pub block_import_receiver: Mutex<Option<mpsc::Receiver<IndexedBlock>>>,
+    pub mempool: RwLock<TransactionPool>,
+    /// Messages to be relayed to all connected peers.
+    pub gossip: broadcast::Sender<ChannelMessage>,
 }
 
 impl AppContext {
@@ -40,7 +57,7 @@ impl AppContext {
         let genesis_config = GenesisConfig::load_from_file(&genesis_path)?;
         let genesis_blk = genesis_config.to_indexed_block()?;
 
-        let chain_db = ChainDB::new(&config.storage.data_dir);
+        let chain_db = ChainDB::open(&config.storage.engine, &config.storage.data_dir)?;
         if !chain_db.has_block(&genesis_blk) {
             if let Ok(_) = chain_db.get_genesis_block() {
                 panic!("genesis block config is inconsistent with chain-db");
@@ -68,6 +85,9 @@ impl AppContext {
         let ref_block_hashes = chain_db.ref_block_hashes_of_block_num(db_manager.latest_block_number());
         db_manager.init_ref_blocks(ref_block_hashes);
 
+        let (block_import_queue, block_import_receiver) = mpsc::channel(IMPORT_QUEUE_SIZE);
+        let (gossip, _) = broadcast::channel(1_000);
+
         Ok(AppContext {
             chain_db,
             config,
@@ -77,11 +97,86 @@ impl AppContext {
             genesis_block_id: Some(genesis_block_id),
             running: AtomicBool::new(true),
             syncing: AtomicBool::new(false),
+            importing: AtomicBool::new(false),
+            num_active_connections: AtomicU32::new(0),
+            num_passive_connections: AtomicU32::new(0),
+            recent_blk_ids: RwLock::new(HashSet::new()),
+            peers: RwLock::default(),
+            manager: RwLock::new(db_manager),
+            solid_state_db: None,
+            block_import_queue,
+            block_import_receiver: Mutex::new(Some(block_import_receiver)),
+            mempool: RwLock::new(TransactionPool::new()),
+            gossip,
+        })
+    }
+
+    /// Context of an API node, which opens the dbs of a running node as secondary instances.
+    ///
+    /// `secondary_dir` keeps info logs of the secondary instances, and must not be shared between API nodes.
+    pub fn from_config_as_secondary<P1: AsRef<Path>, P2: AsRef<Path>>(
+        path: P1,
+        secondary_dir: P2,
+    ) -> Result<Self, Box<dyn Error>> {
+        let config = Config::load_from_file(&path)?;
+
+        let genesis_path = path.as_ref().parent().unwrap().join(&config.chain.genesis);
+
+        let genesis_config = GenesisConfig::load_from_file(&genesis_path)?;
+        let genesis_blk = genesis_config.to_indexed_block()?;
+
+        if config.storage.engine != "rocksdb" {
+            return Err("API nodes require the rocksdb storage engine".into());
+        }
+
+        let chain_db = ChainDB::new_secondary(&config.storage.data_dir, secondary_dir.as_ref().join("chaindb"));
+        if !chain_db.has_block(&genesis_blk) {
+            return Err("genesis block not found in chain-db, is the node initialized?".into());
+        }
+        chain_db.report_status();
+
+        let genesis_block_id = BlockId {
+            number: 0,
+            hash: genesis_blk.header.hash.as_ref().to_owned(),
+        };
+
+        let node_id = chain_db.get_node_id();
+        info!("genesis block id => {}", hex::encode(&genesis_block_id.hash));
+        info!("chain-db loaded as secondary");
+
+        let solid_state_db =
+            ReadOnlySolidStateDB::new(&config.storage.state_data_dir, secondary_dir.as_ref().join("statedb"));
+        // The running node migrates the state-db on start, an API node can only follow the same schema.
+        solid_state_db.snapshot().check_db_version()?;
+        let mut db_manager = Manager::with_state_db(solid_state_db.snapshot(), &config, &genesis_config);
+        let ref_block_hashes = chain_db.ref_block_hashes_of_block_num(db_manager.latest_block_number());
+        db_manager.init_ref_blocks(ref_block_hashes);
+        info!("state-db loaded as secondary, solid block #{}", db_manager.latest_block_number());
+
+        // Never consumed, API nodes neither import blocks nor relay messages.
+        let (block_import_queue, _) = mpsc::channel(1);
+        let (gossip, _) = broadcast::channel(1);
+
+        Ok(AppContext {
+            chain_db,
+            config,
+            genesis_config,
+            node_id,
+            outbound_ip: "127.0.0.1".into(),
+            genesis_block_id: Some(genesis_block_id),
+            running: AtomicBool::new(true),
+            syncing: AtomicBool::new(false),
+            importing: AtomicBool::new(false),
             num_active_connections: AtomicU32::new(0),
             num_passive_connections: AtomicU32::new(0),
             recent_blk_ids: RwLock::new(HashSet::new()),
             peers: RwLock::default(),
             manager: RwLock::new(db_manager),
+            solid_state_db: Some(solid_state_db),
+            block_import_queue,
+            block_import_receiver: Mutex::new(None),
+            mempool: RwLock::new(TransactionPool::new()),
+            gossip,
         })
     }
 }
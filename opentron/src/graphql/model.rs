@@ -8,6 +8,8 @@ pub struct NodeInfo {
     pub code_version: &'static str,
     /// Is node syncing.
     pub syncing: bool,
+    /// Is node importing blocks, i.e. state is behind the chain.
+    pub importing: bool,
     /// Number of active(outgoing) connections.
     pub num_active_connections: u32,
     /// Number of passive(incoming) connections.
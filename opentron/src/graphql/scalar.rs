@@ -1,5 +1,5 @@
 use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
-use primitive_types::H256;
+use primitive_types::{H256, U256};
 
 /// Bytes32 is a 32 byte binary string, represented as 0x-prefixed hexadecimal.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -90,10 +90,36 @@ impl ScalarType for Bytes {
     }
 }
 
-// /// BigInt is a large integer. Input is accepted as either a JSON number or as a string.
-// /// Strings may be either decimal or 0x-prefixed hexadecimal. Output values are all
-// /// 0x-prefixed hexadecimal.
-// pub struct BigInt(BigInt);
+/// BigInt is a large integer. Input is accepted as either a JSON number or as a string.
+/// Strings may be either decimal or 0x-prefixed hexadecimal. Output values are all
+/// 0x-prefixed hexadecimal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BigInt(pub U256);
+
+#[Scalar]
+impl ScalarType for BigInt {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(value) => {
+                if value.starts_with("0x") {
+                    Ok(value[2..].parse::<U256>().map(BigInt)?)
+                } else {
+                    Ok(U256::from_dec_str(&value).map(BigInt)?)
+                }
+            }
+            Value::Number(value) => value
+                .as_u64()
+                .map(U256::from)
+                .map(BigInt)
+                .ok_or(InputValueError::custom("invalid number type")),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("{:#x}", self.0))
+    }
+}
 
 /// Long is a 64 bit unsigned integer.
 #[derive(Clone, Copy, Debug, PartialEq)]
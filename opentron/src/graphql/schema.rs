@@ -1,38 +1,44 @@
 use std::convert::TryFrom;
 use std::str;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use ::state::keys;
-use async_graphql::{Context, Enum, Error, InputObject, Object, Result, SimpleObject};
+use async_graphql::{Context, Enum, Error, ErrorExtensions, InputObject, Object, Result, SimpleObject};
 use byteorder::{ByteOrder, BE};
 use chain::{IndexedBlockHeader, IndexedTransaction};
 use chrono::{DateTime, TimeZone, Utc};
 use primitive_types::H256;
+use proto2::contract::TriggerSmartContract;
 use proto2::state;
 use std::mem;
 
 use super::contract::{AccountType, Contract};
 use super::model::NodeInfo;
-use super::scalar::{Address, Bytes, Bytes32, Long};
+use super::scalar::{Address, BigInt, Bytes, Bytes32, Long};
 use crate::context::AppContext;
+use crate::manager::executor::{estimate_energy, EnergyEstimate, ExecutionError};
+use crate::manager::tracer::{self, CallTracer, OpcodeTraceOptions, OpcodeTracer, PrestateTracer, SharedTracer};
+use crate::manager::Manager;
 
 const CODE_VERSION: &'static str = "0.1.0";
 const API_VERSION: &'static str = "0.1.0";
 const MAX_NUMBER_OF_BATCH_ITEMS_PER_REQUEST: i64 = 1000;
+/// Default energy limit of calls, also the max energy limit of estimation.
+const MAX_CALL_ENERGY_LIMIT: i64 = 100_000_000;
 
 /// Account is an Tron account.
 pub struct Account {
     address: Address,
+    /// Block number of the state, `None` for the latest state.
+    block: Option<i64>,
     inner: RwLock<Option<state::Account>>,
 }
 
 impl Account {
     fn require_inner(&self, ctx: &Context<'_>) -> Result<()> {
         if self.inner.read().unwrap().is_none() {
-            let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
-            let acct = manager
-                .state()
-                .get(&keys::Account(self.address.0))?
+            let ref manager = manager_snapshot(ctx);
+            let acct = get_state_at(manager, &keys::Account(self.address.0), self.block)?
                 .ok_or_else(|| "account not found")?;
             *self.inner.write().unwrap() = Some(acct);
         }
@@ -40,6 +46,40 @@ impl Account {
     }
 }
 
+/// The state snapshot of a request, taken on first use and shared by all resolvers of the request.
+#[derive(Default)]
+pub struct RequestSnapshot(Mutex<Option<Arc<Manager>>>);
+
+/// The consistent snapshot of the state for the current request.
+///
+/// The manager lock is only held while taking the snapshot, so queries never block the block writer.
+fn manager_snapshot(ctx: &Context<'_>) -> Arc<Manager> {
+    ctx.data_unchecked::<RequestSnapshot>()
+        .0
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap().snapshot()))
+        .clone()
+}
+
+/// A throwaway manager on the request snapshot, for constant calls and dry runs.
+///
+/// All changes made through it are discarded when dropped, and not seen by other resolvers.
+fn throwaway_manager(ctx: &Context<'_>) -> Manager {
+    manager_snapshot(ctx).snapshot()
+}
+
+/// Get state value as of the block, or the latest state value.
+fn get_state_at<T, K: keys::Key<T>>(manager: &Manager, key: &K, block: Option<i64>) -> Result<Option<T>> {
+    match block {
+        Some(block_number) => {
+            let view = manager.state_view_at(block_number)?;
+            Ok(manager.state().get_with_view(key, &view)?)
+        }
+        None => Ok(manager.state().get(key)?),
+    }
+}
+
 #[Object]
 impl Account {
     /// Address is the address owning the account.
@@ -62,13 +102,10 @@ impl Account {
         if inner.as_ref().unwrap().r#type != state::AccountType::Contract as i32 {
             return Ok(Bytes(vec![]));
         }
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
-        manager
-            .state()
-            .get(&keys::ContractCode(self.address.0))
+        let ref manager = manager_snapshot(ctx);
+        get_state_at(manager, &keys::ContractCode(self.address.0), self.block)
             .map(|maybe_code| maybe_code.unwrap_or_default())
             .map(Bytes)
-            .map_err(From::from)
     }
 
     /// Storage provides access to the storage of a contract account, indexed
@@ -79,11 +116,9 @@ impl Account {
         if inner.as_ref().unwrap().r#type != state::AccountType::Contract as i32 {
             return Ok(Bytes32::from(H256::zero()));
         }
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
-        let val = manager
-            .state()
-            .get(&keys::ContractStorage(self.address.0, slot.0))?
-            .unwrap_or_default();
+        let ref manager = manager_snapshot(ctx);
+        let val =
+            get_state_at(manager, &keys::ContractStorage(self.address.0, slot.0), self.block)?.unwrap_or_default();
         Ok(Bytes32(val))
     }
 
@@ -177,7 +212,7 @@ impl Asset {
 
     /// Returns the amount of tokens owned by account.
     async fn balance_of(&self, ctx: &Context<'_>, account: Address) -> Result<Long> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let acct = manager
             .state()
             .get(&keys::Account(account.0))?
@@ -220,6 +255,7 @@ impl Log {
         let address = TryFrom::try_from(&self.inner.address).map(Address).unwrap();
         Account {
             address,
+            block: None,
             inner: RwLock::default(),
         }
     }
@@ -247,6 +283,65 @@ impl Log {
     }
 }
 
+/// InternalTransaction is a CALL, CREATE, SUICIDE or value transfer inside TVM.
+pub struct InternalTransaction(state::InternalTransaction);
+
+#[Object]
+impl InternalTransaction {
+    /// Hash of the internal transaction, derived from the parent transaction hash.
+    async fn hash(&self) -> Bytes32 {
+        Bytes32(H256::from_slice(&self.0.hash))
+    }
+
+    /// Caller is the account which sent the internal transaction.
+    async fn caller(&self) -> Account {
+        Account {
+            address: TryFrom::try_from(&self.0.caller_address).map(Address).unwrap(),
+            block: None,
+            inner: RwLock::default(),
+        }
+    }
+
+    /// To is the account receiving the call, the created contract, or the beneficiary of suicide.
+    async fn to(&self) -> Account {
+        Account {
+            address: TryFrom::try_from(&self.0.to_address).map(Address).unwrap(),
+            block: None,
+            inner: RwLock::default(),
+        }
+    }
+
+    /// Value is the TRX value, in sun, sent along with the internal transaction.
+    async fn value(&self) -> Long {
+        self.0.call_value.into()
+    }
+
+    /// TokenId is the TRC10 token ID.
+    async fn token_id(&self) -> i64 {
+        self.0.call_token_id
+    }
+
+    /// TokenValue is the TRC10 token value.
+    async fn token_value(&self) -> Long {
+        self.0.call_token_value.into()
+    }
+
+    /// Data is the call data.
+    async fn data(&self) -> Bytes {
+        Bytes(self.0.data.clone())
+    }
+
+    /// Note is the kind of internal transaction, one of call, create, suicide.
+    async fn note(&self) -> String {
+        String::from_utf8_lossy(&self.0.note).into_owned()
+    }
+
+    /// Accepted is false when the internal transaction is reverted.
+    async fn accepted(&self) -> bool {
+        self.0.accepted
+    }
+}
+
 #[derive(InputObject)]
 /// FilterCriteria encapsulates log filter criteria for searching log entries.
 struct FilterCriteria {
@@ -361,6 +456,86 @@ pub struct CallData {
     token_id: Option<i64>,
     /// TokenValue is the TRC10 token value.
     token_value: Option<Long>,
+    /// AllowStateChange allows constant methods to change state, the changes are discarded anyway.
+    allow_state_change: Option<bool>,
+}
+
+impl CallData {
+    fn to_trigger_smart_contract(&self) -> Result<TriggerSmartContract> {
+        Ok(TriggerSmartContract {
+            owner_address: self.from.unwrap_or_else(Default::default).0.as_bytes().to_vec(),
+            contract_address: self.to.ok_or("missing contract address")?.0.as_bytes().to_vec(),
+            data: self.data.as_ref().map(|data| data.0.clone()).unwrap_or_default(),
+            call_value: self.value.map(|val| val.0).unwrap_or_default(),
+            call_token_id: self.token_id.unwrap_or_default(),
+            call_token_value: self.token_value.map(|val| val.0).unwrap_or_default(),
+        })
+    }
+}
+
+/// Execute a constant call at the given block's state, or the current block's state.
+///
+/// Runs on a state snapshot, historical states enter the state view of the snapshot.
+fn constant_call(
+    ctx: &Context<'_>,
+    data: CallData,
+    block: Option<Long>,
+    tracer: Option<SharedTracer>,
+) -> Result<state::TransactionReceipt> {
+    use crate::manager::executor::execute_constant_call;
+
+    let trigger = data.to_trigger_smart_contract()?;
+    let energy_limit = data.energy_limit.map(|val| val.0).unwrap_or(MAX_CALL_ENERGY_LIMIT);
+    let allow_state_change = data.allow_state_change.unwrap_or_default();
+
+    let ref mut manager = throwaway_manager(ctx);
+    let receipt = match block {
+        Some(block_number) => manager.with_state_at(block_number.0, |manager| {
+            execute_constant_call(manager, &trigger, energy_limit, allow_state_change, tracer)
+        })?,
+        None => execute_constant_call(manager, &trigger, energy_limit, allow_state_change, tracer),
+    };
+    receipt.map_err(execution_error)
+}
+
+/// Estimate energy of a contract call, the energy limit of `data` is the max energy limit to search.
+fn estimate(ctx: &Context<'_>, data: CallData) -> Result<EnergyEstimate> {
+    let trigger = data.to_trigger_smart_contract()?;
+    let max_energy_limit = data.energy_limit.map(|val| val.0).unwrap_or(MAX_CALL_ENERGY_LIMIT);
+
+    let ref manager = manager_snapshot(ctx);
+    Ok(estimate_energy(manager, &trigger, max_energy_limit).map_err(execution_error)?)
+}
+
+/// Error of a transaction execution, with the error kind as `extensions.code`.
+///
+/// Insufficient balance or resource errors also carry `address`, `required` and `available`.
+fn execution_error(e: ExecutionError) -> Error {
+    e.extend_with(|e, ext| {
+        ext.set("code", e.code());
+        if let Some(shortfall) = e.shortfall() {
+            ext.set("address", shortfall.address.to_string());
+            ext.set("required", shortfall.required.to_string());
+            ext.set("available", shortfall.available.to_string());
+        }
+    })
+}
+
+/// FeeEstimate is the estimated energy and fee of a contract call.
+#[derive(SimpleObject)]
+pub struct FeeEstimate {
+    /// EnergyLimit is the minimum energy limit for the call to succeed.
+    energy_limit: Long,
+    /// EnergyUsed is the amount of energy used by the call.
+    energy_used: Long,
+    /// CallerEnergyUsage is the amount of energy paid by the caller.
+    caller_energy_usage: Long,
+    /// OriginEnergyUsage is the amount of energy paid by the contract origin.
+    origin_energy_usage: Long,
+    /// EnergyFee is the amount of TRX burned for energy, in sun, after the caller's frozen energy.
+    energy_fee: Long,
+    /// FeeLimit is the recommended fee limit of the transaction, in sun.
+    fee_limit: Long,
 }
 
 /// CallResult is the result of a local call operation.
@@ -410,6 +585,306 @@ impl CallResult {
     }
 }
 
+/// TracerType is the kind of tracer.
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TracerType {
+    /// Opcode level struct logs.
+    Opcode,
+    /// Nested tree of calls.
+    Call,
+    /// Accounts and storage slots touched, with values before and after the execution.
+    Prestate,
+}
+
+/// TraceOptions are options of the tracer.
+#[derive(InputObject, Default)]
+pub struct TraceOptions {
+    /// Tracer selects the tracer, defaults to the opcode tracer.
+    tracer: Option<TracerType>,
+    /// DisableStack disables capturing of the stack.
+    disable_stack: Option<bool>,
+    /// DisableMemory disables capturing of the memory.
+    disable_memory: Option<bool>,
+    /// DisableStorage disables capturing of storage changes.
+    disable_storage: Option<bool>,
+}
+
+impl From<TraceOptions> for OpcodeTraceOptions {
+    fn from(opts: TraceOptions) -> Self {
+        OpcodeTraceOptions {
+            disable_stack: opts.disable_stack.unwrap_or_default(),
+            disable_memory: opts.disable_memory.unwrap_or_default(),
+            disable_storage: opts.disable_storage.unwrap_or_default(),
+        }
+    }
+}
+
+/// MemoryChunk is a changed range of the memory.
+#[derive(SimpleObject)]
+pub struct MemoryChunk {
+    offset: i32,
+    data: Bytes,
+}
+
+/// StorageChange is a storage slot written by a step.
+#[derive(SimpleObject)]
+pub struct StorageChange {
+    key: Bytes32,
+    value: Bytes32,
+}
+
+/// StructLog is the trace of a single TVM step.
+pub struct StructLog(tracer::StructLog);
+
+#[Object]
+impl StructLog {
+    /// Pc is the program counter.
+    async fn pc(&self) -> i32 {
+        self.0.pc as i32
+    }
+    /// Op is the opcode name.
+    async fn op(&self) -> &'static str {
+        tracer::opcode_name(self.0.op)
+    }
+    /// Opcode is the raw opcode.
+    async fn opcode(&self) -> i32 {
+        self.0.op as i32
+    }
+    /// Depth is the call depth, starts from 1.
+    async fn depth(&self) -> i32 {
+        self.0.depth as i32
+    }
+    /// Energy is the remaining energy before the step.
+    async fn energy(&self) -> Long {
+        Long(self.0.energy)
+    }
+    /// EnergyCost is the energy cost of the step.
+    async fn energy_cost(&self) -> Long {
+        Long(self.0.energy_cost)
+    }
+    /// Stack before the step, top of stack is the last item.
+    async fn stack(&self) -> Option<Vec<Bytes32>> {
+        self.0
+            .stack
+            .as_ref()
+            .map(|stack| stack.iter().map(|&val| Bytes32(val)).collect())
+    }
+    /// MemoryDiff is the changed memory since the last step of the same call.
+    async fn memory_diff(&self) -> Option<Vec<MemoryChunk>> {
+        self.0.memory_diff.as_ref().map(|diff| {
+            diff.iter()
+                .map(|(offset, data)| MemoryChunk {
+                    offset: *offset as i32,
+                    data: Bytes(data.clone()),
+                })
+                .collect()
+        })
+    }
+    /// Storage is the storage written by the step.
+    async fn storage(&self) -> Option<Vec<StorageChange>> {
+        self.0.storage.as_ref().map(|storage| {
+            storage
+                .iter()
+                .map(|&(key, value)| StorageChange {
+                    key: Bytes32(key),
+                    value: Bytes32(value),
+                })
+                .collect()
+        })
+    }
+}
+
+/// CallFrame is a call of the call tree.
+pub struct CallFrame(tracer::CallFrame);
+
+#[Object]
+impl CallFrame {
+    /// Type is one of CALL, CALLCODE, DELEGATECALL, STATICCALL, CREATE, SELFDESTRUCT.
+    async fn r#type(&self) -> &'static str {
+        self.0.call_type
+    }
+    /// From is the caller.
+    async fn from(&self) -> Address {
+        Address(keys::Address::from_tvm_bytes(self.0.from.as_bytes()))
+    }
+    /// To is the callee, the created contract, or the beneficiary of selfdestruct.
+    async fn to(&self) -> Address {
+        Address(keys::Address::from_tvm_bytes(self.0.to.as_bytes()))
+    }
+    /// Value is the TRX value, in sun, sent along with the call.
+    async fn value(&self) -> BigInt {
+        BigInt(self.0.value)
+    }
+    /// TokenId is the TRC10 token ID.
+    async fn token_id(&self) -> BigInt {
+        BigInt(self.0.token_id)
+    }
+    /// TokenValue is the TRC10 token value.
+    async fn token_value(&self) -> BigInt {
+        BigInt(self.0.token_value)
+    }
+    /// Input is the call data, or the init code of CREATE.
+    async fn input(&self) -> Bytes {
+        Bytes(self.0.input.clone())
+    }
+    /// Output is the return data.
+    async fn output(&self) -> Bytes {
+        Bytes(self.0.output.clone())
+    }
+    /// Energy is the energy provided to the call.
+    async fn energy(&self) -> Long {
+        Long(self.0.energy as i64)
+    }
+    /// EnergyUsed is the energy used by the call.
+    async fn energy_used(&self) -> Long {
+        Long(self.0.energy_used as i64)
+    }
+    /// Error is the exit reason of a failed call.
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+    /// RevertReason is the decoded `Error(string)` of a reverted call.
+    async fn revert_reason(&self) -> Option<&str> {
+        self.0.revert_reason.as_deref()
+    }
+    /// Calls are the inner calls.
+    async fn calls(&self) -> Vec<CallFrame> {
+        self.0.calls.iter().cloned().map(CallFrame).collect()
+    }
+}
+
+/// StorageDiff is a touched storage slot.
+#[derive(SimpleObject)]
+pub struct StorageDiff {
+    key: Bytes32,
+    before: Bytes32,
+    after: Bytes32,
+}
+
+/// AccountDiff is a touched account, with states before and after the execution.
+pub struct AccountDiff(tracer::AccountState);
+
+#[Object]
+impl AccountDiff {
+    /// Address of the account.
+    async fn address(&self) -> Address {
+        Address(keys::Address::from_tvm_bytes(self.0.address.as_bytes()))
+    }
+    /// BalanceBefore is the balance before the execution.
+    async fn balance_before(&self) -> BigInt {
+        BigInt(self.0.before.balance)
+    }
+    /// BalanceAfter is the balance after the execution.
+    async fn balance_after(&self) -> BigInt {
+        BigInt(self.0.after.balance)
+    }
+    /// CodeBefore is the contract code before the execution.
+    async fn code_before(&self) -> Bytes {
+        Bytes(self.0.before.code.clone())
+    }
+    /// CodeAfter is the contract code after the execution.
+    async fn code_after(&self) -> Bytes {
+        Bytes(self.0.after.code.clone())
+    }
+    /// Storage are the touched storage slots.
+    async fn storage(&self) -> Vec<StorageDiff> {
+        self.0
+            .before
+            .storage
+            .iter()
+            .map(|(&key, &before)| StorageDiff {
+                key: Bytes32(key),
+                before: Bytes32(before),
+                after: Bytes32(self.0.after.storage.get(&key).copied().unwrap_or(before)),
+            })
+            .collect()
+    }
+}
+
+/// TransactionTrace is the trace of a TVM execution.
+#[derive(Default)]
+pub struct TransactionTrace {
+    receipt: state::TransactionReceipt,
+    struct_logs: Vec<tracer::StructLog>,
+    call: Option<tracer::CallFrame>,
+    prestate: Vec<tracer::AccountState>,
+}
+
+impl TransactionTrace {
+    /// Run `f` with the tracer selected by options, and collect the trace.
+    fn collect<F>(options: Option<TraceOptions>, f: F) -> Result<TransactionTrace>
+    where
+        F: FnOnce(SharedTracer) -> Result<state::TransactionReceipt>,
+    {
+        let options = options.unwrap_or_default();
+        match options.tracer.unwrap_or(TracerType::Opcode) {
+            TracerType::Opcode => {
+                let opcode_tracer = Arc::new(Mutex::new(OpcodeTracer::new(options.into())));
+                let receipt = f(opcode_tracer.clone() as SharedTracer)?;
+                let struct_logs = opcode_tracer.lock().unwrap().take_logs();
+                Ok(TransactionTrace {
+                    receipt,
+                    struct_logs,
+                    ..Default::default()
+                })
+            }
+            TracerType::Call => {
+                let call_tracer = Arc::new(Mutex::new(CallTracer::new()));
+                let receipt = f(call_tracer.clone() as SharedTracer)?;
+                let call = call_tracer.lock().unwrap().take_root();
+                Ok(TransactionTrace {
+                    receipt,
+                    call,
+                    ..Default::default()
+                })
+            }
+            TracerType::Prestate => {
+                let prestate_tracer = Arc::new(Mutex::new(PrestateTracer::new()));
+                let receipt = f(prestate_tracer.clone() as SharedTracer)?;
+                let prestate = prestate_tracer.lock().unwrap().take_states();
+                Ok(TransactionTrace {
+                    receipt,
+                    prestate,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+}
+
+#[Object]
+impl TransactionTrace {
+    /// EnergyUsed is the amount of energy used by the execution.
+    async fn energy_used(&self) -> Long {
+        self.receipt
+            .resource_receipt
+            .as_ref()
+            .map(|receipt| receipt.energy)
+            .unwrap_or_default()
+            .into()
+    }
+    /// Failed is true if the execution is not successful.
+    async fn failed(&self) -> bool {
+        self.receipt.vm_status != VmStatus::Default as i32 && self.receipt.vm_status != VmStatus::Success as i32
+    }
+    /// ReturnValue is the return data of the execution.
+    async fn return_value(&self) -> Bytes {
+        Bytes(self.receipt.vm_result.clone())
+    }
+    /// StructLogs are the traces of all steps, from the opcode tracer.
+    async fn struct_logs(&self) -> Vec<StructLog> {
+        self.struct_logs.iter().cloned().map(StructLog).collect()
+    }
+    /// Call is the top-level call of the call tree, from the call tracer.
+    async fn call(&self) -> Option<CallFrame> {
+        self.call.clone().map(CallFrame)
+    }
+    /// Prestate are all accounts touched, from the prestate tracer.
+    async fn prestate(&self) -> Vec<AccountDiff> {
+        self.prestate.iter().cloned().map(AccountDiff).collect()
+    }
+}
+
 /// SyncState contains the current synchronisation state of the client.
 #[derive(SimpleObject)]
 pub struct SyncState {
@@ -429,6 +904,32 @@ pub struct SyncState {
     known_states: Option<Long>,
 }
 
+/// Pending represents the current pending state.
+pub struct Pending;
+
+#[Object]
+impl Pending {
+    /// TransactionCount is the number of transactions in the pending state.
+    async fn transaction_count(&self, ctx: &Context<'_>) -> i32 {
+        let ref mempool = ctx.data_unchecked::<Arc<AppContext>>().mempool.read().unwrap();
+        mempool.len() as i32
+    }
+
+    /// Transactions is a list of transactions in the current pending state, highest priority first.
+    async fn transactions(&self, ctx: &Context<'_>, first: Option<i32>) -> Vec<Transaction> {
+        let ref mempool = ctx.data_unchecked::<Arc<AppContext>>().mempool.read().unwrap();
+        let limit = first
+            .map(|n| n.max(0) as usize)
+            .unwrap_or(usize::MAX)
+            .min(MAX_NUMBER_OF_BATCH_ITEMS_PER_REQUEST as usize);
+        mempool
+            .pending()
+            .take(limit)
+            .map(|txn| Transaction { inner: txn.clone() })
+            .collect()
+    }
+}
+
 /// Transaction is a Tron transaction.
 pub struct Transaction {
     inner: IndexedTransaction,
@@ -494,6 +995,22 @@ impl Transaction {
         Contract::from(cntr)
     }
 
+    /// Internal transactions of TVM execution. Empty if the transaction has not yet been executed.
+    async fn internal_transactions(&self, ctx: &Context<'_>) -> Result<Vec<InternalTransaction>> {
+        let ref manager = manager_snapshot(ctx);
+        Ok(manager
+            .state()
+            .get(&keys::TransactionReceipt(self.inner.hash))?
+            .map(|receipt| {
+                receipt
+                    .vm_internal_transactions
+                    .into_iter()
+                    .map(InternalTransaction)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     // eip1767:
 
     /// From is the account that sent this transaction - this will always be
@@ -503,6 +1020,7 @@ impl Transaction {
         let address = Contract::from(cntr).owner_address();
         Account {
             address,
+            block: None,
             inner: RwLock::default(),
         }
     }
@@ -513,6 +1031,7 @@ impl Transaction {
         let cntr = self.inner.raw.raw_data.as_ref().unwrap().contract.as_ref().unwrap();
         Contract::from(cntr).to_address().map(|address| Account {
             address,
+            block: None,
             inner: RwLock::default(),
         })
     }
@@ -668,6 +1187,7 @@ impl Block {
         let address = ::keys::Address::try_from(header.as_ref().unwrap().witness())?;
         Ok(Account {
             address: address.into(),
+            block: None,
             inner: RwLock::default(),
         })
     }
@@ -709,7 +1229,7 @@ impl Block {
     /// Logs returns a filtered set of logs from this block.
     async fn logs(&self, ctx: &Context<'_>, filter: BlockFilterCriteria) -> Result<Vec<Log>> {
         self.require_txns(ctx)?;
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let mut logs = vec![];
         for (index, txn) in self.transactions.read().unwrap().as_ref().unwrap().iter().enumerate() {
             if let Some(receipt) = manager.state().get(&keys::TransactionReceipt(txn.hash))? {
@@ -762,7 +1282,7 @@ pub struct Chain;
 impl Chain {
     /// Chain parameters.
     async fn parameters(&self, ctx: &Context<'_>) -> Result<Vec<ChainParameter>> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let mut params = Vec::with_capacity(50);
         {
             let params = &mut params;
@@ -779,7 +1299,7 @@ impl Chain {
 
     /// Get a chain parameter.
     async fn parameter(&self, ctx: &Context<'_>, id: i32) -> Result<ChainParameter> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let param = keys::ChainParameter::from_i32(id).ok_or_else(|| "invalid parameter id")?;
         let value = manager.state().must_get(&param);
         Ok(ChainParameter {
@@ -791,7 +1311,7 @@ impl Chain {
 
     /// Next maintenance time.
     async fn next_maintenance_time(&self, ctx: &Context<'_>) -> DateTime<Utc> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let ts = manager.state().must_get(&keys::DynamicProperty::NextMaintenanceTime);
         Utc.timestamp(ts / 1_000, ts as u32 % 1_000 * 1_000_000)
     }
@@ -813,6 +1333,7 @@ impl QueryRoot {
         NodeInfo {
             code_version: CODE_VERSION,
             syncing: app.syncing.load(std::sync::atomic::Ordering::Relaxed),
+            importing: app.importing.load(std::sync::atomic::Ordering::Relaxed),
             num_active_connections: app.num_active_connections.load(std::sync::atomic::Ordering::Relaxed),
             num_passive_connections: 0,
             num_running_compactions: db.get_db_property("rocksdb.num-running-compactions") as _,
@@ -865,8 +1386,10 @@ impl QueryRoot {
         Ok((from.0..=to_num).map(|num| Block::from_number(Long(num))).collect())
     }
 
-    // # Pending returns the current pending state.
-    // pending: Pending!
+    /// Pending returns the current pending state.
+    async fn pending(&self) -> Pending {
+        Pending
+    }
 
     /// Transaction returns a transaction specified by its hash.
     async fn transaction(&self, ctx: &Context<'_>, hash: Bytes32) -> Result<Transaction> {
@@ -879,7 +1402,7 @@ impl QueryRoot {
     /// Logs returns log entries matching the provided filter.
     async fn logs(&self, ctx: &Context<'_>, filter: FilterCriteria) -> Result<Vec<Log>> {
         let ref db = ctx.data_unchecked::<Arc<AppContext>>().chain_db;
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let defaut_block = manager.latest_block_number();
 
         let from_block = filter.from_block.unwrap_or(defaut_block.into()).0;
@@ -918,7 +1441,7 @@ impl QueryRoot {
     /// Syncing returns information on the current synchronisation state.
     async fn syncing(&self, ctx: &Context<'_>) -> SyncState {
         let ref db = ctx.data_unchecked::<Arc<AppContext>>().chain_db;
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
 
         SyncState {
             current_block: Long(db.get_block_height()),
@@ -931,59 +1454,76 @@ impl QueryRoot {
         }
     }
 
-    // NOTE: Tron does not support block history, so the following query is moved from Block to Query.
+    // NOTE: Block history is only available in archive mode(`storage.archive`) for solidified blocks,
+    // so the following query is moved from Block to Query, with an optional block number.
 
-    /// Account fetches an Tron account at the current block's state.
-    async fn account(&self, ctx: &Context<'_>, address: Address) -> Result<Account> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
-        let acct = manager
-            .state()
-            .get(&keys::Account(address.0))?
-            .ok_or_else(|| "account not found")?;
+    /// Account fetches an Tron account at the given block's state, or the current block's state.
+    async fn account(&self, ctx: &Context<'_>, address: Address, block: Option<Long>) -> Result<Account> {
+        let ref manager = manager_snapshot(ctx);
+        let block = block.map(|num| num.0);
+        let acct = get_state_at(manager, &keys::Account(address.0), block)?.ok_or_else(|| "account not found")?;
 
         Ok(Account {
             address,
+            block,
             inner: RwLock::new(Some(acct)),
         })
     }
 
-    /// Call executes a local call operation at the current block's state.
-    async fn call(&self, ctx: &Context<'_>, data: CallData) -> Result<CallResult> {
-        use crate::manager::executor::TransactionExecutor;
-        use proto2::contract::TriggerSmartContract;
+    /// Call executes a local call operation at the given block's state, or the current block's state.
+    ///
+    /// The call is read-only, no fee is charged and the caller account is not required.
+    async fn call(&self, ctx: &Context<'_>, data: CallData, block: Option<Long>) -> Result<CallResult> {
+        let receipt = constant_call(ctx, data, block, None)?;
+        Ok(CallResult { receipt })
+    }
 
-        let trigger = TriggerSmartContract {
-            owner_address: data.from.unwrap_or_else(Default::default).0.as_bytes().to_vec(),
-            contract_address: data.to.unwrap().0.as_bytes().to_vec(),
-            data: data.data.unwrap().0,
-            call_value: data.value.map(|val| val.0).unwrap_or_default(),
-            call_token_id: data.token_id.unwrap_or_default(),
-            call_token_value: data.token_value.map(|val| val.0).unwrap_or_default(),
-        };
+    /// TraceTransaction re-executes a transaction and returns its trace.
+    ///
+    /// The state before the transaction's block must be available, i.e. in archive mode or an unsolidified block.
+    async fn trace_transaction(
+        &self,
+        ctx: &Context<'_>,
+        hash: Bytes32,
+        options: Option<TraceOptions>,
+    ) -> Result<TransactionTrace> {
+        let app = ctx.data_unchecked::<Arc<AppContext>>();
+        let block_hash = app.chain_db.get_transaction_block_hash(&hash.0)?;
+        let block = app.chain_db.get_block_by_hash(&block_hash)?;
 
-        let ref mut manager = ctx.data_unchecked::<Arc<AppContext>>().manager.write().unwrap();
-        let energy_limit = data.energy_limit.map(|val| val.0).unwrap_or(100_000_000);
+        let ref mut manager = throwaway_manager(ctx);
+        TransactionTrace::collect(options, |tracer| Ok(manager.trace_transaction(&block, &hash.0, tracer)?))
+    }
 
-        let receipt = TransactionExecutor::new(manager).execute_smart_contract(&trigger, energy_limit)?;
-        Ok(CallResult { receipt })
+    /// TraceCall executes a local call operation and returns its trace.
+    async fn trace_call(
+        &self,
+        ctx: &Context<'_>,
+        data: CallData,
+        block: Option<Long>,
+        options: Option<TraceOptions>,
+    ) -> Result<TransactionTrace> {
+        TransactionTrace::collect(options, |tracer| constant_call(ctx, data, block, Some(tracer)))
     }
 
     /// EstimateEnergy estimates the amount of energy that will be required for
     /// successful execution of a transaction at the current block's state.
     async fn estimate_energy(&self, ctx: &Context<'_>, data: CallData) -> Result<Long> {
-        self.call(ctx, data).await.and_then(|result| {
-            if result.receipt.vm_status == VmStatus::Default as i32 ||
-                result.receipt.vm_status == VmStatus::Success as i32
-            {
-                Ok(result
-                    .receipt
-                    .resource_receipt
-                    .map(|res| res.energy)
-                    .unwrap_or(0)
-                    .into())
-            } else {
-                Err(format!("vm execution result: {:?}", result.receipt.vm_status).into())
-            }
+        let estimate = estimate(ctx, data)?;
+        Ok(Long(estimate.energy_limit))
+    }
+
+    /// EstimateFee estimates the energy and the fee limit that will be required for
+    /// successful execution of a transaction at the current block's state.
+    async fn estimate_fee(&self, ctx: &Context<'_>, data: CallData) -> Result<FeeEstimate> {
+        let estimate = estimate(ctx, data)?;
+        Ok(FeeEstimate {
+            energy_limit: Long(estimate.energy_limit),
+            energy_used: Long(estimate.energy_used),
+            caller_energy_usage: Long(estimate.caller_energy_usage),
+            origin_energy_usage: Long(estimate.origin_energy_usage),
+            energy_fee: Long(estimate.energy_fee),
+            fee_limit: Long(estimate.fee_limit),
         })
     }
 
@@ -991,7 +1531,7 @@ impl QueryRoot {
 
     /// Asset fetches an Tron asset(TRC10 token).
     async fn asset(&self, ctx: &Context<'_>, issuer: Option<Address>, id: Option<i64>) -> Result<Asset> {
-        let ref manager = ctx.data_unchecked::<Arc<AppContext>>().manager.read().unwrap();
+        let ref manager = manager_snapshot(ctx);
         let token_id = match (issuer, id) {
             (None, Some(token_id)) => token_id,
             (Some(issuer_addr), None) => {
@@ -1021,22 +1561,57 @@ pub struct MutationRoot;
 #[Object]
 impl MutationRoot {
     /// SendRawTransaction sends an protobuf-encoded transaction to the network.
-    async fn send_raw_transaction(&self, _data: Bytes) -> Result<Bytes32> {
-        unimplemented!()
+    async fn send_raw_transaction(&self, ctx: &Context<'_>, data: Bytes) -> Result<Bytes32> {
+        use crate::channel::protocol::ChannelMessage;
+        use prost::Message;
+        use proto2::chain::Transaction;
+        use proto2::channel::Inventory;
+
+        let app = ctx.data_unchecked::<Arc<AppContext>>();
+        if app.solid_state_db.is_some() {
+            return Err("read-only API node, send transactions to a full node".into());
+        }
+        if app.importing.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err("node is importing blocks".into());
+        }
+
+        let txn = Transaction::decode(&*data.0)?;
+        let indexed_txn = IndexedTransaction::from_raw(txn).ok_or("invalid transaction")?;
+        let txn_hash = indexed_txn.hash;
+
+        // Same path as transactions from peers, checked against the latest state.
+        let added = {
+            let ref mut manager = app.manager.write().unwrap();
+            let ref mut mempool = app.mempool.write().unwrap();
+            mempool.add(manager, indexed_txn)?
+        };
+        if !added {
+            return Ok(Bytes32(txn_hash));
+        }
+
+        let inv = Inventory {
+            r#type: 0, // TRX
+            ids: vec![txn_hash.as_bytes().to_vec()],
+        };
+        let _ = app.gossip.send(ChannelMessage::TransactionInventory(inv));
+
+        Ok(Bytes32(txn_hash))
     }
 
     /// DryRunRawTransaction runs an protobuf-encoded transaction and returns the receipt as json.
+    ///
+    /// On failure, the error kind is returned as `extensions.code`, e.g. `SIGERROR`, `BANDWIDTH_ERROR`.
     async fn dry_run_raw_transaction(&self, ctx: &Context<'_>, data: Bytes) -> Result<CallResult> {
         use chain::IndexedTransaction;
         use prost::Message;
         use proto2::chain::Transaction;
 
-        let ref mut manager = ctx.data_unchecked::<Arc<AppContext>>().manager.write().unwrap();
+        let ref mut manager = throwaway_manager(ctx);
 
         let txn = Transaction::decode(&*data.0)?;
         let indexed_txn = IndexedTransaction::from_raw(txn).ok_or("invalid transaction")?;
 
-        let receipt = manager.dry_run_transaction(&indexed_txn)?;
+        let receipt = manager.dry_run_transaction(&indexed_txn).map_err(execution_error)?;
 
         Ok(CallResult { receipt })
     }
@@ -10,7 +10,7 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use warp::{Filter, Rejection};
 
-use super::schema::{QueryRoot, MutationRoot};
+use super::schema::{MutationRoot, QueryRoot, RequestSnapshot};
 use crate::context::AppContext;
 
 pub async fn graphql_server(ctx: Arc<AppContext>, mut shutdown_signal: broadcast::Receiver<()>) {
@@ -30,6 +30,7 @@ pub async fn graphql_server(ctx: Arc<AppContext>, mut shutdown_signal: broadcast
     let graphql_post = async_graphql_warp::graphql(schema).and_then(
         |(schema, request): (Schema<_, _, _>, async_graphql::Request)| async move {
             trace!("req: {:?}", request.query);
+            let request = request.data(RequestSnapshot::default());
             Ok::<_, Infallible>(async_graphql_warp::Response::from(schema.execute(request).await))
         },
     );
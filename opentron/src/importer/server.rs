@@ -0,0 +1,369 @@
+//! Block import pipeline, feeding blocks received from channel into the state Manager.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chain::IndexedBlock;
+use futures::future::FutureExt;
+use futures::select;
+use log::{debug, error, info, warn};
+use primitive_types::H256;
+use tokio::stream::StreamExt;
+use tokio::sync::broadcast;
+use tokio::time::{delay_for, Duration};
+
+use crate::context::AppContext;
+use crate::manager::executor::ExecutionError;
+use crate::manager::UnlinkedBlock;
+use crate::mempool::TransactionPool;
+
+/// Capacity of the block import queue. Channel handlers wait when the queue is full.
+pub const IMPORT_QUEUE_SIZE: usize = 1_000;
+
+/// Max number of out-of-order blocks kept in memory. Dropped blocks are reloaded from chain-db.
+const MAX_NUM_OF_PENDING_BLOCKS: usize = 2_000;
+
+/// Max number of blocks imported while holding the manager lock.
+const MAX_NUM_OF_BLOCKS_PER_ROUND: usize = 100;
+
+/// Max number of blocks waiting for their parents. The lowest blocks are dropped first.
+const MAX_NUM_OF_UNLINKED_BLOCKS: usize = 500;
+
+/// Number of failed attempts before a block is considered invalid, and never retried.
+///
+/// Unlinked blocks and state-db errors are not counted, since the block itself is not at fault.
+const MAX_IMPORT_ATTEMPTS: usize = 3;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+pub async fn block_import_server(
+    ctx: Arc<AppContext>,
+    mut signal: broadcast::Receiver<()>,
+) -> Result<(), Box<dyn Error>> {
+    let mut queue = match ctx.block_import_receiver.lock().unwrap().take() {
+        Some(queue) => queue,
+        None => {
+            warn!("block import service already started");
+            return Ok(());
+        }
+    };
+
+    info!(
+        "block import service started, state height={}, chain height={}",
+        ctx.manager.read().unwrap().latest_block_number(),
+        ctx.chain_db.get_block_height()
+    );
+
+    let mut pending = PendingBlocks::default();
+    let mut failed = FailedBlocks::default();
+    let mut has_more = true;
+    let mut last_reported_at = Instant::now();
+
+    loop {
+        // Import immediately when there is remaining work, otherwise wait for new blocks.
+        let idle = if has_more { 0 } else { 3 };
+        {
+            let mut next_block = queue.next().fuse();
+            let mut tick = delay_for(Duration::from_secs(idle)).fuse();
+            select! {
+                _ = signal.recv().fuse() => {
+                    warn!("block import service closed");
+                    break;
+                }
+                block = next_block => {
+                    match block {
+                        Some(block) => pending.insert(block),
+                        None => {
+                            warn!("block import queue closed");
+                            break;
+                        }
+                    }
+                }
+                _ = tick => {}
+            }
+        }
+        while let Ok(block) = queue.try_recv() {
+            pending.insert(block);
+        }
+
+        if !ctx.running.load(Ordering::Relaxed) {
+            break;
+        }
+
+        has_more = tokio::task::block_in_place(|| import_blocks(&ctx, &mut pending, &mut failed));
+
+        if last_reported_at.elapsed() >= REPORT_INTERVAL {
+            report_status(&ctx, &pending, &failed);
+            last_reported_at = Instant::now();
+        }
+    }
+
+    Ok(())
+}
+
+/// Import blocks in order, returns true if there are more blocks to import.
+fn import_blocks(ctx: &AppContext, pending: &mut PendingBlocks, failed: &mut FailedBlocks) -> bool {
+    let mut manager = ctx.manager.write().unwrap();
+    let mut num_imported = 0;
+
+    while num_imported < MAX_NUM_OF_BLOCKS_PER_ROUND {
+        let state_height = manager.latest_block_number();
+        let chain_height = ctx.chain_db.get_block_height();
+
+        let mut ready: VecDeque<_> = pending.take_until(state_height + 1).into();
+        if ready.is_empty() {
+            // Fill the gap from chain-db, blocks are persisted there by the channel handler.
+            if state_height >= chain_height {
+                break;
+            }
+            match ctx.chain_db.get_block_by_number(state_height as u64 + 1) {
+                Ok(block) => ready.push_back(block),
+                Err(e) => {
+                    debug!("can not load block #{} from chain-db: {}", state_height + 1, e);
+                    break;
+                }
+            }
+        }
+
+        while let Some(block) = ready.pop_front() {
+            if failed.is_invalid(block.hash()) {
+                continue;
+            }
+            match manager.push_block(&block) {
+                Ok(imported) => {
+                    if imported {
+                        num_imported += 1;
+                    }
+                    // Children that arrived before this block can be linked now.
+                    ready.extend(pending.take_children_of(block.hash()));
+                }
+                Err(e) if e.is::<UnlinkedBlock>() => {
+                    debug!("{}, wait for the parent", e);
+                    pending.insert_unlinked(block);
+                }
+                Err(e) => match e.downcast_ref::<ExecutionError>() {
+                    Some(ExecutionError::Db(_)) => warn!("import block #{} failed, will retry: {}", block.number(), e),
+                    _ => failed.record(&block, e.to_string()),
+                },
+            }
+        }
+
+        if manager.latest_block_number() <= state_height {
+            // No progress, wait for more blocks.
+            break;
+        }
+    }
+
+    pending.drop_unlinked_until(manager.solid_block_number());
+
+    let state_height = manager.latest_block_number();
+    let chain_height = ctx.chain_db.get_block_height();
+    ctx.importing.store(state_height < chain_height, Ordering::Relaxed);
+
+    if num_imported > 0 {
+        // Pending transactions are validated again without holding the manager lock.
+        let snapshot = manager.snapshot();
+        drop(manager);
+        TransactionPool::prune(&ctx.mempool, snapshot);
+    }
+
+    num_imported == MAX_NUM_OF_BLOCKS_PER_ROUND
+}
+
+fn report_status(ctx: &AppContext, pending: &PendingBlocks, failed: &FailedBlocks) {
+    let (state_height, solid_height) = {
+        let manager = ctx.manager.read().unwrap();
+        (manager.latest_block_number(), manager.solid_block_number())
+    };
+    info!(
+        "state height={}, solid height={}, chain height={}, pending blocks={}, unlinked blocks={}",
+        state_height,
+        solid_height,
+        ctx.chain_db.get_block_height(),
+        pending.len(),
+        pending.num_of_unlinked()
+    );
+    if let Some((number, hash, ref reason)) = failed.first_invalid {
+        if number == state_height + 1 {
+            error!(
+                "block import stopped at invalid block #{} {:?}, a valid block is required: {}",
+                number, hash, reason
+            );
+        }
+    }
+}
+
+/// Blocks that failed to import, by block hash.
+#[derive(Default)]
+struct FailedBlocks {
+    attempts: HashMap<H256, usize>,
+    invalid: HashSet<H256>,
+    // The lowest invalid block, with the error.
+    first_invalid: Option<(i64, H256, String)>,
+}
+
+impl FailedBlocks {
+    fn is_invalid(&self, hash: &H256) -> bool {
+        self.invalid.contains(hash)
+    }
+
+    fn record(&mut self, block: &IndexedBlock, reason: String) {
+        let attempts = self.attempts.entry(*block.hash()).or_default();
+        *attempts += 1;
+        if *attempts < MAX_IMPORT_ATTEMPTS {
+            warn!("import block #{} failed, attempts={}: {}", block.number(), attempts, reason);
+            return;
+        }
+
+        error!("block #{} {:?} failed {} times, marked invalid: {}", block.number(), block.hash(), attempts, reason);
+        self.attempts.remove(block.hash());
+        self.invalid.insert(*block.hash());
+        if self
+            .first_invalid
+            .as_ref()
+            .map_or(true, |&(number, ..)| block.number() < number)
+        {
+            self.first_invalid = Some((block.number(), *block.hash(), reason));
+        }
+    }
+}
+
+/// Out-of-order blocks, keyed by block number, and unlinked blocks, keyed by parent hash.
+#[derive(Default)]
+struct PendingBlocks {
+    blocks: BTreeMap<i64, Vec<IndexedBlock>>,
+    len: usize,
+    unlinked: HashMap<H256, Vec<IndexedBlock>>,
+    num_of_unlinked: usize,
+}
+
+impl PendingBlocks {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn num_of_unlinked(&self) -> usize {
+        self.num_of_unlinked
+    }
+
+    /// Keep a block until its parent is imported.
+    fn insert_unlinked(&mut self, block: IndexedBlock) {
+        let siblings = self.unlinked.entry(H256::from_slice(block.parent_hash())).or_default();
+        if siblings.contains(&block) {
+            return;
+        }
+        siblings.push(block);
+        self.num_of_unlinked += 1;
+
+        while self.num_of_unlinked > MAX_NUM_OF_UNLINKED_BLOCKS {
+            let lowest = self
+                .unlinked
+                .iter()
+                .flat_map(|(_, blocks)| blocks.iter().map(|block| block.number()))
+                .min()
+                .unwrap();
+            self.drop_unlinked_until(lowest);
+        }
+    }
+
+    /// Take blocks whose parent is `parent_hash`.
+    fn take_children_of(&mut self, parent_hash: &H256) -> Vec<IndexedBlock> {
+        let children = self.unlinked.remove(parent_hash).unwrap_or_default();
+        self.num_of_unlinked -= children.len();
+        children
+    }
+
+    /// Drop unlinked blocks whose number is less than or equal to `block_number`, they can never be linked.
+    fn drop_unlinked_until(&mut self, block_number: i64) {
+        for blocks in self.unlinked.values_mut() {
+            let before = blocks.len();
+            blocks.retain(|block| block.number() > block_number);
+            self.num_of_unlinked -= before - blocks.len();
+        }
+        self.unlinked.retain(|_, blocks| !blocks.is_empty());
+    }
+
+    fn insert(&mut self, block: IndexedBlock) {
+        let blocks = self.blocks.entry(block.number()).or_default();
+        if blocks.contains(&block) {
+            return;
+        }
+        blocks.push(block);
+        self.len += 1;
+
+        // Drop the highest blocks, they will be reloaded from chain-db later.
+        while self.len > MAX_NUM_OF_PENDING_BLOCKS {
+            let highest = *self.blocks.keys().next_back().unwrap();
+            let dropped = self.blocks.remove(&highest).unwrap();
+            self.len -= dropped.len();
+        }
+    }
+
+    /// Take all blocks whose number is less than or equal to `block_number`, in ascending order.
+    fn take_until(&mut self, block_number: i64) -> Vec<IndexedBlock> {
+        let remains = self.blocks.split_off(&(block_number + 1));
+        let taken = std::mem::replace(&mut self.blocks, remains);
+        let blocks: Vec<_> = taken.into_iter().flat_map(|(_, blocks)| blocks).collect();
+        self.len -= blocks.len();
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::{BlockHeader, IndexedBlockHeader};
+    use proto2::chain::block_header::Raw as BlockHeaderRaw;
+
+    fn block_of(number: i64, parent: &H256, timestamp: i64) -> IndexedBlock {
+        let header = BlockHeader {
+            raw_data: Some(BlockHeaderRaw {
+                number,
+                parent_hash: parent.as_bytes().to_vec(),
+                timestamp,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        IndexedBlock::new(IndexedBlockHeader::from_raw(header).unwrap(), vec![])
+    }
+
+    #[test]
+    fn test_unlinked_blocks() {
+        let mut pending = PendingBlocks::default();
+
+        let b1 = block_of(1, &H256::repeat_byte(0xaa), 3_000);
+        let b2 = block_of(2, b1.hash(), 6_000);
+        let b2x = block_of(2, b1.hash(), 9_000);
+        let b3 = block_of(3, b2.hash(), 12_000);
+        pending.insert_unlinked(b2.clone());
+        pending.insert_unlinked(b2x.clone());
+        pending.insert_unlinked(b2x.clone());
+        pending.insert_unlinked(b3.clone());
+        assert_eq!(pending.num_of_unlinked(), 3);
+
+        let children = pending.take_children_of(b1.hash());
+        assert_eq!(children.len(), 2);
+        assert!(children.contains(&b2) && children.contains(&b2x));
+        assert!(pending.take_children_of(b1.hash()).is_empty());
+        assert_eq!(pending.num_of_unlinked(), 1);
+
+        pending.drop_unlinked_until(3);
+        assert_eq!(pending.num_of_unlinked(), 0);
+        assert!(pending.take_children_of(b2.hash()).is_empty());
+
+        for i in 0..MAX_NUM_OF_UNLINKED_BLOCKS as i64 + 1 {
+            pending.insert_unlinked(block_of(10 + i, &H256::repeat_byte(0xbb), 3_000 * i));
+        }
+        assert_eq!(pending.num_of_unlinked(), MAX_NUM_OF_UNLINKED_BLOCKS);
+        // the lowest block is dropped first
+        let lowest = pending.unlinked[&H256::repeat_byte(0xbb)]
+            .iter()
+            .map(|blk| blk.number())
+            .min();
+        assert_eq!(lowest, Some(11));
+    }
+}
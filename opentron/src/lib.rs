@@ -6,5 +6,8 @@ pub mod commands;
 pub mod context;
 pub mod discovery;
 pub mod graphql;
+pub mod importer;
 pub mod manager;
+pub mod mempool;
+pub mod producer;
 pub mod util;
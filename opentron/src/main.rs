@@ -16,6 +16,8 @@ use opentron::channel::server::channel_server;
 use opentron::context::AppContext;
 use opentron::discovery::server::discovery_server;
 use opentron::graphql::server::graphql_server;
+use opentron::importer::server::block_import_server;
+use opentron::producer::server::block_producer_server;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ! init app command line arguments
@@ -49,10 +51,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build()?;
 
     info!("load config from {:?}", config_file);
-    let ctx = AppContext::from_config(config_file)?;
+    let ctx = match matches.subcommand() {
+        ("api", Some(arg_matches)) => {
+            let secondary_dir = arg_matches
+                .value_of("secondary-dir")
+                .expect("has default in cli.yml; qed");
+            AppContext::from_config_as_secondary(config_file, secondary_dir)?
+        }
+        ("migrate", Some(arg_matches)) => {
+            let fut = opentron::commands::migrate::main(config_file, arg_matches);
+            return rt.block_on(fut);
+        }
+        _ => AppContext::from_config(config_file)?,
+    };
     debug!("load config => \n{:#?}", ctx.config);
 
     match matches.subcommand() {
+        ("api", Some(_)) => {
+            let fut = opentron::commands::api::main(ctx);
+            rt.block_on(fut)
+        }
         ("check", Some(arg_matches)) => {
             let fut = opentron::commands::check::main(ctx, arg_matches);
             rt.block_on(fut)
@@ -61,6 +79,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let fut = opentron::commands::fix::main(ctx, arg_matches);
             rt.block_on(fut)
         }
+        ("replay", Some(arg_matches)) => {
+            let fut = opentron::commands::replay::main(ctx, arg_matches);
+            rt.block_on(fut)
+        }
+        ("snapshot", Some(arg_matches)) => {
+            let fut = opentron::commands::snapshot::main(ctx, arg_matches);
+            rt.block_on(fut)
+        }
         ("dev", Some(_)) => {
             let fut = opentron::commands::dev::main(ctx);
             rt.block_on(fut)
@@ -124,7 +150,21 @@ async fn run(ctx: AppContext) -> Result<(), Box<dyn Error>> {
         let done_signal = done.subscribe();
         discovery_server(ctx, done_signal)
     };
-    let _ = join!(graphql_service, channel_service, discovery_service);
+
+    let block_import_service = {
+        let ctx = ctx.clone();
+        let done_signal = done.subscribe();
+        let logger = slog_scope::logger().new(o!("service" => "importer"));
+        block_import_server(ctx, done_signal).with_logger(logger)
+    };
+
+    let block_producer_service = {
+        let ctx = ctx.clone();
+        let done_signal = done.subscribe();
+        let logger = slog_scope::logger().new(o!("service" => "producer"));
+        block_producer_server(ctx, done_signal).with_logger(logger)
+    };
+    let _ = join!(graphql_service, channel_service, discovery_service, block_import_service, block_producer_service);
 
     Ok(termination_done.await?)
 }
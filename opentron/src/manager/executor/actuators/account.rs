@@ -11,13 +11,13 @@ use proto2::contract as contract_pb;
 use proto2::state::{Account, ActivePermission, OwnerPermission, PermissionKey};
 use state::keys;
 
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::super::Manager;
 use super::BuiltinContractExecutorExt;
 
 // Set account's name.
 impl BuiltinContractExecutorExt for contract_pb::AccountUpdateContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         // validAccountName
@@ -28,7 +28,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountUpdateContract {
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "account not exists")?;
 
         let allow_update_account_name = state_db.must_get(&keys::ChainParameter::AllowUpdateAccountName) != 0;
@@ -43,7 +43,11 @@ impl BuiltinContractExecutorExt for contract_pb::AccountUpdateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
 
@@ -52,11 +56,11 @@ impl BuiltinContractExecutorExt for contract_pb::AccountUpdateContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::AccountIndex(self.account_name.clone()), owner_address)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -64,7 +68,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountUpdateContract {
 
 // Update account's permission for multisig or transfering ownership.
 impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowMultisig) == 0 {
@@ -74,7 +78,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "account not exists")?;
 
         if self.owner.is_none() {
@@ -83,7 +87,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
 
         let is_witness = state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .is_some();
         if is_witness {
             if let Some(wit_perm) = self.witness.as_ref() {
@@ -110,14 +114,23 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
 
         let fee = self.fee(manager);
         if acct.balance < fee {
-            return Err("insufficient balance to set account permission".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance to set account permission",
+                owner_address,
+                fee,
+                acct.balance,
+            ));
         }
         ctx.contract_fee = fee;
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
 
@@ -161,7 +174,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
             manager
                 .state_db
                 .put_key(keys::Witness(owner_address), wit)
-                .map_err(|e| e.to_string())?;
+                .map_err(ExecutionError::db)?;
         }
 
         if ctx.contract_fee != 0 {
@@ -171,7 +184,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -187,7 +200,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountPermissionUpdateContract
 //
 // NOTE: This is a bad desgin, and is still vulnerable. One can create a contract of any type, which is meanningless.
 impl BuiltinContractExecutorExt for contract_pb::AccountCreateContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let fee = self.fee(manager);
@@ -197,18 +210,21 @@ impl BuiltinContractExecutorExt for contract_pb::AccountCreateContract {
 
         let owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "account not exists")?;
 
-        let maybe_new_acct = state_db
-            .get(&keys::Account(new_address))
-            .map_err(|_| "db query error")?;
+        let maybe_new_acct = state_db.get(&keys::Account(new_address)).map_err(ExecutionError::db)?;
         if maybe_new_acct.is_some() {
             return Err("account already exists".into());
         }
 
         if owner_acct.balance < fee {
-            return Err("insufficient balance to create an account".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance to create an account",
+                owner_address,
+                fee,
+                owner_acct.balance,
+            ));
         }
 
         // NOTE: type is not checked here!
@@ -217,7 +233,11 @@ impl BuiltinContractExecutorExt for contract_pb::AccountCreateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
 
@@ -243,13 +263,13 @@ impl BuiltinContractExecutorExt for contract_pb::AccountCreateContract {
             manager
                 .state_db
                 .put_key(keys::Account(owner_address), owner_acct)
-                .map_err(|e| e.to_string())?;
+                .map_err(ExecutionError::db)?;
         }
 
         manager
             .state_db
             .put_key(keys::Account(new_address), new_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -265,7 +285,7 @@ impl BuiltinContractExecutorExt for contract_pb::AccountCreateContract {
 
 // Deprecated but not removed.
 impl BuiltinContractExecutorExt for contract_pb::SetAccountIdContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -283,7 +303,7 @@ impl BuiltinContractExecutorExt for contract_pb::SetAccountIdContract {
 
         let acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or("account not exists")?;
 
         if !acct.account_id.is_empty() {
@@ -295,7 +315,11 @@ impl BuiltinContractExecutorExt for contract_pb::SetAccountIdContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let mut acct = manager.state_db.must_get(&keys::Account(owner_address));
 
@@ -304,7 +328,7 @@ impl BuiltinContractExecutorExt for contract_pb::SetAccountIdContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -321,12 +345,13 @@ fn find_account_by_name(manager: &Manager, acct_name: &str) -> Option<Account> {
 }
 
 /// Check permission pb definition.
-fn check_permission(perm: &Permission, perm_type: PermissionType) -> Result<(), String> {
+fn check_permission(perm: &Permission, perm_type: PermissionType) -> Result<(), ExecutionError> {
     if perm.keys.len() > constants::MAX_NUM_OF_KEYS_IN_PERMISSION {
         return Err(format!(
             "number of keys in permission should not be greater than {}",
             constants::MAX_NUM_OF_KEYS_IN_PERMISSION
-        ));
+        )
+        .into());
     }
     if perm.keys.is_empty() {
         return Err("no permission key provided".into());
@@ -378,7 +403,7 @@ fn check_permission(perm: &Permission, perm_type: PermissionType) -> Result<(),
             for type_code in 0..256 {
                 let mask = (perm.operations[type_code / 8] >> (type_code % 8)) & 1;
                 if mask != 0 && ContractType::from_i32(type_code as i32).is_none() {
-                    return Err(format!("operation of {} is undefined", type_code));
+                    return Err(format!("operation of {} is undefined", type_code).into());
                 }
             }
         }
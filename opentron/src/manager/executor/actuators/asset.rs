@@ -14,103 +14,111 @@ use proto2::state::{asset::FrozenSupply, Account, Asset};
 use state::keys;
 
 use super::super::Manager;
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::BuiltinContractExecutorExt;
 
-impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
-        let state_db = manager.state();
+/// Validate an asset issue. Assets issued by TOKENISSUE in TVM have no url, ICO or frozen supply settings.
+pub fn validate_asset_issue(
+    cntr: &contract_pb::AssetIssueContract,
+    manager: &Manager,
+    ctx: &mut TransactionContext,
+    from_vm: bool,
+) -> Result<(), ExecutionError> {
+    let state_db = manager.state();
 
-        let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
+    let owner_address = Address::try_from(&cntr.owner_address).map_err(|_| "invalid owner_address")?;
 
-        let fee = self.fee(manager);
-        ctx.contract_fee = fee;
+    let fee = cntr.fee(manager);
+    ctx.contract_fee = fee;
 
-        // validAssetName
-        if self.name.is_empty() || self.name.len() > 32 || self.name.as_bytes().iter().any(|&b| b < 0x21 || b > 0x7e) {
-            return Err("invalid asset name".into());
-        }
+    // validAssetName
+    if cntr.name.is_empty() || cntr.name.len() > 32 || cntr.name.as_bytes().iter().any(|&b| b < 0x21 || b > 0x7e) {
+        return Err("invalid asset name".into());
+    }
 
-        let allow_same_token_name = state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0;
+    let allow_same_token_name = state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0;
 
-        if allow_same_token_name && self.name.to_lowercase() == "trx" {
-            return Err("asset name cannot be 'TRX'".into());
-        }
+    if allow_same_token_name && cntr.name.to_lowercase() == "trx" {
+        return Err("asset name cannot be 'TRX'".into());
+    }
 
-        // NOTE: The check logic is wrong, but to be compatible, just leave it.
-        // The actual check logic should check `precison==0` when `!allow_same_token_name`.
-        if allow_same_token_name && self.precision != 0 {
-            if self.precision < 0 || self.precision > 6 {
-                return Err("invalid precision, valid range is [0, 6]".into());
-            }
+    // NOTE: The check logic is wrong, but to be compatible, just leave it.
+    // The actual check logic should check `precison==0` when `!allow_same_token_name`.
+    if allow_same_token_name && cntr.precision != 0 {
+        if cntr.precision < 0 || cntr.precision > 6 {
+            return Err("invalid precision, valid range is [0, 6]".into());
         }
+    }
 
-        // NOTE: `abbr` can be empty, like asset #1000477.
-        if self.abbr.len() > 32 || self.abbr.as_bytes().iter().any(|&b| b < 0x21 || b > 0x7e) {
-            return Err("invalid asset abbr".into());
-        }
+    // NOTE: `abbr` can be empty, like asset #1000477.
+    if cntr.abbr.len() > 32 || cntr.abbr.as_bytes().iter().any(|&b| b < 0x21 || b > 0x7e) {
+        return Err("invalid asset abbr".into());
+    }
 
+    if !from_vm {
         // validUrl
-        if self.url.is_empty() || self.url.len() > 256 {
+        if cntr.url.is_empty() || cntr.url.len() > 256 {
             return Err("invalid asset url".into());
         }
 
         // validAssetDescription
-        if self.description.len() > 200 {
+        if cntr.description.len() > 200 {
             return Err("invalid asset description, too long".into());
         }
 
-        if self.start_time == 0 {
+        if cntr.start_time == 0 {
             return Err("asset start time cannot be empty".into());
         }
-        if self.end_time == 0 {
+        if cntr.end_time == 0 {
             return Err("asset end time cannot be empty".into());
         }
-        if self.end_time <= self.start_time {
+        if cntr.end_time <= cntr.start_time {
             return Err("asset end time should be greater than start time".into());
         }
-        if self.start_time <= manager.latest_block_timestamp() {
+        if cntr.start_time <= manager.latest_block_timestamp() {
             return Err("asset start time should be greater than latest block timestamp".into());
         }
+    }
 
-        if !allow_same_token_name && find_asset_by_name(manager, &self.name).is_some() {
-            return Err("asset name already exists".into());
-        }
+    if !allow_same_token_name && find_asset_by_name(manager, &cntr.name).is_some() {
+        return Err("asset name already exists".into());
+    }
 
-        if self.total_supply <= 0 {
-            return Err("total supply should be greater than 0".into());
-        }
+    if cntr.total_supply <= 0 {
+        return Err("total supply should be greater than 0".into());
+    }
 
-        if self.trx_num <= 0 {
+    if !from_vm {
+        if cntr.trx_num <= 0 {
             return Err("trx_num should be greater than 0".into());
         }
-        if self.num <= 0 {
+        if cntr.num <= 0 {
             return Err("num should be greater than 0".into());
         }
 
         // NOTE: This is a design flaw. This field is used for state-db, not for sending a builtin contract.
-        if self.public_free_asset_bandwidth_usage != 0 {
+        if cntr.public_free_asset_bandwidth_usage != 0 {
             return Err("do not fill public_free_asset_net_usage".into());
         }
 
-        if self.frozen_supply.len() > constants::MAX_NUM_OF_FROZEN_SUPPLIES_IN_ASSET_ISSUE {
+        if cntr.frozen_supply.len() > constants::MAX_NUM_OF_FROZEN_SUPPLIES_IN_ASSET_ISSUE {
             return Err("frozen supply list is too long".into());
         }
 
-        if self.free_asset_bandwidth_limit < 0 ||
-            self.free_asset_bandwidth_limit >= constants::MAX_FREE_BANDWIDTH_IN_ASSET_ISSUE
+        if cntr.free_asset_bandwidth_limit < 0 ||
+            cntr.free_asset_bandwidth_limit >= constants::MAX_FREE_BANDWIDTH_IN_ASSET_ISSUE
         {
             return Err("invalid free_asset_bandwidth_limit".into());
         }
 
-        if self.public_free_asset_bandwidth_limit < 0 ||
-            self.public_free_asset_bandwidth_limit >= constants::MAX_FREE_BANDWIDTH_IN_ASSET_ISSUE
+        if cntr.public_free_asset_bandwidth_limit < 0 ||
+            cntr.public_free_asset_bandwidth_limit >= constants::MAX_FREE_BANDWIDTH_IN_ASSET_ISSUE
         {
             return Err("invalid public_free_asset_bandwidth_limit".into());
         }
 
-        let mut remain_supply = self.total_supply;
-        for frozen_supply in &self.frozen_supply {
+        let mut remain_supply = cntr.total_supply;
+        for frozen_supply in &cntr.frozen_supply {
             if frozen_supply.frozen_amount <= 0 {
                 return Err("frozen amount should be greater than 0".into());
             }
@@ -124,34 +132,45 @@ impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
                     "frozen days should be in the range [{}, {}]",
                     constants::MIN_NUM_OF_FROZEN_DAYS_IN_ASSET_ISSUE,
                     constants::MAX_NUM_OF_FROZEN_DAYS_IN_ASSET_ISSUE
-                ));
+                )
+                .into());
             }
             remain_supply -= frozen_supply.frozen_amount;
         }
+    }
 
-        let maybe_acct = manager
-            .state_db
-            .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?;
-        if maybe_acct.is_none() {
-            return Err("account not exists".into());
-        }
-        let acct = maybe_acct.unwrap();
+    let maybe_acct = manager
+        .state_db
+        .get(&keys::Account(owner_address))
+        .map_err(ExecutionError::db)?;
+    if maybe_acct.is_none() {
+        return Err("account not exists".into());
+    }
+    let acct = maybe_acct.unwrap();
 
-        if acct.issued_asset_id != 0 {
-            return Err("an account can only issue one asset".into());
-        }
+    if acct.issued_asset_id != 0 {
+        return Err("an account can only issue one asset".into());
+    }
 
-        if acct.balance < fee {
-            return Err("insufficient balance".into());
-        }
+    if acct.balance < fee {
+        return Err(ExecutionError::insufficient_balance("insufficient balance", owner_address, fee, acct.balance));
+    }
 
-        // NOTE: The `order` field is not used(commented out in java-tron).
+    // NOTE: The `order` field is not used(commented out in java-tron).
 
-        Ok(())
+    Ok(())
+}
+
+impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
+        validate_asset_issue(self, manager, ctx, false)
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         const DAY_IN_MS: i64 = 86_400_000;
 
         let owner_address = Address::try_from(&self.owner_address).unwrap();
@@ -200,11 +219,11 @@ impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
         manager
             .state_db
             .put_key(keys::Asset(token_id), asset)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::DynamicProperty::LatestTokenId, token_id)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         if ctx.contract_fee != 0 {
             owner_acct.adjust_balance(-ctx.contract_fee).unwrap();
@@ -213,7 +232,7 @@ impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         // NOTE: `assetIssueID` of TransactionResult is not filled.
         Ok(TransactionResult::success())
@@ -226,7 +245,7 @@ impl BuiltinContractExecutorExt for contract_pb::AssetIssueContract {
 
 // Transfer TRC10(Asset) tokens, creating to_account when it is not on chain.
 impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = manager.state();
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -245,19 +264,19 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
         let allow_same_token_name = manager.state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0;
         let maybe_asset = if allow_same_token_name {
             let token_id = self.asset_name.parse().map_err(|_| "invalid asset name")?;
-            state_db.get(&keys::Asset(token_id)).map_err(|_| "db query error")?
+            state_db.get(&keys::Asset(token_id)).map_err(ExecutionError::db)?
         } else {
             find_asset_by_name(manager, &self.asset_name)
         };
         if maybe_asset.is_none() {
-            return Err(format!("asset name {} not found", self.asset_name));
+            return Err(format!("asset name {} not found", self.asset_name).into());
         }
         let asset = maybe_asset.unwrap();
 
         let maybe_owner_acct = manager
             .state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_owner_acct.is_none() {
             return Err("account not exists".into());
         }
@@ -265,12 +284,15 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
 
         let token_balance = owner_acct.token_balance.get(&asset.id).copied().unwrap_or(0);
         if token_balance < self.amount {
-            return Err("insufficient token balance".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient token balance",
+                owner_address,
+                self.amount,
+                token_balance,
+            ));
         }
 
-        let maybe_to_acct = state_db
-            .get(&keys::Account(to_address))
-            .map_err(|_| "error while querying db")?;
+        let maybe_to_acct = state_db.get(&keys::Account(to_address)).map_err(ExecutionError::db)?;
         if let Some(to_acct) = maybe_to_acct {
             if to_acct.r#type == AccountType::Contract as i32 &&
                 state_db.must_get(&keys::ChainParameter::ForbidTransferToContract) == 1
@@ -295,7 +317,12 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
         }
 
         if fee != 0 && owner_acct.balance < fee {
-            return Err("insufficient balance".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance",
+                owner_address,
+                fee,
+                owner_acct.balance,
+            ));
         }
 
         ctx.contract_fee = fee;
@@ -303,7 +330,11 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let to_address = Address::try_from(&self.to_address).unwrap();
 
@@ -314,7 +345,7 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
         let mut to_acct = manager
             .state_db
             .get(&keys::Account(to_address))
-            .map_err(|e| format!("state-db error: {:?}", e))?
+            .map_err(ExecutionError::db)?
             .unwrap_or_else(|| Account::new(manager.latest_block_timestamp()));
 
         let allow_same_token_name = manager.state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0;
@@ -338,11 +369,11 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(to_address), to_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -350,7 +381,7 @@ impl BuiltinContractExecutorExt for contract_pb::TransferAssetContract {
 
 // Participate asset issuing while asset is in issuing period. Buy new TRC10 token using TRX.
 impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = manager.state();
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -366,30 +397,35 @@ impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
 
         let maybe_owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_owner_acct.is_none() {
             return Err("owner account is not on chain".into());
         }
         let owner_acct = maybe_owner_acct.unwrap();
 
         if owner_acct.balance < self.amount {
-            return Err("insufficient balance".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance",
+                owner_address,
+                self.amount,
+                owner_acct.balance,
+            ));
         }
 
         let allow_same_token_name = manager.state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0;
         let maybe_asset = if allow_same_token_name {
             let token_id = self.asset_name.parse().map_err(|_| "invalid asset name")?;
-            state_db.get(&keys::Asset(token_id)).map_err(|_| "db query error")?
+            state_db.get(&keys::Asset(token_id)).map_err(ExecutionError::db)?
         } else {
             find_asset_by_name(manager, &self.asset_name)
         };
         if maybe_asset.is_none() {
-            return Err(format!("asset name {} not found", self.asset_name));
+            return Err(format!("asset name {} not found", self.asset_name).into());
         }
         let asset = maybe_asset.unwrap();
 
         if to_address.as_bytes() != &*asset.owner_address {
-            return Err(format!("asset {} is not issued by {}", asset.id, to_address));
+            return Err(format!("asset {} is not issued by {}", asset.id, to_address).into());
         }
 
         // exchange feasibility check
@@ -409,22 +445,30 @@ impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
         }
 
         // NOTE: asset implies account, this might be useless.
-        let maybe_to_acct = state_db
-            .get(&keys::Account(to_address))
-            .map_err(|_| "error while querying db")?;
+        let maybe_to_acct = state_db.get(&keys::Account(to_address)).map_err(ExecutionError::db)?;
         if maybe_to_acct.is_none() {
             return Err("to account is not on chain".into());
         }
         let to_acct = maybe_to_acct.unwrap();
 
-        if to_acct.token_balance.get(&asset.id).copied().unwrap_or(0) < exchange_amount {
-            return Err("insufficient balance of target asset".into());
+        let to_token_balance = to_acct.token_balance.get(&asset.id).copied().unwrap_or(0);
+        if to_token_balance < exchange_amount {
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance of target asset",
+                to_address,
+                exchange_amount,
+                to_token_balance,
+            ));
         }
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let to_address = Address::try_from(&self.to_address).unwrap();
 
@@ -438,7 +482,7 @@ impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
             manager
                 .state_db
                 .get(&keys::Asset(token_id))
-                .map_err(|_| "db query error")?
+                .map_err(ExecutionError::db)?
                 .unwrap()
         } else {
             find_asset_by_name(manager, &self.asset_name).unwrap()
@@ -454,11 +498,11 @@ impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(to_address), to_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -466,14 +510,14 @@ impl BuiltinContractExecutorExt for contract_pb::ParticipateAssetIssueContract {
 
 // Update an asset' url, description, per-account free bw limit, global free bw limit.
 impl BuiltinContractExecutorExt for contract_pb::UpdateAssetContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = manager.state();
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
 
         let maybe_owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_owner_acct.is_none() {
             return Err("owner account is not on chain".into());
         }
@@ -485,12 +529,9 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateAssetContract {
         // TODO: is this needless?
         let maybe_asset = state_db
             .get(&keys::Asset(owner_acct.issued_asset_id))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_asset.is_none() {
-            return Err(format!(
-                "asset for id {} is not found in state-db",
-                owner_acct.issued_asset_id
-            ));
+            return Err(format!("asset for id {} is not found in state-db", owner_acct.issued_asset_id).into());
         }
 
         // validUrl
@@ -514,7 +555,11 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateAssetContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
         let mut asset = manager.state_db.must_get(&keys::Asset(owner_acct.issued_asset_id));
@@ -527,7 +572,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateAssetContract {
         manager
             .state_db
             .put_key(keys::Asset(owner_acct.issued_asset_id), asset)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -535,14 +580,14 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateAssetContract {
 
 // Unfreeze an asset's frozen_supply.
 impl BuiltinContractExecutorExt for contract_pb::UnfreezeAssetContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = manager.state();
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
 
         let maybe_owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_owner_acct.is_none() {
             return Err("owner account is not on chain".into());
         }
@@ -553,12 +598,9 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeAssetContract {
 
         let maybe_asset = state_db
             .get(&keys::Asset(owner_acct.issued_asset_id))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_asset.is_none() {
-            return Err(format!(
-                "asset for id {} is not found in state-db",
-                owner_acct.issued_asset_id
-            ));
+            return Err(format!("asset for id {} is not found in state-db", owner_acct.issued_asset_id).into());
         }
         let asset = maybe_asset.unwrap();
         if asset.frozen_supply.is_empty() {
@@ -579,7 +621,11 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeAssetContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
         let mut asset = manager.state_db.must_get(&keys::Asset(owner_acct.issued_asset_id));
@@ -602,11 +648,11 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeAssetContract {
         manager
             .state_db
             .put_key(keys::Asset(owner_acct.issued_asset_id), asset)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -11,7 +11,7 @@ use proto2::state::Exchange;
 use state::keys;
 
 use super::super::Manager;
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::asset::find_asset_by_name;
 use super::BuiltinContractExecutorExt;
 
@@ -19,7 +19,7 @@ const EXCHANGE_BALANCE_LIMIT: i64 = 1_000_000_000_000_000;
 
 // Create an exchange pair.
 impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let fee = self.fee(manager);
@@ -27,11 +27,16 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
         let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let owner_acct = state_db
             .get(&keys::Account(owner_addr))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         if owner_acct.balance < fee {
-            return Err("insufficient balance".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance",
+                owner_addr,
+                fee,
+                owner_acct.balance,
+            ));
         }
 
         if self.first_token_id == self.second_token_id {
@@ -39,7 +44,12 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
         }
 
         if owner_acct.balance < fee {
-            return Err("insufficient TRX balance".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient TRX balance",
+                owner_addr,
+                fee,
+                owner_acct.balance,
+            ));
         }
 
         let first_token_id = get_exchange_token_id(manager, &self.first_token_id)?;
@@ -47,33 +57,51 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
 
         if first_token_id == 0 {
             if owner_acct.balance < self.first_token_balance + fee {
-                return Err("insufficient TRX balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient TRX balance",
+                    owner_addr,
+                    self.first_token_balance + fee,
+                    owner_acct.balance,
+                ));
             }
         } else {
-            if owner_acct
+            let owner_token_balance = owner_acct
                 .token_balance
                 .get(&first_token_id)
                 .copied()
-                .unwrap_or_default() <
-                self.first_token_balance
-            {
-                return Err("insufficient token balance".into());
+                .unwrap_or_default();
+            if owner_token_balance < self.first_token_balance {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    self.first_token_balance,
+                    owner_token_balance,
+                ));
             }
         }
 
         if second_token_id == 0 {
             if owner_acct.balance < self.second_token_balance + fee {
-                return Err("insufficient TRX balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient TRX balance",
+                    owner_addr,
+                    self.second_token_balance + fee,
+                    owner_acct.balance,
+                ));
             }
         } else {
-            if owner_acct
+            let owner_token_balance = owner_acct
                 .token_balance
                 .get(&second_token_id)
                 .copied()
-                .unwrap_or_default() <
-                self.second_token_balance
-            {
-                return Err("insufficient token balance".into());
+                .unwrap_or_default();
+            if owner_token_balance < self.second_token_balance {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    self.second_token_balance,
+                    owner_token_balance,
+                ));
             }
         }
 
@@ -81,7 +109,11 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
 
@@ -125,15 +157,15 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
         manager
             .state_db
             .put_key(keys::Exchange(exchange_id), exch)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::DynamicProperty::LatestExchangeId, exchange_id)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager.add_to_blackhole(ctx.contract_fee).unwrap();
 
         Ok(TransactionResult::success())
@@ -146,13 +178,13 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeCreateContract {
 
 // Withdraw exchange balance by owner.
 impl BuiltinContractExecutorExt for contract_pb::ExchangeWithdrawContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let _ = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let exch = state_db
             .get(&keys::Exchange(self.exchange_id))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "exchange not found on chain")?;
 
         // NOTE: Exchange owner implies account existence.
@@ -208,7 +240,11 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeWithdrawContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
 
@@ -247,11 +283,11 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeWithdrawContract {
         manager
             .state_db
             .put_key(keys::Exchange(exch.id), exch)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -261,17 +297,17 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeWithdrawContract {
 //
 // NOTE: This builtin contract has similar logic as ExchangeWithdrawContract.
 impl BuiltinContractExecutorExt for contract_pb::ExchangeInjectContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let owner_acct = state_db
             .get(&keys::Account(owner_addr))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account not found on chain")?;
         let exch = state_db
             .get(&keys::Exchange(self.exchange_id))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "exchange not found on chain")?;
 
         if exch.owner_address != self.owner_address {
@@ -293,7 +329,7 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeInjectContract {
             if exch.first_token_balance + self.quant > EXCHANGE_BALANCE_LIMIT ||
                 exch.second_token_balance + other_token_amount > EXCHANGE_BALANCE_LIMIT
             {
-                return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT));
+                return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT).into());
             }
             (exch.second_token_id, other_token_amount)
         } else if token_id == exch.second_token_id {
@@ -302,7 +338,7 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeInjectContract {
             if exch.second_token_balance + self.quant > EXCHANGE_BALANCE_LIMIT ||
                 exch.first_token_balance + other_token_amount > EXCHANGE_BALANCE_LIMIT
             {
-                return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT));
+                return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT).into());
             }
             (exch.first_token_id, other_token_amount)
         } else {
@@ -317,34 +353,58 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeInjectContract {
 
         if token_id == 0 {
             if owner_acct.balance < self.quant {
-                return Err("insufficient balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_addr,
+                    self.quant,
+                    owner_acct.balance,
+                ));
             }
         } else {
-            if owner_acct.token_balance.get(&token_id).copied().unwrap_or_default() < self.quant {
-                return Err("insufficient token balance".into());
+            let owner_token_balance = owner_acct.token_balance.get(&token_id).copied().unwrap_or_default();
+            if owner_token_balance < self.quant {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    self.quant,
+                    owner_token_balance,
+                ));
             }
         }
 
         if other_token_id == 0 {
             if owner_acct.balance < other_token_amount {
-                return Err("insufficient balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_addr,
+                    other_token_amount,
+                    owner_acct.balance,
+                ));
             }
         } else {
-            if owner_acct
+            let owner_token_balance = owner_acct
                 .token_balance
                 .get(&other_token_id)
                 .copied()
-                .unwrap_or_default() <
-                other_token_amount
-            {
-                return Err("insufficient token balance".into());
+                .unwrap_or_default();
+            if owner_token_balance < other_token_amount {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    other_token_amount,
+                    owner_token_balance,
+                ));
             }
         }
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
 
@@ -383,28 +443,28 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeInjectContract {
         manager
             .state_db
             .put_key(keys::Exchange(exch.id), exch)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
 }
 
 impl BuiltinContractExecutorExt for contract_pb::ExchangeTransactionContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let owner_acct = state_db
             .get(&keys::Account(owner_addr))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account not found on chain")?;
         let exch = state_db
             .get(&keys::Exchange(self.exchange_id))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "exchange not found on chain")?;
 
         if self.quant <= 0 {
@@ -426,16 +486,27 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeTransactionContract {
             return Err("token is not in the exchange".into());
         };
         if token_balance + self.quant > EXCHANGE_BALANCE_LIMIT {
-            return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT));
+            return Err(format!("token balance in exchange exceeds {}", EXCHANGE_BALANCE_LIMIT).into());
         }
 
         if token_id == 0 {
             if owner_acct.balance < self.quant {
-                return Err("insufficient balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_addr,
+                    self.quant,
+                    owner_acct.balance,
+                ));
             }
         } else {
-            if owner_acct.token_balance.get(&token_id).copied().unwrap_or_default() < self.quant {
-                return Err("insufficient token balance".into());
+            let owner_token_balance = owner_acct.token_balance.get(&token_id).copied().unwrap_or_default();
+            if owner_token_balance < self.quant {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    self.quant,
+                    owner_token_balance,
+                ));
             }
         }
 
@@ -452,7 +523,11 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeTransactionContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
 
@@ -497,11 +572,11 @@ impl BuiltinContractExecutorExt for contract_pb::ExchangeTransactionContract {
         manager
             .state_db
             .put_key(keys::Exchange(exch.id), exch)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -529,7 +604,7 @@ fn exchange(mut supply: i64, sell_balance: i64, buy_balance: i64, sell_amount: i
 
 /// Get token id used by exchange. Use 0 to denote TRX.
 #[inline]
-fn get_exchange_token_id(manager: &Manager, token_id: &str) -> Result<i64, String> {
+fn get_exchange_token_id(manager: &Manager, token_id: &str) -> Result<i64, ExecutionError> {
     if token_id == "_" {
         Ok(0)
     } else if manager.state_db.must_get(&keys::ChainParameter::AllowSameTokenName) != 0 {
@@ -0,0 +1,537 @@
+//! Market, the on-chain order book DEX.
+//!
+//! Orders of the same token pair are grouped into price levels. A new order(taker) is filled by the opposite
+//! orders(makers) at maker's price, best price and earliest order first. The remain is put into the order book.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::str;
+
+use ::keys::Address;
+use primitive_types::H256;
+use proto2::chain::transaction::Result as TransactionResult;
+use proto2::contract as contract_pb;
+use proto2::state::{market_order::State as OrderState, Account, MarketOrder, MarketOrderDetail};
+use state::keys;
+
+use super::super::Manager;
+use super::super::{ExecutionError, TransactionContext};
+use super::BuiltinContractExecutorExt;
+
+/// Max number of active orders of an account.
+const MAX_ACTIVE_ORDER_NUM: usize = 100;
+/// Max number of maker orders filled by one taker order.
+const MAX_MATCH_NUM: usize = 20;
+const MARKET_QUANTITY_LIMIT: i64 = 1_000_000_000_000_000;
+
+// Place a sell order.
+impl BuiltinContractExecutorExt for contract_pb::MarketSellAssetContract {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
+        let state_db = &manager.state_db;
+
+        if state_db.must_get(&keys::ChainParameter::AllowMarketTransaction) == 0 {
+            return Err("market transaction is not allowed".into());
+        }
+
+        let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
+        let owner_acct = state_db
+            .get(&keys::Account(owner_addr))
+            .map_err(ExecutionError::db)?
+            .ok_or_else(|| "owner account is not on chain")?;
+
+        let sell_token_id = get_market_token_id(manager, &self.sell_token_id)?;
+        let buy_token_id = get_market_token_id(manager, &self.buy_token_id)?;
+        if sell_token_id == buy_token_id {
+            return Err("cannot exchange same tokens".into());
+        }
+
+        if self.sell_token_quantity <= 0 || self.buy_token_quantity <= 0 {
+            return Err("token quantity must be greater than zero".into());
+        }
+        if self.sell_token_quantity > MARKET_QUANTITY_LIMIT || self.buy_token_quantity > MARKET_QUANTITY_LIMIT {
+            return Err(format!("token quantity must be less than {}", MARKET_QUANTITY_LIMIT).into());
+        }
+
+        let num_of_active_orders = state_db
+            .get(&keys::MarketAccountOrders(owner_addr))
+            .map_err(ExecutionError::db)?
+            .map(|orders| orders.order_ids.len())
+            .unwrap_or(0);
+        if num_of_active_orders >= MAX_ACTIVE_ORDER_NUM {
+            return Err(format!("maximum number of active orders is {}", MAX_ACTIVE_ORDER_NUM).into());
+        }
+
+        let fee = self.fee(manager);
+        if sell_token_id == 0 {
+            if owner_acct.balance < self.sell_token_quantity + fee {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_addr,
+                    self.sell_token_quantity + fee,
+                    owner_acct.balance,
+                ));
+            }
+        } else {
+            if owner_acct.balance < fee {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance for fee",
+                    owner_addr,
+                    fee,
+                    owner_acct.balance,
+                ));
+            }
+            let owner_token_balance = owner_acct
+                .token_balance
+                .get(&sell_token_id)
+                .copied()
+                .unwrap_or_default();
+            if owner_token_balance < self.sell_token_quantity {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_addr,
+                    self.sell_token_quantity,
+                    owner_token_balance,
+                ));
+            }
+        }
+
+        ctx.contract_fee = fee;
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
+        let owner_addr = Address::try_from(&self.owner_address).unwrap();
+        let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
+
+        let sell_token_id = get_market_token_id(manager, &self.sell_token_id).unwrap();
+        let buy_token_id = get_market_token_id(manager, &self.buy_token_id).unwrap();
+
+        owner_acct.adjust_balance(-ctx.contract_fee).unwrap();
+        adjust_token_balance(&mut owner_acct, sell_token_id, -self.sell_token_quantity).unwrap();
+        manager
+            .state_db
+            .put_key(keys::Account(owner_addr), owner_acct)
+            .map_err(ExecutionError::db)?;
+        manager.add_to_blackhole(ctx.contract_fee).unwrap();
+
+        let mut account_orders = manager
+            .state_db
+            .get(&keys::MarketAccountOrders(owner_addr))
+            .map_err(ExecutionError::db)?
+            .unwrap_or_default();
+        let order_id = order_id_of(&owner_addr, &self.sell_token_id, &self.buy_token_id, account_orders.total_count);
+        account_orders.total_count += 1;
+        manager
+            .state_db
+            .put_key(keys::MarketAccountOrders(owner_addr), account_orders)
+            .map_err(ExecutionError::db)?;
+
+        let mut order = MarketOrder {
+            order_id: order_id.as_bytes().to_vec(),
+            owner_address: self.owner_address.clone(),
+            creation_time: manager.latest_block_timestamp(),
+            sell_token_id,
+            sell_token_quantity: self.sell_token_quantity,
+            buy_token_id,
+            buy_token_quantity: self.buy_token_quantity,
+            sell_token_quantity_remain: self.sell_token_quantity,
+            sell_token_quantity_return: 0,
+            state: OrderState::Active as i32,
+        };
+
+        match_order(manager, &mut order, ctx)?;
+
+        if order.sell_token_quantity_remain > 0 {
+            add_to_price_level(manager, &order)?;
+
+            // NOTE: account orders might be changed while matching, when makers are of the same account.
+            let mut account_orders = manager.state_db.must_get(&keys::MarketAccountOrders(owner_addr));
+            account_orders.order_ids.push(order.order_id.clone());
+            manager
+                .state_db
+                .put_key(keys::MarketAccountOrders(owner_addr), account_orders)
+                .map_err(ExecutionError::db)?;
+        } else {
+            order.state = OrderState::Inactive as i32;
+        }
+        log::debug!(
+            "market order {:?}, remain={}, filled by {} orders",
+            order_id,
+            order.sell_token_quantity_remain,
+            ctx.market_order_details.len()
+        );
+        manager
+            .state_db
+            .put_key(keys::MarketOrder(order_id), order)
+            .map_err(ExecutionError::db)?;
+
+        ctx.market_order_id = Some(order_id);
+        Ok(TransactionResult::success())
+    }
+
+    fn fee(&self, manager: &Manager) -> i64 {
+        manager.state_db.must_get(&keys::ChainParameter::MarketSellFee)
+    }
+}
+
+// Cancel an active order, the remain is returned to owner.
+impl BuiltinContractExecutorExt for contract_pb::MarketCancelOrderContract {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
+        let state_db = &manager.state_db;
+
+        if state_db.must_get(&keys::ChainParameter::AllowMarketTransaction) == 0 {
+            return Err("market transaction is not allowed".into());
+        }
+
+        let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
+        let owner_acct = state_db
+            .get(&keys::Account(owner_addr))
+            .map_err(ExecutionError::db)?
+            .ok_or_else(|| "owner account is not on chain")?;
+
+        if self.order_id.len() != 32 {
+            return Err("invalid order id".into());
+        }
+        let order = state_db
+            .get(&keys::MarketOrder(H256::from_slice(&self.order_id)))
+            .map_err(ExecutionError::db)?
+            .ok_or_else(|| "order not found")?;
+        if order.owner_address != self.owner_address {
+            return Err("order does not belong to the account".into());
+        }
+        if order.state != OrderState::Active as i32 {
+            return Err("order is not active".into());
+        }
+
+        let fee = self.fee(manager);
+        if owner_acct.balance < fee {
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance",
+                owner_addr,
+                fee,
+                owner_acct.balance,
+            ));
+        }
+
+        ctx.contract_fee = fee;
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
+        let owner_addr = Address::try_from(&self.owner_address).unwrap();
+        let order_id = H256::from_slice(&self.order_id);
+        let mut order = manager.state_db.must_get(&keys::MarketOrder(order_id));
+
+        let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
+        owner_acct.adjust_balance(-ctx.contract_fee).unwrap();
+        adjust_token_balance(&mut owner_acct, order.sell_token_id, order.sell_token_quantity_remain)
+            .map_err(|_| ExecutionError::Overflow("token balance overflow".into()))?;
+        manager
+            .state_db
+            .put_key(keys::Account(owner_addr), owner_acct)
+            .map_err(ExecutionError::db)?;
+        manager.add_to_blackhole(ctx.contract_fee).unwrap();
+
+        remove_from_price_level(manager, &order)?;
+        remove_from_account_orders(manager, &order)?;
+
+        order.sell_token_quantity_remain = 0;
+        order.state = OrderState::Canceled as i32;
+        manager
+            .state_db
+            .put_key(keys::MarketOrder(order_id), order)
+            .map_err(ExecutionError::db)?;
+
+        Ok(TransactionResult::success())
+    }
+
+    fn fee(&self, manager: &Manager) -> i64 {
+        manager.state_db.must_get(&keys::ChainParameter::MarketCancelFee)
+    }
+}
+
+/// Token id of market orders, `_` for TRX. Only numeric token ids are allowed.
+fn get_market_token_id(manager: &Manager, token_id: &[u8]) -> Result<i64, ExecutionError> {
+    if token_id == b"_" {
+        return Ok(0);
+    }
+    let token_id: i64 = str::from_utf8(token_id)
+        .ok()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| "invalid token id")?;
+    manager
+        .state_db
+        .get(&keys::Asset(token_id))
+        .map_err(ExecutionError::db)?
+        .ok_or_else(|| "token not found")?;
+    Ok(token_id)
+}
+
+/// `keccak256(<<owner_address, sell_token_id, buy_token_id, count: i64>>)`, token ids are raw bytes in the contract.
+fn order_id_of(owner_addr: &Address, sell_token_id: &[u8], buy_token_id: &[u8], count: i64) -> H256 {
+    let raw = [
+        owner_addr.as_bytes(),
+        sell_token_id,
+        buy_token_id,
+        &count.to_be_bytes()[..],
+    ]
+    .concat();
+    crypto::keccak256(&raw)
+}
+
+fn adjust_token_balance(acct: &mut Account, token_id: i64, diff: i64) -> Result<(), ()> {
+    if token_id == 0 {
+        acct.adjust_balance(diff)
+    } else {
+        acct.adjust_token_balance(token_id, diff)
+    }
+}
+
+fn transfer_to_owner(
+    manager: &mut Manager,
+    order: &MarketOrder,
+    token_id: i64,
+    amount: i64,
+) -> Result<(), ExecutionError> {
+    let owner_addr = Address::try_from(&order.owner_address).unwrap();
+    let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_addr));
+    adjust_token_balance(&mut owner_acct, token_id, amount)
+        .map_err(|_| ExecutionError::Overflow("token balance overflow".into()))?;
+    manager
+        .state_db
+        .put_key(keys::Account(owner_addr), owner_acct)
+        .map_err(ExecutionError::db)?;
+    Ok(())
+}
+
+/// `a * b / c`, without overflow.
+fn multiply_and_divide(a: i64, b: i64, c: i64) -> i64 {
+    (a as i128 * b as i128 / c as i128) as i64
+}
+
+fn gcd(mut a: i64, mut b: i64) -> i64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// Price level of an order.
+fn price_level_of(order: &MarketOrder) -> keys::MarketPriceLevel {
+    let divisor = gcd(order.sell_token_quantity, order.buy_token_quantity);
+    keys::MarketPriceLevel(
+        order.sell_token_id,
+        order.buy_token_id,
+        order.sell_token_quantity / divisor,
+        order.buy_token_quantity / divisor,
+    )
+}
+
+/// Compare the price(buy quantity per sell quantity) of price levels of the same pair. Lower is better for takers.
+fn cmp_price(a: &keys::MarketPriceLevel, b: &keys::MarketPriceLevel) -> Ordering {
+    (a.3 as i128 * b.2 as i128).cmp(&(b.3 as i128 * a.2 as i128))
+}
+
+/// Is the maker price level acceptable to the taker?
+fn is_price_matched(taker: &MarketOrder, maker_level: &keys::MarketPriceLevel) -> bool {
+    taker.sell_token_quantity as i128 * maker_level.2 as i128 >=
+        taker.buy_token_quantity as i128 * maker_level.3 as i128
+}
+
+fn add_to_price_level(manager: &mut Manager, order: &MarketOrder) -> Result<(), ExecutionError> {
+    let level = price_level_of(order);
+    let mut order_list = manager
+        .state_db
+        .get(&level)
+        .map_err(ExecutionError::db)?
+        .unwrap_or_default();
+    order_list.order_ids.push(order.order_id.clone());
+    manager
+        .state_db
+        .put_key(level, order_list)
+        .map_err(ExecutionError::db)?;
+    Ok(())
+}
+
+fn remove_from_price_level(manager: &mut Manager, order: &MarketOrder) -> Result<(), ExecutionError> {
+    let level = price_level_of(order);
+    let mut order_list = manager.state_db.must_get(&level);
+    order_list.order_ids.retain(|order_id| order_id != &order.order_id);
+    if order_list.order_ids.is_empty() {
+        manager.state_db.delete_key(&level).map_err(ExecutionError::db)?;
+    } else {
+        manager
+            .state_db
+            .put_key(level, order_list)
+            .map_err(ExecutionError::db)?;
+    }
+    Ok(())
+}
+
+fn remove_from_account_orders(manager: &mut Manager, order: &MarketOrder) -> Result<(), ExecutionError> {
+    let owner_addr = Address::try_from(&order.owner_address).unwrap();
+    let mut account_orders = manager.state_db.must_get(&keys::MarketAccountOrders(owner_addr));
+    account_orders.order_ids.retain(|order_id| order_id != &order.order_id);
+    manager
+        .state_db
+        .put_key(keys::MarketAccountOrders(owner_addr), account_orders)
+        .map_err(ExecutionError::db)?;
+    Ok(())
+}
+
+/// Fill the taker order with maker orders selling what the taker buys.
+fn match_order(
+    manager: &mut Manager,
+    taker: &mut MarketOrder,
+    ctx: &mut TransactionContext,
+) -> Result<(), ExecutionError> {
+    let mut maker_levels = vec![];
+    manager.state_db.for_each_by_prefix(
+        &keys::MarketPriceLevel::prefix_of(taker.buy_token_id, taker.sell_token_id),
+        |level: &keys::MarketPriceLevel, _| maker_levels.push(*level),
+    );
+    maker_levels.sort_by(cmp_price);
+
+    let mut num_of_matches = 0;
+    for level in maker_levels {
+        if taker.sell_token_quantity_remain == 0 || !is_price_matched(taker, &level) {
+            break;
+        }
+
+        let mut order_list = manager.state_db.must_get(&level);
+        while !order_list.order_ids.is_empty() && taker.sell_token_quantity_remain > 0 {
+            num_of_matches += 1;
+            if num_of_matches > MAX_MATCH_NUM {
+                return Err(format!("too many matches, max is {}", MAX_MATCH_NUM).into());
+            }
+
+            let maker_id = H256::from_slice(&order_list.order_ids[0]);
+            let mut maker = manager.state_db.must_get(&keys::MarketOrder(maker_id));
+            fill_order(manager, taker, &mut maker, ctx)?;
+
+            if maker.sell_token_quantity_remain == 0 {
+                maker.state = OrderState::Inactive as i32;
+                order_list.order_ids.remove(0);
+                remove_from_account_orders(manager, &maker)?;
+            }
+            manager
+                .state_db
+                .put_key(keys::MarketOrder(maker_id), maker)
+                .map_err(ExecutionError::db)?;
+        }
+
+        if order_list.order_ids.is_empty() {
+            manager.state_db.delete_key(&level).map_err(ExecutionError::db)?;
+        } else {
+            manager
+                .state_db
+                .put_key(level, order_list)
+                .map_err(ExecutionError::db)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fill a pair of orders at maker's price.
+fn fill_order(
+    manager: &mut Manager,
+    taker: &mut MarketOrder,
+    maker: &mut MarketOrder,
+    ctx: &mut TransactionContext,
+) -> Result<(), ExecutionError> {
+    let taker_sell_remain = taker.sell_token_quantity_remain;
+    let maker_sell_remain = maker.sell_token_quantity_remain;
+
+    // Quantity the taker can buy with all its remain, at maker's price.
+    let taker_buy_remain = multiply_and_divide(taker_sell_remain, maker.sell_token_quantity, maker.buy_token_quantity);
+    if taker_buy_remain == 0 {
+        // Too small to buy anything, return the remain to owner.
+        taker.sell_token_quantity_return = taker_sell_remain;
+        taker.sell_token_quantity_remain = 0;
+        return transfer_to_owner(manager, taker, taker.sell_token_id, taker_sell_remain);
+    }
+
+    let taker_receive;
+    let maker_receive;
+    match taker_buy_remain.cmp(&maker_sell_remain) {
+        Ordering::Equal | Ordering::Greater => {
+            // Maker is filled.
+            taker_receive = maker_sell_remain;
+            maker_receive = multiply_and_divide(maker_sell_remain, maker.buy_token_quantity, maker.sell_token_quantity);
+            taker.sell_token_quantity_remain -= maker_receive;
+            maker.sell_token_quantity_remain = 0;
+        }
+        Ordering::Less => {
+            // Taker is filled.
+            taker_receive = taker_buy_remain;
+            maker_receive = taker_sell_remain;
+            taker.sell_token_quantity_remain = 0;
+            maker.sell_token_quantity_remain -= taker_buy_remain;
+
+            // Return the maker's remain if it's too small to buy anything.
+            let maker_remain = maker.sell_token_quantity_remain;
+            if multiply_and_divide(maker_remain, maker.buy_token_quantity, maker.sell_token_quantity) == 0 {
+                maker.sell_token_quantity_return = maker_remain;
+                maker.sell_token_quantity_remain = 0;
+                transfer_to_owner(manager, maker, maker.sell_token_id, maker_remain)?;
+            }
+        }
+    }
+
+    transfer_to_owner(manager, taker, taker.buy_token_id, taker_receive)?;
+    transfer_to_owner(manager, maker, maker.buy_token_id, maker_receive)?;
+
+    ctx.market_order_details.push(MarketOrderDetail {
+        maker_order_id: maker.order_id.clone(),
+        taker_order_id: taker.order_id.clone(),
+        fill_sell_quantity: maker_receive,
+        fill_buy_quantity: taker_receive,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_price_level() {
+        let order = MarketOrder {
+            sell_token_id: 0,
+            sell_token_quantity: 300,
+            buy_token_id: 1000001,
+            buy_token_quantity: 200,
+            ..Default::default()
+        };
+        let level = price_level_of(&order);
+        assert_eq!(level, keys::MarketPriceLevel(0, 1000001, 3, 2));
+
+        let cheap = keys::MarketPriceLevel(0, 1000001, 2, 1);
+        assert_eq!(cmp_price(&cheap, &level), Ordering::Less);
+        assert_eq!(cmp_price(&level, &keys::MarketPriceLevel(0, 1000001, 6, 4)), Ordering::Equal);
+
+        // taker sells 2 for 3, exactly the maker's price
+        let taker = MarketOrder {
+            sell_token_id: 1000001,
+            sell_token_quantity: 2,
+            buy_token_id: 0,
+            buy_token_quantity: 3,
+            ..Default::default()
+        };
+        assert!(is_price_matched(&taker, &level));
+        assert!(is_price_matched(&taker, &cheap));
+        assert!(!is_price_matched(&taker, &keys::MarketPriceLevel(0, 1000001, 1, 1)));
+
+        assert_eq!(multiply_and_divide(i64::MAX, 4, 8), i64::MAX / 2);
+    }
+}
@@ -10,11 +10,12 @@ use proto2::state::Account;
 use state::keys;
 
 use super::Manager;
-use super::TransactionContext;
+use super::{ExecutionError, TransactionContext};
 
 mod account;
 pub mod asset;
 mod exchange;
+mod market;
 mod proposal;
 mod resource;
 #[cfg(feature = "nile")]
@@ -49,12 +50,12 @@ pub trait BuiltinContractExecutorExt: BuiltinContractExt {
         recover_addrs: Vec<Address>,
         manager: &Manager,
         ctx: &mut TransactionContext,
-    ) -> Result<(), String> {
+    ) -> Result<(), ExecutionError> {
         let owner_address = Address::try_from(self.owner_address()).map_err(|_| "invalid owner_address")?;
         let maybe_acct = manager
             .state()
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_acct.is_none() {
             return Err("owner account not exists".into());
         }
@@ -77,12 +78,11 @@ pub trait BuiltinContractExecutorExt: BuiltinContractExt {
         Ok(())
     }
 
-    fn validate(&self, _manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, _manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         Ok(())
     }
 
-    // TODO: for now, use String as Error type
-    fn execute(&self, _manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(&self, _manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, ExecutionError> {
         unimplemented!("TODO: support builtin contract type {:?}", self.type_code())
     }
 
@@ -101,12 +101,12 @@ pub fn validate_multisig(
     mut recover_addrs: Vec<Address>,
     operation_mask: Option<i32>,
     allow_multisig: bool,
-) -> Result<(), String> {
+) -> Result<(), ExecutionError> {
     let len_of_recover_addrs = recover_addrs.len();
     recover_addrs.sort();
     recover_addrs.dedup();
     if recover_addrs.len() != len_of_recover_addrs {
-        return Err("duplicate signature".into());
+        return Err(ExecutionError::Permission("duplicate signature".into()));
     }
 
     if allow_multisig {
@@ -117,14 +117,17 @@ pub fn validate_multisig(
                     if let Some(key) = owner_perm.keys.iter().find(|key| key.address == rec_addr.as_bytes()) {
                         total_weight += key.weight;
                     } else {
-                        return Err(format!("signature address {} is not in permission keys", rec_addr));
+                        return Err(ExecutionError::Permission(format!(
+                            "signature address {} is not in permission keys",
+                            rec_addr
+                        )));
                     }
                 }
 
                 if total_weight >= owner_perm.threshold {
                     return Ok(());
                 } else {
-                    return Err("insufficient weight".into());
+                    return Err(ExecutionError::Permission("insufficient weight".into()));
                 }
             }
         }
@@ -137,7 +140,10 @@ pub fn validate_multisig(
                     let type_num = type_code as i32 as usize;
                     let mask = (active_perm.operations[type_num / 8] >> (type_num % 8)) & 1;
                     if mask == 0 {
-                        return Err(format!("operation bit of {} is disabled", type_code));
+                        return Err(ExecutionError::Permission(format!(
+                            "operation bit of {} is disabled",
+                            type_code
+                        )));
                     }
                 }
 
@@ -146,14 +152,14 @@ pub fn validate_multisig(
                     if let Some(key) = active_perm.keys.iter().find(|key| key.address == rec_addr.as_bytes()) {
                         total_weight += key.weight;
                     } else {
-                        return Err(format!("{} is not in permission keys", rec_addr));
+                        return Err(ExecutionError::Permission(format!("{} is not in permission keys", rec_addr)));
                     }
                 }
 
                 if total_weight >= active_perm.threshold {
                     return Ok(());
                 } else {
-                    return Err("insufficient weight".into());
+                    return Err(ExecutionError::Permission("insufficient weight".into()));
                 }
             }
         }
@@ -171,7 +177,7 @@ pub fn validate_multisig(
     {
         return Ok(());
     }
-    Err("invalid signature".into())
+    Err(ExecutionError::Permission("invalid signature".into()))
 }
 
 /// Impl BuiltinContractExt for builtin contract protobufs.
@@ -238,6 +244,8 @@ impl_contract_ext_for!(ExchangeCreateContract);
 impl_contract_ext_for!(ExchangeInjectContract);
 impl_contract_ext_for!(ExchangeWithdrawContract);
 impl_contract_ext_for!(ExchangeTransactionContract);
+impl_contract_ext_for!(MarketSellAssetContract);
+impl_contract_ext_for!(MarketCancelOrderContract);
 
 #[cfg(feature = "nile")]
 impl BuiltinContractExt for ::proto2::contract::ShieldedTransferContract {
@@ -7,13 +7,13 @@ use proto2::contract as contract_pb;
 use proto2::state::{proposal::State as ProposalState, Proposal};
 use state::keys;
 
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::super::super::governance::proposal::ProposalUtil;
 use super::super::Manager;
 use super::BuiltinContractExecutorExt;
 
 impl BuiltinContractExecutorExt for contract_pb::ProposalCreateContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
 
         // NOTE: witness implies account
@@ -21,7 +21,7 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalCreateContract {
         let maybe_wit = manager
             .state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_wit.is_none() {
             return Err("account is not a witness".into());
         }
@@ -36,7 +36,11 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalCreateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
 
         let proposal_id = manager.state_db.must_get(&keys::DynamicProperty::LatestProposalId) + 1;
@@ -61,25 +65,25 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalCreateContract {
         manager
             .state_db
             .put_key(keys::Proposal(proposal_id), proposal)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::DynamicProperty::LatestProposalId, proposal_id)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
 }
 
 impl BuiltinContractExecutorExt for contract_pb::ProposalApproveContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
 
         // NOTE: witness implies account, so no need to check account
         let maybe_wit = manager
             .state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_wit.is_none() {
             return Err("account is not a witness".into());
         }
@@ -87,7 +91,7 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalApproveContract {
         let maybe_proposal = manager
             .state_db
             .get(&keys::Proposal(self.proposal_id))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if let Some(proposal) = maybe_proposal {
             if manager.latest_block_timestamp() >= proposal.expiration_time {
                 return Err("proposal has expired".into());
@@ -105,7 +109,11 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalApproveContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
 
         let mut proposal = manager.state_db.must_get(&keys::Proposal(self.proposal_id));
@@ -122,47 +130,47 @@ impl BuiltinContractExecutorExt for contract_pb::ProposalApproveContract {
         manager
             .state_db
             .put_key(keys::Proposal(self.proposal_id), proposal)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
 }
 
 impl BuiltinContractExecutorExt for contract_pb::ProposalDeleteContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
 
         // NOTE: Proposal creator implies a witness. No need to check others.
         let proposal = manager
             .state_db
             .get(&keys::Proposal(self.proposal_id))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or("proposal id not found")?;
 
         if proposal.proposer_address != self.owner_address {
-            return Err(format!(
-                "proposal #{} is not proposed by {}",
-                proposal.proposal_id, owner_address
-            ));
+            return Err(format!("proposal #{} is not proposed by {}", proposal.proposal_id, owner_address).into());
         }
         // NOTE: Pending implies not-expired, not-cancelled
         if proposal.state != ProposalState::Pending as i32 {
-            return Err(format!(
-                "proposal #{} is not in pending state(expired or cancelled)",
-                proposal.proposal_id
-            ));
+            return Err(
+                format!("proposal #{} is not in pending state(expired or cancelled)", proposal.proposal_id).into()
+            );
         }
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let mut proposal = manager.state_db.must_get(&keys::Proposal(self.proposal_id));
         proposal.state = ProposalState::Cancelled as _;
         manager
             .state_db
             .put_key(keys::Proposal(self.proposal_id), proposal)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
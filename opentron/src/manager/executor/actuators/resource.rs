@@ -11,17 +11,17 @@ use state::keys;
 
 use super::super::super::governance::reward::RewardController;
 use super::super::Manager;
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::BuiltinContractExecutorExt;
 
 impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         if self.frozen_balance < 1_000_000 {
@@ -31,7 +31,8 @@ impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
             return Err(format!(
                 "insufficient frozen balance, balance={}, required={}",
                 owner_acct.balance, self.frozen_balance
-            ));
+            )
+            .into());
         }
 
         // TODO: handle block.checkFrozenTime config
@@ -42,7 +43,8 @@ impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
                 "frozen duration must be in range [{}, {}]",
                 constants::MIN_NUM_OF_FROZEN_DAYS_FOR_RESOURCE,
                 constants::MAX_NUM_OF_FROZEN_DAYS_FOR_RESOURCE
-            ));
+            )
+            .into());
         }
 
         if ResourceCode::from_i32(self.resource).is_none() {
@@ -59,7 +61,7 @@ impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
             let receiver_address = Address::try_from(&self.receiver_address).map_err(|_| "invalid receiver_address")?;
             let recv_acct = state_db
                 .get(&keys::Account(receiver_address))
-                .map_err(|_| "error while querying db")?
+                .map_err(ExecutionError::db)?
                 .ok_or_else(|| "receiver account is not on chain")?;
 
             if manager
@@ -77,7 +79,11 @@ impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         const DAY_IN_MS: i64 = 86_400_000;
 
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
@@ -117,13 +123,13 @@ impl BuiltinContractExecutorExt for contract_pb::FreezeBalanceContract {
 
 // Unfreeze and get frozen amount back. Will also remove all votes.
 impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_addr = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
         let owner_acct = state_db
             .get(&keys::Account(owner_addr))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         let res_type = ResourceCode::from_i32(self.resource).ok_or("invalid resource type")?;
@@ -139,13 +145,13 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
             let recv_addr = Address::try_from(&self.receiver_address).map_err(|_| "invalid receiver_address")?;
             let recv_acct = state_db
                 .get(&keys::Account(recv_addr))
-                .map_err(|_| "error while querying db")?
+                .map_err(ExecutionError::db)?
                 .ok_or_else(|| "receiver account is not on chain")?;
 
             let del = manager
                 .state_db
                 .get(&keys::ResourceDelegation(owner_addr, recv_addr))
-                .map_err(|_| "error while querying db")?
+                .map_err(ExecutionError::db)?
                 .ok_or_else(|| "delegation does not exist")?;
 
             let allow_tvm_constantinople = manager
@@ -165,13 +171,13 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
                     }
                     if !allow_tvm_constantinople {
                         if recv_acct.delegated_frozen_amount_for_bandwidth < del.amount_for_bandwidth {
-                            return Err("inconsistent state-db!!".into());
+                            return Err(ExecutionError::Db("inconsistent delegated frozen amount".into()));
                         }
                     } else if !allow_tvm_solidity059 &&
                         recv_acct.r#type != AccountType::Contract as i32 &&
                         recv_acct.delegated_frozen_amount_for_bandwidth < del.amount_for_bandwidth
                     {
-                        return Err("inconsistent state-db!!".into());
+                        return Err(ExecutionError::Db("inconsistent delegated frozen amount".into()));
                     }
                     if del.expiration_timestamp_for_bandwidth > now {
                         return Err("delegation is not expired".into());
@@ -183,13 +189,13 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
                     }
                     if !allow_tvm_constantinople {
                         if recv_acct.delegated_frozen_amount_for_energy < del.amount_for_energy {
-                            return Err("inconsistent state-db!!".into());
+                            return Err(ExecutionError::Db("inconsistent delegated frozen amount".into()));
                         }
                     } else if !allow_tvm_solidity059 &&
                         recv_acct.r#type != AccountType::Contract as i32 &&
                         recv_acct.delegated_frozen_amount_for_energy < del.amount_for_energy
                     {
-                        return Err("inconsistent state-db!!".into());
+                        return Err(ExecutionError::Db("inconsistent delegated frozen amount".into()));
                     }
                     if del.expiration_timestamp_for_energy > now {
                         return Err("delegation is not expired".into());
@@ -223,7 +229,11 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
 
         // withdrawReward
@@ -278,7 +288,7 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
                 manager
                     .state_db
                     .put_key(keys::Account(recv_addr), recv_acct)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
 
             owner_acct.adjust_balance(unfrozen_amount).unwrap();
@@ -288,12 +298,12 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
                 manager
                     .state_db
                     .delete_key(&keys::ResourceDelegation(owner_addr, recv_addr))
-                    .map_err(|_| "db delete error")?;
+                    .map_err(ExecutionError::db)?;
             } else {
                 manager
                     .state_db
                     .put_key(keys::ResourceDelegation(owner_addr, recv_addr), del)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
         } else {
             // handle frozen resource of oneself
@@ -328,7 +338,7 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
             manager
                 .state_db
                 .put_key(keys::ResourceDelegation(owner_addr, owner_addr), del)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
         }
 
         // handle global weight
@@ -340,13 +350,13 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
         manager
             .state_db
             .put_key(weight_key, weight - unfrozen_amount / 1_000_000)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         // clear votes
         let maybe_votes = manager
             .state_db
             .get(&keys::Votes(owner_addr))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if let Some(votes) = maybe_votes {
             for vote in &votes.votes {
                 let wit_addr = Address::try_from(&vote.vote_address).unwrap();
@@ -355,58 +365,58 @@ impl BuiltinContractExecutorExt for contract_pb::UnfreezeBalanceContract {
                 manager
                     .state_db
                     .put_key(keys::Witness(wit_addr), wit)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
             manager
                 .state_db
                 .delete_key(&keys::Votes(owner_addr))
-                .map_err(|_| "db delete error")?;
+                .map_err(ExecutionError::db)?;
         }
 
         // save owner_acct at last
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         ctx.unfrozen_amount = unfrozen_amount;
         Ok(TransactionResult::success())
     }
 }
 
-fn add_to_delegation_index(manager: &mut Manager, from: Address, to: Address) -> Result<(), String> {
+fn add_to_delegation_index(manager: &mut Manager, from: Address, to: Address) -> Result<(), ExecutionError> {
     let maybe_indexed_addrs = manager
         .state_db
         .get(&keys::ResourceDelegationIndex(from))
-        .map_err(|_| "db query error")?;
+        .map_err(ExecutionError::db)?;
     let mut indexed_addrs = maybe_indexed_addrs.unwrap_or_default();
     if !indexed_addrs.contains(&to) {
         indexed_addrs.push(to);
         manager
             .state_db
             .put_key(keys::ResourceDelegationIndex(from), indexed_addrs)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
     }
     Ok(())
 }
 
-fn remove_from_delegation_index(manager: &mut Manager, from: Address, to: Address) -> Result<(), String> {
+fn remove_from_delegation_index(manager: &mut Manager, from: Address, to: Address) -> Result<(), ExecutionError> {
     let maybe_indexed_addrs = manager
         .state_db
         .get(&keys::ResourceDelegationIndex(from))
-        .map_err(|_| "db query error")?;
+        .map_err(ExecutionError::db)?;
     let indexed_addrs = maybe_indexed_addrs.unwrap_or_default();
     let indexed_addrs: Vec<_> = indexed_addrs.into_iter().filter(|addr| addr != &to).collect();
     if !indexed_addrs.is_empty() {
         manager
             .state_db
             .put_key(keys::ResourceDelegationIndex(from), indexed_addrs)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
     } else {
         manager
             .state_db
             .delete_key(&keys::ResourceDelegationIndex(from))
-            .map_err(|_| "db delete eerror")?;
+            .map_err(ExecutionError::db)?;
     }
     Ok(())
 }
@@ -418,10 +428,10 @@ fn delegate_resource(
     resouce_code: ResourceCode,
     amount: i64,
     expired_time: i64,
-) -> Result<(), String> {
+) -> Result<(), ExecutionError> {
     let key = keys::ResourceDelegation(from, to);
 
-    let maybe_delegated = manager.state_db.get(&key).map_err(|_| "db query error")?;
+    let maybe_delegated = manager.state_db.get(&key).map_err(ExecutionError::db)?;
     let mut delegated = maybe_delegated.unwrap_or_else(|| ResourceDelegation {
         from_address: from.as_bytes().to_vec(),
         to_address: to.as_bytes().to_vec(),
@@ -445,16 +455,13 @@ fn delegate_resource(
         }
     }
 
-    manager
-        .state_db
-        .put_key(key, delegated)
-        .map_err(|_| "db insert error")?;
+    manager.state_db.put_key(key, delegated).map_err(ExecutionError::db)?;
 
     let old_total_weight = manager.state_db.must_get(&weight_key);
     manager
         .state_db
         .put_key(weight_key, old_total_weight + amount / 1_000_000)
-        .map_err(|_| "db insert error")?;
+        .map_err(ExecutionError::db)?;
 
     // handle delegated-resource-index
     add_to_delegation_index(manager, from, to)?;
@@ -472,7 +479,7 @@ fn delegate_resource(
     manager
         .state_db
         .put_key(keys::Account(to), to_acct)
-        .map_err(|_| "db insert error")?;
+        .map_err(ExecutionError::db)?;
 
     // handle from_account balance
     let mut from_acct = manager.state_db.must_get(&keys::Account(from));
@@ -481,7 +488,7 @@ fn delegate_resource(
     manager
         .state_db
         .put_key(keys::Account(from), from_acct)
-        .map_err(|e| e.to_string())?;
+        .map_err(ExecutionError::db)?;
     Ok(())
 }
 
@@ -491,10 +498,10 @@ fn freeze_resource(
     resouce_code: ResourceCode,
     amount: i64,
     expired_time: i64,
-) -> Result<(), String> {
+) -> Result<(), ExecutionError> {
     let key = keys::ResourceDelegation(from, from);
 
-    let maybe_delegated = manager.state_db.get(&key).map_err(|_| "db query error")?;
+    let maybe_delegated = manager.state_db.get(&key).map_err(ExecutionError::db)?;
     let mut delegated = maybe_delegated.unwrap_or_else(|| ResourceDelegation {
         to_address: from.as_bytes().to_vec(),
         from_address: from.as_bytes().to_vec(),
@@ -518,16 +525,13 @@ fn freeze_resource(
         }
     }
 
-    manager
-        .state_db
-        .put_key(key, delegated)
-        .map_err(|_| "db insert error")?;
+    manager.state_db.put_key(key, delegated).map_err(ExecutionError::db)?;
 
     let old_total_weight = manager.state_db.must_get(&weight_key);
     manager
         .state_db
         .put_key(weight_key, old_total_weight + amount / 1_000_000)
-        .map_err(|_| "db insert error")?;
+        .map_err(ExecutionError::db)?;
 
     // handle delegated-resource-index
     add_to_delegation_index(manager, from, from)?;
@@ -550,6 +554,6 @@ fn freeze_resource(
     manager
         .state_db
         .put_key(keys::Account(from), from_acct)
-        .map_err(|_| "db insert error")?;
+        .map_err(ExecutionError::db)?;
     Ok(())
 }
@@ -10,7 +10,7 @@ use proto2::contract as contract_pb;
 use proto2::state::Account;
 use state::keys;
 
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::super::Manager;
 use super::BuiltinContractExecutorExt;
 
@@ -21,7 +21,7 @@ const SHIELDED_TRANSACTION_FEE: i64 = 10_000_000;
 const SHIELDED_TRANSACTION_CREATE_ACCOUNT_FEE: i64 = 10_000_000;
 
 impl BuiltinContractExecutorExt for contract_pb::ShieldedTransferContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         if !self.transparent_from_address.is_empty() {
             let fee = self.fee(manager);
             ctx.contract_fee = fee;
@@ -29,7 +29,11 @@ impl BuiltinContractExecutorExt for contract_pb::ShieldedTransferContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         if !self.transparent_from_address.is_empty() {
             let from_addr = Address::try_from(&self.transparent_from_address).unwrap();
             let mut from_acct = manager.state_db.must_get(&keys::Account(from_addr));
@@ -6,6 +6,7 @@ use std::rc::Rc;
 
 use ::keys::Address;
 use constants::block_version::BlockVersion;
+use crypto::keccak256;
 use log::{debug, warn};
 use primitive_types::{H160, H256};
 use proto2::chain::transaction::{result::ContractStatus, Result as TransactionResult};
@@ -15,10 +16,11 @@ use state::keys;
 use tvm::{backend::ApplyBackend, ExitError, ExitFatal, ExitReason, TvmUpgrade};
 
 use super::super::super::resource::{EnergyProcessor, EnergyUtil};
+use super::super::super::tracer::{InternalTransactionRecorder, SharedTracer, VmListener};
 use super::super::super::version_fork::ForkController;
 use super::super::super::vm::StateBackend;
 use super::super::Manager;
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::BuiltinContractExecutorExt;
 
 const MAX_CONTRACT_NAME_LENGTH: usize = 32;
@@ -28,7 +30,7 @@ const SAVE_CODE_ENERGY_PER_BYTE: usize = 200;
 
 // Create a smart contract and deploy it on chain.
 impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowTvm) == 0 {
@@ -55,7 +57,7 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         if manager
             .state_db
             .get(&keys::Account(cntr_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .is_some()
         {
             return Err("contract address already exists".into());
@@ -83,7 +85,7 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         let maybe_owner_acct = manager
             .state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_owner_acct.is_none() {
             return Err("owner_account not found".into());
         }
@@ -130,7 +132,11 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let new_cntr = self.new_contract.as_ref().unwrap();
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let cntr_address = generate_created_contract_address(&ctx.transaction_hash, &owner_address);
@@ -155,9 +161,11 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         let call_value = new_cntr.call_value;
         if call_value > 0 {
             if owner_acct.adjust_balance(-call_value).is_err() {
-                return Err(format!(
-                    "insufficient balance, balance={} required={}",
-                    owner_acct.balance, call_value
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_address,
+                    call_value,
+                    owner_acct.balance,
                 )); // validate error
             }
             cntr_acct.adjust_balance(call_value).unwrap();
@@ -167,7 +175,16 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
                 .adjust_token_balance(self.call_token_id, -self.call_token_value)
                 .is_err()
             {
-                return Err("insufficient token balance".into()); // validate error
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_address,
+                    self.call_token_value,
+                    owner_acct
+                        .token_balance
+                        .get(&self.call_token_id)
+                        .copied()
+                        .unwrap_or_default(),
+                )); // validate error
             }
             cntr_acct
                 .adjust_token_balance(self.call_token_id, self.call_token_value)
@@ -204,7 +221,11 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         let precompile = upgrade.precompile();
         let config = upgrade.to_tvm_config();
 
+        let recorder =
+            InternalTransactionRecorder::new(ctx.transaction_hash, H160::from_slice(cntr_address.as_tvm_bytes()));
+        let mut listener = VmListener::new(recorder, manager.tracer.clone());
         let mut backend = StateBackend::new(owner_address, manager, ctx);
+        listener.track_builtins(backend.builtin_journal());
         let mut executor = tvm::StackExecutor::new_with_precompile(&backend, energy_limit, &config, precompile);
 
         let vm_ctx = tvm::Context {
@@ -219,12 +240,14 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
         let code = Rc::new(new_cntr.bytecode.clone());
         let data = Rc::default();
 
+        listener.start(&vm_ctx, &code, energy_limit, true);
         let mut rt = tvm::Runtime::new(code, data, vm_ctx, &config);
-        let mut exit_reason = executor.execute(&mut rt);
+        let mut exit_reason = listener.listen(|| executor.execute(&mut rt));
         let mut used_energy = executor.used_gas();
         let ret_val = rt.machine().return_value();
 
         let (applies, logs) = executor.deconstruct();
+        let applies: Vec<_> = applies.into_iter().collect();
 
         let save_code_energy = ret_val.len() * SAVE_CODE_ENERGY_PER_BYTE;
         let remain_energy = energy_limit - used_energy;
@@ -239,6 +262,13 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
                 exit_reason
             );
         }
+        if exit_reason.is_succeed() {
+            if let Err(e) = backend.check_builtin_balances(&applies) {
+                exit_reason = ExitReason::Error(e);
+            }
+        }
+        listener.finish(&exit_reason, &ret_val, used_energy);
+        listener.capture_state(&backend, false);
 
         if exit_reason.is_succeed() {
             backend.apply(applies, logs, false);
@@ -248,13 +278,16 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
                     .put_key(keys::ContractCode(cntr_address), ret_val.clone())
                     .unwrap();
             }
+            listener.capture_state(&StateBackend::new(owner_address, manager, ctx), true);
         } else {
+            let builtin_layers = backend.builtin_layers();
             drop(backend);
             drop(applies);
             drop(logs);
-            manager.rollback_layers(1);
+            manager.rollback_layers(1 + builtin_layers);
         }
         ctx.result = ret_val;
+        ctx.internal_transactions = listener.into_internal_transactions(exit_reason.is_succeed());
 
         let energy_usage = if exit_reason.is_succeed() {
             (used_energy + save_code_energy) as i64
@@ -292,7 +325,7 @@ impl BuiltinContractExecutorExt for contract_pb::CreateSmartContract {
 
 // Calling smart contract method.
 impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowTvm) == 0 {
@@ -305,7 +338,7 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         let maybe_cntr = manager
             .state_db
             .get(&keys::Contract(cntr_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_cntr.is_none() {
             return Err("contract not found".into());
         }
@@ -349,17 +382,17 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         let code = manager
             .state_db
             .get(&keys::ContractCode(cntr_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if code.is_some() && !code.as_ref().unwrap().is_empty() {
             if ctx.fee_limit < 0 || ctx.fee_limit > MAX_FEE_LIMIT {
                 return Err("invalid fee_limit".into());
             }
 
-            // TODO: check constant call
+            // NOTE: Constant calls are not transactions, see `execute_constant_call`.
             let caller_acct = manager
                 .state_db
                 .get(&keys::Account(owner_address))
-                .map_err(|_| "db query error")?
+                .map_err(ExecutionError::db)?
                 .ok_or_else(|| "owner account is not on chain")?;
             let origin_acct = manager.state_db.must_get(&keys::Account(origin_address));
 
@@ -375,7 +408,11 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let cntr_address = Address::try_from(&self.contract_address).unwrap();
 
@@ -415,9 +452,11 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         if self.call_value > 0 {
             has_transfer = true;
             if owner_acct.adjust_balance(-self.call_value).is_err() {
-                return Err(format!(
-                    "insufficient balance, balance={} required={}",
-                    owner_acct.balance, self.call_value
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_address,
+                    self.call_value,
+                    owner_acct.balance,
                 )); // validate error
             }
             cntr_acct.adjust_balance(self.call_value).unwrap();
@@ -440,7 +479,16 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
                 .adjust_token_balance(call_token_id, -call_token_value)
                 .is_err()
             {
-                return Err("insufficient token balance".into()); // validate error
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_address,
+                    call_token_value,
+                    owner_acct
+                        .token_balance
+                        .get(&call_token_id)
+                        .copied()
+                        .unwrap_or_default(),
+                )); // validate error
             }
             cntr_acct.adjust_token_balance(call_token_id, call_token_value).unwrap();
         }
@@ -460,7 +508,7 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         let code = manager
             .state_db
             .get(&keys::ContractCode(cntr_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .unwrap_or_default();
         let code = Rc::new(code);
         let data = Rc::new(self.data.to_vec());
@@ -470,7 +518,11 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
         let precompile = upgrade.precompile();
         let config = upgrade.to_tvm_config();
 
+        let recorder =
+            InternalTransactionRecorder::new(ctx.transaction_hash, H160::from_slice(cntr_address.as_tvm_bytes()));
+        let mut listener = VmListener::new(recorder, manager.tracer.clone());
         let mut backend = StateBackend::new(owner_address, manager, ctx);
+        listener.track_builtins(backend.builtin_journal());
         let mut executor = tvm::StackExecutor::new_with_precompile(&backend, energy_limit, &config, precompile);
 
         let vm_ctx = tvm::Context {
@@ -482,26 +534,38 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
             call_token_value: call_token_value.into(),
         };
 
+        listener.start(&vm_ctx, &data, energy_limit, false);
         let mut rt = tvm::Runtime::new(code, data, vm_ctx, &config);
-        let exit_reason = executor.execute(&mut rt);
+        let mut exit_reason = listener.listen(|| executor.execute(&mut rt));
         let used_energy = executor.used_gas();
         let ret_val = rt.machine().return_value();
 
         let (applies, logs) = executor.deconstruct();
+        let applies: Vec<_> = applies.into_iter().collect();
+        if exit_reason.is_succeed() {
+            if let Err(e) = backend.check_builtin_balances(&applies) {
+                exit_reason = ExitReason::Error(e);
+            }
+        }
+        listener.finish(&exit_reason, &ret_val, used_energy);
+        listener.capture_state(&backend, false);
 
         if exit_reason.is_succeed() {
             backend.apply(applies, logs, false);
+            listener.capture_state(&backend, true);
         } else {
+            let builtin_layers = backend.builtin_layers();
             drop(backend);
             drop(applies);
             drop(logs);
-            manager.rollback_layers(1);
+            manager.rollback_layers(1 + builtin_layers);
         }
 
         if !ret_val.is_empty() {
             debug!("return value: {:?}", hex::encode(&ret_val));
             ctx.result = ret_val;
         }
+        ctx.internal_transactions = listener.into_internal_transactions(exit_reason.is_succeed());
 
         let energy_usage = if exit_reason.is_fatal() {
             energy_limit as i64
@@ -531,7 +595,7 @@ impl BuiltinContractExecutorExt for contract_pb::TriggerSmartContract {
 
 // Update a contract's `consume_user_energy_percent` setting.
 impl BuiltinContractExecutorExt for contract_pb::UpdateSettingContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowTvm) == 0 {
@@ -548,7 +612,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateSettingContract {
         let maybe_cntr = manager
             .state_db
             .get(&keys::Contract(cntr_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_cntr.is_none() {
             return Err("contract not found".into());
         }
@@ -562,7 +626,11 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateSettingContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let cntr_address = Address::try_from(&self.contract_address).unwrap();
         let mut cntr = manager.state_db.must_get(&keys::Contract(cntr_address));
 
@@ -570,7 +638,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateSettingContract {
         manager
             .state_db
             .put_key(keys::Contract(cntr_address), cntr)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -578,7 +646,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateSettingContract {
 
 // Update a contract's `origin_energy_limit` setting.
 impl BuiltinContractExecutorExt for contract_pb::UpdateEnergyLimitContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowTvm) == 0 {
@@ -595,7 +663,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateEnergyLimitContract {
         let cntr = manager
             .state_db
             .get(&keys::Contract(cntr_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "contract not found on chain")?;
 
         let origin_address = *Address::from_bytes(&cntr.origin_address);
@@ -607,7 +675,11 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateEnergyLimitContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let cntr_address = Address::try_from(&self.contract_address).unwrap();
         let mut cntr = manager.state_db.must_get(&keys::Contract(cntr_address));
 
@@ -615,7 +687,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateEnergyLimitContract {
         manager
             .state_db
             .put_key(keys::Contract(cntr_address), cntr)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -625,7 +697,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateEnergyLimitContract {
 //
 // NOTE: This is a design flaw, to deceive oneself.
 impl BuiltinContractExecutorExt for contract_pb::ClearAbiContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         if state_db.must_get(&keys::ChainParameter::AllowTvm) == 0 {
@@ -638,7 +710,7 @@ impl BuiltinContractExecutorExt for contract_pb::ClearAbiContract {
         let maybe_cntr = manager
             .state_db
             .get(&keys::Contract(cntr_address))
-            .map_err(|_| "db query error")?;
+            .map_err(ExecutionError::db)?;
         if maybe_cntr.is_none() {
             return Err("contract not found".into());
         }
@@ -652,7 +724,11 @@ impl BuiltinContractExecutorExt for contract_pb::ClearAbiContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let cntr_address = Address::try_from(&self.contract_address).unwrap();
         let mut cntr = manager.state_db.must_get(&keys::Contract(cntr_address));
 
@@ -660,7 +736,7 @@ impl BuiltinContractExecutorExt for contract_pb::ClearAbiContract {
         manager
             .state_db
             .put_key(keys::Contract(cntr_address), cntr)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -671,7 +747,7 @@ pub fn execute_smart_contract(
     manager: &mut Manager,
     trigger: &contract_pb::TriggerSmartContract,
     ctx: &mut TransactionContext,
-) -> Result<TransactionResult, String> {
+) -> Result<TransactionResult, ExecutionError> {
     let owner_address = Address::try_from(&trigger.owner_address).map_err(|_| "invalid owner address")?;
     let cntr_address = Address::try_from(&trigger.contract_address).map_err(|_| "invalid contract address")?;
 
@@ -693,9 +769,11 @@ pub fn execute_smart_contract(
             .ok_or_else(|| "contract not found")?;
         if trigger.call_value > 0 {
             if owner_acct.adjust_balance(-trigger.call_value).is_err() {
-                return Err(format!(
-                    "insufficient balance, balance={} required={}",
-                    owner_acct.balance, trigger.call_value
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_address,
+                    trigger.call_value,
+                    owner_acct.balance,
                 ));
             }
             cntr_acct.adjust_balance(trigger.call_value).unwrap();
@@ -705,7 +783,16 @@ pub fn execute_smart_contract(
                 .adjust_token_balance(trigger.call_token_id, -trigger.call_token_value)
                 .is_err()
             {
-                return Err("insufficient token balance".into());
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient token balance",
+                    owner_address,
+                    trigger.call_token_value,
+                    owner_acct
+                        .token_balance
+                        .get(&trigger.call_token_id)
+                        .copied()
+                        .unwrap_or_default(),
+                ));
             }
             cntr_acct
                 .adjust_token_balance(trigger.call_token_id, trigger.call_token_value)
@@ -725,7 +812,7 @@ pub fn execute_smart_contract(
     let code = manager
         .state()
         .get(&keys::ContractCode(cntr_address))
-        .map_err(|_| "db query error")?
+        .map_err(ExecutionError::db)?
         .unwrap_or_default();
     let code = Rc::new(code);
     let data = Rc::new(trigger.data.to_vec());
@@ -734,7 +821,11 @@ pub fn execute_smart_contract(
     let precompile = upgrade.precompile();
     let config = upgrade.to_tvm_config();
 
+    let recorder =
+        InternalTransactionRecorder::new(ctx.transaction_hash, H160::from_slice(cntr_address.as_tvm_bytes()));
+    let mut listener = VmListener::new(recorder, manager.tracer.clone());
     let mut backend = StateBackend::new(owner_address, manager, ctx);
+    listener.track_builtins(backend.builtin_journal());
     let mut executor = tvm::StackExecutor::new_with_precompile(&backend, energy_limit, &config, precompile);
 
     let vm_ctx = tvm::Context {
@@ -746,21 +837,29 @@ pub fn execute_smart_contract(
         call_token_value: trigger.call_token_value.into(),
     };
 
+    listener.start(&vm_ctx, &data, energy_limit, false);
     let mut rt = tvm::Runtime::new(code, data, vm_ctx, &config);
-    let exit_reason = executor.execute(&mut rt);
+    let exit_reason = listener.listen(|| executor.execute(&mut rt));
     let used_energy = executor.used_gas();
     let ret_val = rt.machine().return_value();
 
     let (applies, logs) = executor.deconstruct();
+    listener.finish(&exit_reason, &ret_val, used_energy);
+    listener.capture_state(&backend, false);
     backend.apply(applies, logs, false);
+    if exit_reason.is_succeed() {
+        listener.capture_state(&backend, true);
+    }
+    let builtin_layers = backend.builtin_layers();
     drop(backend);
 
-    manager.rollback_layers(1);
+    manager.rollback_layers(1 + builtin_layers);
 
     if !ret_val.is_empty() {
         debug!("return value: {:?}", hex::encode(&ret_val));
         ctx.result = ret_val;
     }
+    ctx.internal_transactions = listener.into_internal_transactions(exit_reason.is_succeed());
 
     let energy_usage = if exit_reason.is_fatal() {
         energy_limit as i64
@@ -781,6 +880,150 @@ pub fn execute_smart_contract(
     Ok(ret)
 }
 
+/// Execute a constant call, with read-only access to the state-db.
+///
+/// Call value is visible to the contract, but balances are not transferred. State changes are always discarded,
+/// and fail the call if the called method is constant in ABI, unless `allow_state_change` is set.
+pub fn execute_constant_call(
+    manager: &Manager,
+    trigger: &contract_pb::TriggerSmartContract,
+    ctx: &mut TransactionContext,
+    allow_state_change: bool,
+    tracer: Option<SharedTracer>,
+) -> Result<TransactionResult, ExecutionError> {
+    let owner_address = Address::try_from(&trigger.owner_address).map_err(|_| "invalid owner address")?;
+    let cntr_address = Address::try_from(&trigger.contract_address).map_err(|_| "invalid contract address")?;
+
+    if trigger.call_value < 0 || trigger.call_token_value < 0 {
+        return Err("invalid call value".into());
+    }
+    // Caller account is only required when sending value.
+    if trigger.call_value > 0 || trigger.call_token_value > 0 {
+        let owner_acct = manager
+            .state_db
+            .get(&keys::Account(owner_address))
+            .map_err(ExecutionError::db)?
+            .ok_or_else(|| "owner account not found")?;
+        if owner_acct.balance < trigger.call_value {
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance",
+                owner_address,
+                trigger.call_value,
+                owner_acct.balance,
+            ));
+        }
+        let owner_token_balance = owner_acct
+            .token_balance
+            .get(&trigger.call_token_id)
+            .copied()
+            .unwrap_or_default();
+        if owner_token_balance < trigger.call_token_value {
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient token balance",
+                owner_address,
+                trigger.call_token_value,
+                owner_token_balance,
+            ));
+        }
+    }
+
+    let cntr = manager
+        .state_db
+        .get(&keys::Contract(cntr_address))
+        .map_err(ExecutionError::db)?
+        .ok_or_else(|| "contract not found")?;
+    let is_constant = is_constant_method(&cntr, &trigger.data);
+
+    let energy_limit = ctx.energy_limit as usize;
+    let code = manager
+        .state_db
+        .get(&keys::ContractCode(cntr_address))
+        .map_err(ExecutionError::db)?
+        .unwrap_or_default();
+    let code = Rc::new(code);
+    let data = Rc::new(trigger.data.to_vec());
+
+    let upgrade = get_current_tvm_upgrade(manager);
+    let precompile = upgrade.precompile();
+    let config = upgrade.to_tvm_config();
+
+    let recorder =
+        InternalTransactionRecorder::new(ctx.transaction_hash, H160::from_slice(cntr_address.as_tvm_bytes()));
+    let mut listener = VmListener::new(recorder, tracer);
+    let mut backend = StateBackend::new_read_only(owner_address, manager, ctx);
+    let mut executor = tvm::StackExecutor::new_with_precompile(&backend, energy_limit, &config, precompile);
+
+    let vm_ctx = tvm::Context {
+        address: H160::from_slice(cntr_address.as_tvm_bytes()),
+        caller: H160::from_slice(owner_address.as_tvm_bytes()),
+        call_value: trigger.call_value.into(),
+        call_token_id: trigger.call_token_id.into(),
+        call_token_value: trigger.call_token_value.into(),
+    };
+
+    listener.start(&vm_ctx, &data, energy_limit, false);
+    let mut rt = tvm::Runtime::new(code, data, vm_ctx, &config);
+    let exit_reason = listener.listen(|| executor.execute(&mut rt));
+    let used_energy = executor.used_gas();
+    let ret_val = rt.machine().return_value();
+
+    let (applies, logs) = executor.deconstruct();
+    listener.finish(&exit_reason, &ret_val, used_energy);
+    listener.capture_state(&backend, false);
+    let modified = backend.discard(applies, logs);
+    drop(backend);
+
+    if modified && is_constant && !allow_state_change {
+        return Err(ExecutionError::Vm("attempt to modify state in a constant call".into()));
+    }
+
+    if !ret_val.is_empty() {
+        ctx.result = ret_val;
+    }
+    ctx.internal_transactions = listener.into_internal_transactions(exit_reason.is_succeed());
+    ctx.energy = if exit_reason.is_fatal() {
+        energy_limit as i64
+    } else {
+        used_energy as i64
+    };
+    ctx.contract_status = exit_reason.as_contrat_status();
+
+    let mut ret = TransactionResult::success();
+    ret.contract_status = exit_reason.as_contrat_status() as i32;
+    debug!(
+        "constant call, vm_exit_reason={:?} modified={}",
+        exit_reason, modified
+    );
+    Ok(ret)
+}
+
+/// Whether the method called by `data` is marked constant(view or pure) in ABI.
+fn is_constant_method(cntr: &SmartContract, data: &[u8]) -> bool {
+    use proto2::common::smart_contract::abi::{EntryType, StateMutabilityType};
+
+    if data.len() < 4 {
+        return false;
+    }
+    let entries = match cntr.abi {
+        Some(ref abi) => &abi.entries,
+        None => return false,
+    };
+    entries
+        .iter()
+        .filter(|entry| entry.r#type == EntryType::Function as i32)
+        .find(|entry| {
+            let types: Vec<&str> = entry.inputs.iter().map(|param| &*param.r#type).collect();
+            let signature = format!("{}({})", entry.name, types.join(","));
+            keccak256(signature.as_bytes()).as_bytes()[..4] == data[..4]
+        })
+        .map(|entry| {
+            entry.constant ||
+                entry.state_mutability == StateMutabilityType::View as i32 ||
+                entry.state_mutability == StateMutabilityType::Pure as i32
+        })
+        .unwrap_or(false)
+}
+
 // NOTE: This is a really bad implementation.
 // It preserves constructor parameters and is inconsistent with save code energy.
 // Anyway, we are not the inventors of bugs, instead, we are copiers.
@@ -982,9 +1225,18 @@ fn get_current_tvm_upgrade(manager: &Manager) -> TvmUpgrade {
             .state_db
             .must_get(&keys::ChainParameter::AllowTvmShieldedUpgrade) !=
             0,
-        stake: false,
-        istanbul: false,
-        asset_issue: false,
+        stake: manager
+            .state_db
+            .must_get(&keys::ChainParameter::AllowTvmStakeUpgrade) !=
+            0,
+        istanbul: manager
+            .state_db
+            .must_get(&keys::ChainParameter::AllowTvmIstanbulUpgrade) !=
+            0,
+        asset_issue: manager
+            .state_db
+            .must_get(&keys::ChainParameter::AllowTvmAssetIssueUpgrade) !=
+            0,
         multisig: manager.state_db.must_get(&keys::ChainParameter::AllowMultisig) != 0,
     }
 }
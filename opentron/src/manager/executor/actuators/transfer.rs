@@ -8,14 +8,14 @@ use proto2::contract as contract_pb;
 use proto2::state::Account;
 use state::keys;
 
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::super::Manager;
 use super::BuiltinContractExecutorExt;
 
 const TRANSFER_FEE: i64 = 0;
 
 impl BuiltinContractExecutorExt for contract_pb::TransferContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -33,23 +33,23 @@ impl BuiltinContractExecutorExt for contract_pb::TransferContract {
 
         let owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         if let Some(spend) = self.amount.checked_add(fee) {
             if owner_acct.balance < spend {
-                return Err(format!(
-                    "insufficient balance, balance={}, required={}",
-                    owner_acct.balance, spend
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance",
+                    owner_address,
+                    spend,
+                    owner_acct.balance,
                 ));
             }
         } else {
             return Err("math overflow".into());
         }
 
-        let maybe_to_acct = state_db
-            .get(&keys::Account(to_address))
-            .map_err(|_| "error while querying db")?;
+        let maybe_to_acct = state_db.get(&keys::Account(to_address)).map_err(ExecutionError::db)?;
 
         match maybe_to_acct {
             None => {
@@ -77,7 +77,11 @@ impl BuiltinContractExecutorExt for contract_pb::TransferContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
         let to_address = Address::try_from(&self.to_address).unwrap();
 
@@ -88,7 +92,7 @@ impl BuiltinContractExecutorExt for contract_pb::TransferContract {
         let mut to_acct = manager
             .state_db
             .get(&keys::Account(to_address))
-            .map_err(|e| format!("state-db error: {:?}", e))?
+            .map_err(ExecutionError::db)?
             .unwrap_or_else(|| Account::new(manager.latest_block_timestamp()));
 
         if fee != 0 {
@@ -102,11 +106,11 @@ impl BuiltinContractExecutorExt for contract_pb::TransferContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::Account(to_address), to_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -115,3 +119,68 @@ impl BuiltinContractExecutorExt for contract_pb::TransferContract {
         TRANSFER_FEE
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::IndexedBlockHeader;
+
+    use crate::manager::executor::Shortfall;
+
+    const BALANCE: i64 = 1_000_000;
+
+    fn new_manager_with_account(addr: Address) -> Manager {
+        let mut manager = Manager::new_for_test();
+        manager.new_layer();
+        let mut acct = Account::new(manager.latest_block_timestamp());
+        acct.balance = BALANCE;
+        manager.state_db.put_key(keys::Account(addr), acct).unwrap();
+        manager
+    }
+
+    fn transfer(from: Address, to: Address, amount: i64) -> contract_pb::TransferContract {
+        contract_pb::TransferContract {
+            owner_address: from.as_bytes().to_vec(),
+            to_address: to.as_bytes().to_vec(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_transfer_to_new_account() {
+        let from: Address = "TN21Wx2yoNYiZ7znuQonmZMJnH5Vdfxu78".parse().unwrap();
+        let to: Address = "TCCcBZEdTHmS1NfFtCYfwpjBKeTv515n71".parse().unwrap();
+        let mut manager = new_manager_with_account(from);
+        let block_header = IndexedBlockHeader::dummy(1, manager.latest_block_timestamp() + 3_000);
+        let mut ctx = TransactionContext::dummy(&block_header);
+
+        let cntr = transfer(from, to, 1_000);
+        cntr.validate(&manager, &mut ctx).unwrap();
+        assert!(ctx.new_account_created);
+        cntr.execute(&mut manager, &mut ctx).unwrap();
+
+        assert_eq!(manager.state_db.must_get(&keys::Account(from)).balance, BALANCE - 1_000);
+        assert_eq!(manager.state_db.must_get(&keys::Account(to)).balance, 1_000);
+    }
+
+    #[test]
+    fn test_transfer_validation_errors() {
+        let from: Address = "TN21Wx2yoNYiZ7znuQonmZMJnH5Vdfxu78".parse().unwrap();
+        let to: Address = "TCCcBZEdTHmS1NfFtCYfwpjBKeTv515n71".parse().unwrap();
+        let manager = new_manager_with_account(from);
+        let block_header = IndexedBlockHeader::dummy(1, manager.latest_block_timestamp() + 3_000);
+        let mut ctx = TransactionContext::dummy(&block_header);
+
+        let err = transfer(from, to, BALANCE + 1)
+            .validate(&manager, &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.code(), "INSUFFICIENT_BALANCE");
+        assert_eq!(err.shortfall(), Some(&Shortfall::new(from, BALANCE + 1, BALANCE)));
+
+        let err = transfer(from, from, 1_000).validate(&manager, &mut ctx).unwrap_err();
+        assert_eq!(err.code(), "CONTRACT_VALIDATE_ERROR");
+
+        let err = transfer(to, from, 1_000).validate(&manager, &mut ctx).unwrap_err();
+        assert_eq!(err, ExecutionError::Validation("owner account is not on chain".into()));
+    }
+}
@@ -10,13 +10,13 @@ use proto2::contract as contract_pb;
 use proto2::state::{Votes, Witness};
 use state::keys;
 
-use super::super::TransactionContext;
+use super::super::{ExecutionError, TransactionContext};
 use super::super::super::governance::reward::{RewardController, RewardUtil};
 use super::super::Manager;
 use super::BuiltinContractExecutorExt;
 
 impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
-    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -30,18 +30,23 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
 
         let owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         let maybe_witness = state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_witness.is_some() {
-            return Err(format!("witness {} already exists", owner_address));
+            return Err(format!("witness {} already exists", owner_address).into());
         }
 
         if owner_acct.balance < fee {
-            return Err("insufficient balance to create witness".into());
+            return Err(ExecutionError::insufficient_balance(
+                "insufficient balance to create witness",
+                owner_address,
+                fee,
+                owner_acct.balance,
+            ));
         }
 
         ctx.contract_fee = fee;
@@ -49,7 +54,11 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_address = Address::try_from(&self.owner_address).unwrap();
 
         let mut owner_acct = manager.state_db.must_get(&keys::Account(owner_address));
@@ -68,7 +77,7 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
         manager
             .state_db
             .put_key(keys::Witness(owner_address), witness)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         // TODO: setIsWitness for account,  getAllowMultiSign for witness permission
 
@@ -77,7 +86,7 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
         manager
             .state_db
             .put_key(keys::Account(owner_address), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -93,7 +102,7 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessCreateContract {
 // The new votes will be directely counted and save to Witness store.
 // The current effective vote count is saved in WitnessSchedule.
 impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -118,7 +127,7 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
                 warn!("BUG: duplicated vote address");
             }
             // witness implies account
-            let maybe_witness = state_db.get(&keys::Witness(wit_addr)).map_err(|_| "db query error")?;
+            let maybe_witness = state_db.get(&keys::Witness(wit_addr)).map_err(ExecutionError::db)?;
             if maybe_witness.is_none() {
                 return Err("witness not found".into());
             }
@@ -129,7 +138,7 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
 
         let owner_acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "error while querying db")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "owner account is not on chain")?;
 
         // 1_TRX for 1_TP
@@ -138,13 +147,18 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
             return Err(format!(
                 "total number of votes is greater than account's tron power, {} > {}",
                 total_vote_count, tp
-            ));
+            )
+            .into());
         }
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
 
         // delegationService.withdrawReward(ownerAddress);
@@ -156,7 +170,7 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
         let old_votes = manager
             .state_db
             .get(&votes_key)
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .unwrap_or_default();
 
         if old_votes.votes != self.votes {
@@ -180,7 +194,7 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
                 manager
                     .state_db
                     .put_key(keys::Witness(addr), wit)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
         }
 
@@ -194,11 +208,11 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
                     votes: self.votes.clone(),
                 },
             )
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
         manager
             .state_db
             .put_key(keys::DynamicProperty::HasNewVotesInCurrentEpoch, 1)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -206,7 +220,7 @@ impl BuiltinContractExecutorExt for contract_pb::VoteWitnessContract {
 
 // Withdraw block producing reward, standby witness reward, and voting reward.
 impl BuiltinContractExecutorExt for contract_pb::WithdrawBalanceContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         const DAY_IN_MS: i64 = 86_400_000;
 
         let state_db = &manager.state_db;
@@ -215,7 +229,7 @@ impl BuiltinContractExecutorExt for contract_pb::WithdrawBalanceContract {
 
         let acct = state_db
             .get(&keys::Account(owner_address))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .ok_or_else(|| "account not exists")?;
 
         let is_gr = manager
@@ -247,7 +261,11 @@ impl BuiltinContractExecutorExt for contract_pb::WithdrawBalanceContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
 
         // delegationService.withdrawReward(ownerAddress);
@@ -268,14 +286,14 @@ impl BuiltinContractExecutorExt for contract_pb::WithdrawBalanceContract {
         manager
             .state_db
             .put_key(keys::Account(owner_addr), owner_acct)
-            .map_err(|e| e.to_string())?;
+            .map_err(ExecutionError::db)?;
         Ok(TransactionResult::success())
     }
 }
 
 // Update brokerage rate in percent of a witness account.
 impl BuiltinContractExecutorExt for contract_pb::UpdateBrokerageContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let allow_change_delegation = state_db.must_get(&keys::ChainParameter::AllowChangeDelegation) != 0;
@@ -292,15 +310,19 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateBrokerageContract {
         // Witness implies Account.
         let maybe_witness = state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_witness.is_none() {
-            return Err(format!("account {} is not a witness", owner_address));
+            return Err(format!("account {} is not a witness", owner_address).into());
         }
 
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut wit = manager.state_db.must_get(&keys::Witness(owner_addr));
 
@@ -309,7 +331,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateBrokerageContract {
             manager
                 .state_db
                 .put_key(keys::Witness(owner_addr), wit)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
         }
 
         Ok(TransactionResult::success())
@@ -317,7 +339,7 @@ impl BuiltinContractExecutorExt for contract_pb::UpdateBrokerageContract {
 }
 
 impl BuiltinContractExecutorExt for contract_pb::WitnessUpdateContract {
-    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), String> {
+    fn validate(&self, manager: &Manager, _ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let state_db = &manager.state_db;
 
         let owner_address = Address::try_from(&self.owner_address).map_err(|_| "invalid owner_address")?;
@@ -325,9 +347,9 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessUpdateContract {
         // Witness implies Account.
         let maybe_witness = state_db
             .get(&keys::Witness(owner_address))
-            .map_err(|_| "error while querying db")?;
+            .map_err(ExecutionError::db)?;
         if maybe_witness.is_none() {
-            return Err(format!("account {} is not a witness", owner_address));
+            return Err(format!("account {} is not a witness", owner_address).into());
         }
 
         // validUrl
@@ -338,7 +360,11 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessUpdateContract {
         Ok(())
     }
 
-    fn execute(&self, manager: &mut Manager, _ctx: &mut TransactionContext) -> Result<TransactionResult, String> {
+    fn execute(
+        &self,
+        manager: &mut Manager,
+        _ctx: &mut TransactionContext,
+    ) -> Result<TransactionResult, ExecutionError> {
         let owner_addr = Address::try_from(&self.owner_address).unwrap();
         let mut wit = manager.state_db.must_get(&keys::Witness(owner_addr));
 
@@ -347,7 +373,7 @@ impl BuiltinContractExecutorExt for contract_pb::WitnessUpdateContract {
         manager
             .state_db
             .put_key(keys::Witness(owner_addr), wit)
-            .map_err(|_| "db insert error")?;
+            .map_err(ExecutionError::db)?;
 
         Ok(TransactionResult::success())
     }
@@ -0,0 +1,156 @@
+//! Transaction execution errors.
+
+use std::fmt;
+
+use ::keys::Address;
+
+/// Amounts behind an insufficient balance or resource error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortfall {
+    /// The account short of balance or resource.
+    pub address: Address,
+    pub required: i64,
+    pub available: i64,
+}
+
+/// Error of transaction execution.
+///
+/// Each kind has a stable code, so API clients can handle errors without parsing the messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionError {
+    /// The contract is invalid against the current state.
+    Validation(String),
+    /// Insufficient TRX or token balance.
+    InsufficientBalance(String, Shortfall),
+    /// Insufficient bandwidth, and insufficient balance to burn for it.
+    InsufficientBandwidth(String, Shortfall),
+    /// Insufficient frozen energy, and insufficient balance to burn for it.
+    InsufficientEnergy(String, Shortfall),
+    /// Signatures do not satisfy the permission of the owner account.
+    Permission(String),
+    /// The transaction has already been applied.
+    DuplicatedTransaction,
+    /// Execution result mismatches the result recorded in the transaction.
+    ResultMismatch,
+    /// Error while reading or writing the state-db.
+    Db(String),
+    /// Balance or amount overflow while applying a validated contract.
+    Overflow(String),
+    /// TVM fault, other than an exception of the contract code.
+    Vm(String),
+}
+
+impl ExecutionError {
+    /// Stable code of the error kind.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ExecutionError::Validation(_) => "CONTRACT_VALIDATE_ERROR",
+            ExecutionError::InsufficientBalance(..) => "INSUFFICIENT_BALANCE",
+            ExecutionError::InsufficientBandwidth(..) => "BANDWIDTH_ERROR",
+            ExecutionError::InsufficientEnergy(..) => "ENERGY_ERROR",
+            ExecutionError::Permission(_) => "SIGERROR",
+            ExecutionError::DuplicatedTransaction => "DUP_TRANSACTION_ERROR",
+            ExecutionError::ResultMismatch => "RESULT_MISMATCH",
+            ExecutionError::Db(_) => "DB_ERROR",
+            ExecutionError::Overflow(_) => "MATH_OVERFLOW",
+            ExecutionError::Vm(_) => "CONTRACT_EXE_ERROR",
+        }
+    }
+
+    pub fn insufficient_balance(msg: &str, address: Address, required: i64, available: i64) -> Self {
+        ExecutionError::InsufficientBalance(msg.to_owned(), Shortfall::new(address, required, available))
+    }
+
+    pub fn insufficient_bandwidth(msg: &str, address: Address, required: i64, available: i64) -> Self {
+        ExecutionError::InsufficientBandwidth(msg.to_owned(), Shortfall::new(address, required, available))
+    }
+
+    pub fn insufficient_energy(msg: &str, address: Address, required: i64, available: i64) -> Self {
+        ExecutionError::InsufficientEnergy(msg.to_owned(), Shortfall::new(address, required, available))
+    }
+
+    /// Structured context of insufficient balance or resource errors.
+    pub fn shortfall(&self) -> Option<&Shortfall> {
+        match *self {
+            ExecutionError::InsufficientBalance(_, ref shortfall) |
+            ExecutionError::InsufficientBandwidth(_, ref shortfall) |
+            ExecutionError::InsufficientEnergy(_, ref shortfall) => Some(shortfall),
+            _ => None,
+        }
+    }
+
+    /// Wraps a state-db error, used as `.map_err(ExecutionError::db)`.
+    pub fn db<E: fmt::Display>(e: E) -> Self {
+        ExecutionError::Db(e.to_string())
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExecutionError::Validation(ref msg) |
+            ExecutionError::Permission(ref msg) |
+            ExecutionError::Overflow(ref msg) |
+            ExecutionError::Vm(ref msg) => msg.fmt(f),
+            ExecutionError::InsufficientBalance(ref msg, ref shortfall) |
+            ExecutionError::InsufficientBandwidth(ref msg, ref shortfall) |
+            ExecutionError::InsufficientEnergy(ref msg, ref shortfall) => write!(
+                f,
+                "{}, address={} required={} available={}",
+                msg, shortfall.address, shortfall.required, shortfall.available
+            ),
+            ExecutionError::DuplicatedTransaction => "duplicated transaction".fmt(f),
+            ExecutionError::ResultMismatch => "result check not passed!".fmt(f),
+            ExecutionError::Db(ref msg) => write!(f, "state-db error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl Shortfall {
+    pub fn new(address: Address, required: i64, available: i64) -> Self {
+        Shortfall {
+            address,
+            required,
+            available,
+        }
+    }
+}
+
+/// Plain messages are validation errors.
+///
+/// Only for messages of `validate()`. State-db, overflow and resource errors must use their own variants.
+impl From<String> for ExecutionError {
+    fn from(msg: String) -> Self {
+        ExecutionError::Validation(msg)
+    }
+}
+
+impl From<&str> for ExecutionError {
+    fn from(msg: &str) -> Self {
+        ExecutionError::Validation(msg.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code() {
+        let err: ExecutionError = "invalid owner_address".into();
+        assert_eq!(err.code(), "CONTRACT_VALIDATE_ERROR");
+        assert_eq!(err.to_string(), "invalid owner_address");
+
+        let err = ExecutionError::db(std::io::Error::new(std::io::ErrorKind::Other, "no db layers found"));
+        assert_eq!(err.code(), "DB_ERROR");
+        assert_eq!(err.to_string(), "state-db error: no db layers found");
+
+        let addr = Address::default();
+        let err = ExecutionError::insufficient_balance("insufficient balance", addr, 100, 42);
+        assert_eq!(err.code(), "INSUFFICIENT_BALANCE");
+        assert_eq!(err.shortfall(), Some(&Shortfall::new(addr, 100, 42)));
+        assert_eq!(err.to_string(), format!("insufficient balance, address={} required=100 available=42", addr));
+    }
+}
@@ -1,6 +1,7 @@
 //! Transaction executor.
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::str;
 
 use ::keys::{b58encode_check, Address};
@@ -11,14 +12,18 @@ use prost::Message;
 use proto2::chain::{transaction::result::ContractStatus, transaction::Result as TransactionResult, ContractType};
 use proto2::common::ResourceCode;
 use proto2::contract as contract_pb;
-use proto2::state::{ResourceReceipt, TransactionLog, TransactionReceipt};
+use proto2::state::{InternalTransaction, MarketOrderDetail, ResourceReceipt, TransactionLog, TransactionReceipt};
 use state::keys;
 
 use self::actuators::{BuiltinContractExecutorExt, BuiltinContractExt};
-use crate::manager::resource::BandwidthProcessor;
+use crate::manager::resource::{BandwidthProcessor, EnergyUtil};
+use crate::manager::tracer::SharedTracer;
 use crate::manager::Manager;
 
+pub use self::error::{ExecutionError, Shortfall};
+
 pub mod actuators;
+mod error;
 
 pub struct TransactionContext<'a> {
     // Transaction static context.
@@ -44,7 +49,11 @@ pub struct TransactionContext<'a> {
     pub energy_fee: i64,
     pub result: Vec<u8>,
     pub logs: Vec<TransactionLog>,
+    pub internal_transactions: Vec<InternalTransaction>,
     pub contract_status: ContractStatus,
+    // Set by market actuators.
+    pub market_order_id: Option<H256>,
+    pub market_order_details: Vec<MarketOrderDetail>,
 }
 
 impl<'a> TransactionContext<'a> {
@@ -71,7 +80,10 @@ impl<'a> TransactionContext<'a> {
             energy_fee: 0,
             result: vec![],
             logs: vec![],
+            internal_transactions: vec![],
             contract_status: ContractStatus::default(),
+            market_order_id: None,
+            market_order_details: vec![],
         }
     }
 
@@ -95,7 +107,10 @@ impl<'a> TransactionContext<'a> {
             energy_fee: 0,
             result: vec![],
             logs: vec![],
+            internal_transactions: vec![],
             contract_status: ContractStatus::default(),
+            market_order_id: None,
+            market_order_details: vec![],
         }
     }
 }
@@ -118,6 +133,10 @@ impl From<TransactionContext<'_>> for TransactionReceipt {
             ..Default::default()
         };
 
+        if let Some(order_id) = ctx.market_order_id {
+            receipt.market_order_id = order_id.as_bytes().to_vec();
+            receipt.market_order_details = ctx.market_order_details;
+        }
         // TODO: distinguish by builtin contract type
         if ctx.energy_limit > 0 {
             receipt.resource_receipt.as_mut().map(|r| {
@@ -129,6 +148,7 @@ impl From<TransactionContext<'_>> for TransactionReceipt {
             receipt.vm_result = ctx.result;
             receipt.vm_status = ctx.contract_status as i32;
             receipt.vm_logs = ctx.logs;
+            receipt.vm_internal_transactions = ctx.internal_transactions;
         }
         receipt
     }
@@ -160,6 +180,154 @@ impl ::std::fmt::Debug for TransactionContext<'_> {
     }
 }
 
+/// Execute a constant call of smart contract with read-only state, no fee is charged.
+pub fn execute_constant_call(
+    manager: &Manager,
+    trigger: &contract_pb::TriggerSmartContract,
+    energy_limit: i64,
+    allow_state_change: bool,
+    tracer: Option<SharedTracer>,
+) -> Result<TransactionReceipt, ExecutionError> {
+    let block_header = IndexedBlockHeader::dummy(
+        manager.latest_block_number() + 1,
+        manager.latest_block_timestamp() + constants::BLOCK_PRODUCING_INTERVAL,
+    );
+
+    let mut ctx = TransactionContext::dummy(&block_header);
+    ctx.energy_limit = energy_limit;
+
+    self::actuators::smart_contract::execute_constant_call(manager, trigger, &mut ctx, allow_state_change, tracer)?;
+    debug!("constant call context => {:?}", ctx);
+    Ok(ctx.into())
+}
+
+/// Estimated energy and fee of a contract call.
+#[derive(Debug, Clone, Default)]
+pub struct EnergyEstimate {
+    /// The minimum energy limit for the call to succeed.
+    pub energy_limit: i64,
+    /// Energy used by the call, with the minimum energy limit.
+    pub energy_used: i64,
+    /// Energy paid by the caller.
+    pub caller_energy_usage: i64,
+    /// Energy paid by the contract origin, from its frozen energy.
+    pub origin_energy_usage: i64,
+    /// TRX burned for the caller's energy not covered by its frozen energy, in sun.
+    pub energy_fee: i64,
+    /// The minimum fee limit for the energy limit, in sun.
+    pub fee_limit: i64,
+}
+
+/// Estimate energy of a contract call, by binary searching the minimum energy limit for the call to succeed.
+///
+/// The energy used with a large energy limit is not enough for calls depending on the energy left, i.e. 63/64
+/// energy forwarding, or try/catch. Only the fixed ratio energy limit(after the ENERGY_LIMIT fork) is supported.
+pub fn estimate_energy(
+    manager: &Manager,
+    trigger: &contract_pb::TriggerSmartContract,
+    max_energy_limit: i64,
+) -> Result<EnergyEstimate, ExecutionError> {
+    let is_succeed = |receipt: &TransactionReceipt| {
+        receipt.vm_status == ContractStatus::Default as i32 || receipt.vm_status == ContractStatus::Success as i32
+    };
+    let run = |energy_limit: i64| {
+        execute_constant_call(manager, trigger, energy_limit, true, None)
+            .ok()
+            .filter(is_succeed)
+            .map(|receipt| receipt.resource_receipt.map(|res| res.energy).unwrap_or_default())
+    };
+
+    let (energy_limit, energy_used) = search_min_energy_limit(max_energy_limit, run)
+        .ok_or_else(|| ExecutionError::Vm("execution failed with the max energy limit".into()))?;
+
+    let owner_address = Address::try_from(&trigger.owner_address).map_err(|_| "invalid owner address")?;
+    let cntr_address = Address::try_from(&trigger.contract_address).map_err(|_| "invalid contract address")?;
+    let cntr = manager
+        .state_db
+        .get(&keys::Contract(cntr_address))
+        .map_err(ExecutionError::db)?
+        .ok_or_else(|| "contract not found")?;
+    let origin_address = Address::try_from(&cntr.origin_address).map_err(|_| "invalid origin address")?;
+    let caller_left_energy = manager
+        .state_db
+        .get(&keys::Account(owner_address))
+        .map_err(ExecutionError::db)?
+        .map(|acct| EnergyUtil::new(manager).get_left_frozen_energy(&acct))
+        .unwrap_or_default();
+
+    // Split energy as `EnergyProcessor::consume`, and energy limit as `getTotalEnergyLimitWithFixRatio`.
+    let (origin_energy_usage, caller_energy_limit) = if owner_address == origin_address {
+        (0, energy_limit)
+    } else {
+        let origin_acct = manager
+            .state_db
+            .get(&keys::Account(origin_address))
+            .map_err(ExecutionError::db)?
+            .ok_or_else(|| "origin account not found")?;
+        let util = EnergyUtil::new(manager);
+        let percent = cntr.consume_user_energy_percent;
+
+        let origin_usage = energy_used * (100 - percent) / 100;
+        let origin_usage = util.get_origin_usage(&origin_acct, cntr.origin_energy_limit, origin_usage);
+
+        let origin_left = util.get_left_frozen_energy(&origin_acct).min(cntr.origin_energy_limit);
+        (origin_usage, caller_energy_limit(energy_limit, percent, origin_left))
+    };
+    let caller_energy_usage = energy_used - origin_energy_usage;
+
+    let energy_price = manager.state_db.must_get(&keys::ChainParameter::EnergyFee);
+    Ok(EnergyEstimate {
+        energy_limit,
+        energy_used,
+        caller_energy_usage,
+        origin_energy_usage,
+        energy_fee: (caller_energy_usage - caller_left_energy).max(0) * energy_price,
+        fee_limit: (caller_energy_limit - caller_left_energy).max(0) * energy_price,
+    })
+}
+
+/// Binary search the minimum energy limit for `run` to succeed, returns the energy limit and the energy used.
+///
+/// `run` returns the energy used, or `None` if the call fails with the energy limit.
+fn search_min_energy_limit<F>(max_energy_limit: i64, mut run: F) -> Option<(i64, i64)>
+where
+    F: FnMut(i64) -> Option<i64>,
+{
+    let mut energy_used = run(max_energy_limit)?;
+    let (mut lo, mut hi) = (energy_used - 1, max_energy_limit);
+    // Most calls succeed with the energy used plus 1/64 for forwarding.
+    let optimistic = energy_used * 64 / 63;
+    if optimistic < hi {
+        if let Some(used) = run(optimistic) {
+            hi = optimistic;
+            energy_used = used;
+        } else {
+            lo = optimistic;
+        }
+    }
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        match run(mid) {
+            Some(used) => {
+                hi = mid;
+                energy_used = used;
+            }
+            None => lo = mid,
+        }
+    }
+    Some((hi, energy_used))
+}
+
+/// Energy limit paid by the caller when the origin pays part of it, as `getTotalEnergyLimitWithFixRatio`.
+fn caller_energy_limit(energy_limit: i64, consume_user_energy_percent: i64, origin_left_energy: i64) -> i64 {
+    let caller_energy_limit = if consume_user_energy_percent > 0 {
+        ((energy_limit * consume_user_energy_percent + 99) / 100).max(energy_limit - origin_left_energy)
+    } else {
+        energy_limit - origin_left_energy
+    };
+    caller_energy_limit.max(0)
+}
+
 /// TransactionTrace + RuntimeImpl.
 pub struct TransactionExecutor<'m> {
     manager: &'m mut Manager,
@@ -174,7 +342,7 @@ impl<'m> TransactionExecutor<'m> {
         &mut self,
         trigger: &contract_pb::TriggerSmartContract,
         energy_limit: i64,
-    ) -> Result<TransactionReceipt, String> {
+    ) -> Result<TransactionReceipt, ExecutionError> {
         debug!(
             "=> Execute Smart Contract, owner={} contract={}",
             b58encode_check(&trigger.owner_address()),
@@ -203,9 +371,18 @@ impl<'m> TransactionExecutor<'m> {
         txn: &IndexedTransaction,
         recover_addrs: Vec<Address>,
         block_header: &IndexedBlockHeader,
-    ) -> Result<TransactionReceipt, String> {
-        let cntr = txn.raw.raw_data.as_ref().unwrap().contract.as_ref().unwrap();
-        let cntr_type = ContractType::from_i32(cntr.r#type).expect("unhandled system contract type");
+    ) -> Result<TransactionReceipt, ExecutionError> {
+        let cntr = txn
+            .raw
+            .raw_data
+            .as_ref()
+            .unwrap()
+            .contract
+            .as_ref()
+            .ok_or("missing contract")?;
+        let cntr_type =
+            ContractType::from_i32(cntr.r#type).ok_or_else(|| format!("unknown contract type {}", cntr.r#type))?;
+        let param = cntr.parameter.as_ref().ok_or("missing contract parameter")?;
         let maybe_result = txn.raw.result.get(0);
 
         let permission_id = cntr.permission_id;
@@ -230,7 +407,7 @@ impl<'m> TransactionExecutor<'m> {
         // since some type of transaction cause bandwidth usage changes(freeze/unfreeze).
         match cntr_type {
             ContractType::TransferContract => {
-                let cntr = contract_pb::TransferContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::TransferContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> transfer from {} to {} with amount {}",
                     b58encode_check(&cntr.owner_address),
@@ -243,13 +420,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ProposalCreateContract => {
-                let cntr = contract_pb::ProposalCreateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ProposalCreateContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Proposal by {} {:?}",
                     b58encode_check(&cntr.owner_address),
@@ -267,13 +444,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ProposalApproveContract => {
-                let cntr = contract_pb::ProposalApproveContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ProposalApproveContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Approve Proposal #{} by {} {}",
                     cntr.proposal_id,
@@ -286,13 +463,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ProposalDeleteContract => {
-                let cntr = contract_pb::ProposalDeleteContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ProposalDeleteContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Delete Proposal #{} by {}",
                     cntr.proposal_id,
@@ -304,12 +481,12 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::WitnessCreateContract => {
-                let cntr = contract_pb::WitnessCreateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::WitnessCreateContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> New Witness {} url={:?}",
                     b58encode_check(cntr.owner_address()),
@@ -321,13 +498,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::WitnessUpdateContract => {
-                let cntr = contract_pb::WitnessUpdateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::WitnessUpdateContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Witness Update {} new_url={:?}",
                     b58encode_check(cntr.owner_address()),
@@ -339,13 +516,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::UpdateBrokerageContract => {
-                let cntr = contract_pb::UpdateBrokerageContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::UpdateBrokerageContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Update Witness Brokerage {}: new_brokerage_rate={}",
                     b58encode_check(cntr.owner_address()),
@@ -355,13 +532,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::FreezeBalanceContract => {
-                let cntr = contract_pb::FreezeBalanceContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::FreezeBalanceContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Freeze Resource {} amount={} resource={:?}",
@@ -375,13 +553,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::UnfreezeBalanceContract => {
-                let cntr = contract_pb::UnfreezeBalanceContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::UnfreezeBalanceContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 if cntr.receiver_address.is_empty() {
                     debug!(
@@ -403,13 +581,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::VoteWitnessContract => {
-                let cntr = contract_pb::VoteWitnessContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::VoteWitnessContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Vote Witness by {} votes: {:?}",
@@ -427,13 +605,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::AssetIssueContract => {
-                let cntr = contract_pb::AssetIssueContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::AssetIssueContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Issue Asset by {}: {:?}",
                     b58encode_check(&cntr.owner_address()),
@@ -445,14 +623,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 // TODO: Fill TransactionReceipt with newly created asset token_id.
                 Ok(ctx.into())
             }
             ContractType::UpdateAssetContract => {
-                let cntr = contract_pb::UpdateAssetContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::UpdateAssetContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!("=> Asset Update {}: {:?}", b58encode_check(&cntr.owner_address()), cntr);
 
                 let mut ctx = TransactionContext::new(&block_header, &txn);
@@ -460,13 +638,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::UnfreezeAssetContract => {
-                let cntr = contract_pb::UnfreezeAssetContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::UnfreezeAssetContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Asset Unfreeze {}: {:?}",
                     b58encode_check(&cntr.owner_address()),
@@ -478,13 +656,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::TransferAssetContract => {
-                let cntr = contract_pb::TransferAssetContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::TransferAssetContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Transfer Asset from {} to {}: amount={} asset_name={:?}",
                     b58encode_check(&cntr.owner_address()),
@@ -498,14 +676,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ParticipateAssetIssueContract => {
                 let cntr =
-                    contract_pb::ParticipateAssetIssueContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                    contract_pb::ParticipateAssetIssueContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> Participate Asset Issue {}, to {}: token_id={} amount={}",
                     b58encode_check(&cntr.owner_address()),
@@ -519,13 +697,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::AccountUpdateContract => {
-                let cntr = contract_pb::AccountUpdateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::AccountUpdateContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Account Set Name {}: name={:?}",
@@ -537,13 +715,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::SetAccountIdContract => {
-                let cntr = contract_pb::SetAccountIdContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::SetAccountIdContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Account Set ID {}: name={:?}",
@@ -555,13 +733,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::AccountCreateContract => {
-                let cntr = contract_pb::AccountCreateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::AccountCreateContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Create Account By {}: {:?}, type={:?}",
@@ -575,14 +753,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::AccountPermissionUpdateContract => {
-                let cntr =
-                    contract_pb::AccountPermissionUpdateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::AccountPermissionUpdateContract::from_any(param)
+                    .ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Account Permission Update {}",
@@ -593,13 +771,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::WithdrawBalanceContract => {
-                let cntr = contract_pb::WithdrawBalanceContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::WithdrawBalanceContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!("=> Withdraw Reward {}", b58encode_check(&cntr.owner_address()),);
                 let mut ctx = TransactionContext::new(&block_header, &txn);
@@ -607,13 +786,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::UpdateSettingContract => {
-                let cntr = contract_pb::UpdateSettingContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::UpdateSettingContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Update Contract setting {}, contract={}",
@@ -625,13 +805,15 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::UpdateEnergyLimitContract => {
-                let cntr = contract_pb::UpdateEnergyLimitContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr =
+                    contract_pb::UpdateEnergyLimitContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Update Contract origin_energy_limit {}, contract={}",
@@ -643,13 +825,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ClearAbiContract => {
-                let cntr = contract_pb::ClearAbiContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ClearAbiContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 debug!(
                     "=> Clear Contract ABI {}, contract={}",
@@ -661,7 +844,8 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
@@ -670,7 +854,7 @@ impl<'m> TransactionExecutor<'m> {
             ContractType::CreateSmartContract => {
                 // See-also: https://github.com/opentron/opentron/issues/34
                 // Sea-also: https://github.com/opentron/opentron/issues/38
-                let raw_cntr = &cntr.parameter.as_ref().unwrap().value[..];
+                let raw_cntr = &param.value[..];
                 let maybe_cntr = contract_pb::CreateSmartContract::decode(raw_cntr);
 
                 let cntr = match maybe_cntr {
@@ -722,16 +906,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 // NOTE: vm must be strictly checked.
-                if !check_transaction_result(&exec_result, &maybe_result) {
-                    debug!("result => {:?}", exec_result);
-                    return Err("result check not passed!".into());
-                }
+                self.verify_transaction_result(&ctx, txn, &exec_result, true)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::TriggerSmartContract => {
-                let cntr = contract_pb::TriggerSmartContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::TriggerSmartContract::from_any(param).ok_or("invalid contract parameter")?;
                 let contract_status = maybe_result
                     .and_then(|ret| ContractStatus::from_i32(ret.contract_status))
                     .unwrap_or_default();
@@ -747,15 +928,13 @@ impl<'m> TransactionExecutor<'m> {
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                if !check_transaction_result(&exec_result, &maybe_result) {
-                    debug!("result => {:?}", exec_result);
-                    return Err("result check not passed!".into());
-                }
+                // NOTE: vm must be strictly checked.
+                self.verify_transaction_result(&ctx, txn, &exec_result, true)?;
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ExchangeCreateContract => {
-                let cntr = contract_pb::ExchangeCreateContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ExchangeCreateContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> ExchangeCreate by {}: {}:{} <=> {}:{}",
                     b58encode_check(&cntr.owner_address()),
@@ -770,13 +949,14 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
                 debug!("context => {:?}", ctx);
 
                 Ok(ctx.into())
             }
             ContractType::ExchangeWithdrawContract => {
-                let cntr = contract_pb::ExchangeWithdrawContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr =
+                    contract_pb::ExchangeWithdrawContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> ExchangeWithdraw by {}: exchange#{} {}:{}",
                     b58encode_check(&cntr.owner_address()),
@@ -790,13 +970,13 @@ impl<'m> TransactionExecutor<'m> {
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
                 debug!("context => {:?}", ctx);
 
                 Ok(ctx.into())
             }
             ContractType::ExchangeInjectContract => {
-                let cntr = contract_pb::ExchangeInjectContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr = contract_pb::ExchangeInjectContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> ExchangeInject by {}: exchange#{} {}:{}",
                     b58encode_check(&cntr.owner_address()),
@@ -810,14 +990,14 @@ impl<'m> TransactionExecutor<'m> {
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
                 debug!("context => {:?}", ctx);
 
                 Ok(ctx.into())
             }
             ContractType::ExchangeTransactionContract => {
                 let cntr =
-                    contract_pb::ExchangeTransactionContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                    contract_pb::ExchangeTransactionContract::from_any(param).ok_or("invalid contract parameter")?;
                 debug!(
                     "=> ExchangeTransaction by {}: exchange#{} {}:{} expected={}",
                     b58encode_check(&cntr.owner_address()),
@@ -832,14 +1012,55 @@ impl<'m> TransactionExecutor<'m> {
                 BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 let exec_result = cntr.execute(self.manager, &mut ctx)?;
-                check_transaction_result(&exec_result, &maybe_result);
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
+                debug!("context => {:?}", ctx);
+
+                Ok(ctx.into())
+            }
+            ContractType::MarketSellAssetContract => {
+                let cntr = contract_pb::MarketSellAssetContract::from_any(param).ok_or("invalid contract parameter")?;
+                debug!(
+                    "=> MarketSellAsset by {}: {}:{} => {}:{}",
+                    b58encode_check(&cntr.owner_address()),
+                    String::from_utf8_lossy(&cntr.sell_token_id),
+                    cntr.sell_token_quantity,
+                    String::from_utf8_lossy(&cntr.buy_token_id),
+                    cntr.buy_token_quantity
+                );
+
+                let mut ctx = TransactionContext::new(&block_header, &txn);
+                cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
+                cntr.validate(self.manager, &mut ctx)?;
+                BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
+
                 debug!("context => {:?}", ctx);
+                Ok(ctx.into())
+            }
+            ContractType::MarketCancelOrderContract => {
+                let cntr =
+                    contract_pb::MarketCancelOrderContract::from_any(param).ok_or("invalid contract parameter")?;
+                debug!(
+                    "=> MarketCancelOrder by {}: order={}",
+                    b58encode_check(&cntr.owner_address()),
+                    hex::encode(&cntr.order_id)
+                );
 
+                let mut ctx = TransactionContext::new(&block_header, &txn);
+                cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
+                cntr.validate(self.manager, &mut ctx)?;
+                BandwidthProcessor::new(self.manager, txn, &cntr)?.consume(&mut ctx)?;
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
+
+                debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             #[cfg(feature = "nile")]
             ContractType::ShieldedTransferContract => {
-                let cntr = contract_pb::ShieldedTransferContract::from_any(cntr.parameter.as_ref().unwrap()).unwrap();
+                let cntr =
+                    contract_pb::ShieldedTransferContract::from_any(param).ok_or("invalid contract parameter")?;
 
                 log::warn!("=> Shielded Transaction, use dummy implementation");
                 // NOTE: dummy implementation
@@ -848,18 +1069,68 @@ impl<'m> TransactionExecutor<'m> {
                 // cntr.validate_signature(permission_id, recover_addrs, self.manager, &mut ctx)?;
                 cntr.validate(self.manager, &mut ctx)?;
                 // NOTE: Shielded transaction won't consume bandwidth.
-                check_transaction_result(&cntr.execute(self.manager, &mut ctx)?, &maybe_result);
+                let exec_result = cntr.execute(self.manager, &mut ctx)?;
+                self.verify_transaction_result(&ctx, txn, &exec_result, false)?;
 
                 debug!("context => {:?}", ctx);
                 Ok(ctx.into())
             }
             ContractType::ObsoleteVoteAssetContract |
             ContractType::ObsoleteCustomContract |
-            ContractType::ObsoleteGetContract => unreachable!("OBSOLETE: {:?}", cntr_type),
+            ContractType::ObsoleteGetContract => Err(format!("obsolete contract type {:?}", cntr_type).into()),
             #[allow(unreachable_patterns)]
-            _ => unimplemented!("TODO: handle contract type {:?}", cntr_type),
+            _ => Err(format!("unsupported contract type {:?}", cntr_type).into()),
         }
     }
+
+    /// Compare the execution result with the result recorded in the transaction.
+    ///
+    /// Mismatch fails the transaction when `must_match` is set or the manager is in strict mode.
+    /// In strict mode, the mismatch is also recorded to the manager.
+    fn verify_transaction_result(
+        &mut self,
+        ctx: &TransactionContext,
+        txn: &IndexedTransaction,
+        exec_result: &TransactionResult,
+        must_match: bool,
+    ) -> Result<(), ExecutionError> {
+        let maybe_result = txn.raw.result.get(0);
+        if check_transaction_result(exec_result, &maybe_result) {
+            return Ok(());
+        }
+        if !must_match && !self.manager.strict_result_check {
+            return Ok(());
+        }
+        if self.manager.strict_result_check {
+            let cntr = txn.raw.raw_data.as_ref().unwrap().contract.as_ref().unwrap();
+            self.manager.result_mismatch = Some(ResultMismatch {
+                block_number: ctx.block_header.number(),
+                transaction_hash: txn.hash,
+                contract_type: ContractType::from_i32(cntr.r#type).expect("unhandled system contract type"),
+                expected: maybe_result.cloned().unwrap_or_default(),
+                actual: exec_result.clone(),
+                energy: ctx.energy,
+                energy_fee: ctx.energy_fee,
+                touched_keys: vec![],
+            });
+        }
+        Err(ExecutionError::ResultMismatch)
+    }
+}
+
+/// Divergence of the execution result from the result recorded in the block.
+#[derive(Debug, Clone)]
+pub struct ResultMismatch {
+    pub block_number: i64,
+    pub transaction_hash: H256,
+    pub contract_type: ContractType,
+    pub expected: TransactionResult,
+    pub actual: TransactionResult,
+    // NOTE: energy is not recorded in blocks, only reported for reference.
+    pub energy: i64,
+    pub energy_fee: i64,
+    /// State keys touched by the transaction, as `(column, key)`.
+    pub touched_keys: Vec<(usize, Vec<u8>)>,
 }
 
 #[inline]
@@ -877,3 +1148,37 @@ fn check_transaction_result(exec_result: &TransactionResult, maybe_result: &Opti
     }
     return true;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_min_energy_limit() {
+        // A call forwarding 63/64 of the energy left to an inner call using 6300.
+        let run = |energy_limit: i64| {
+            if (energy_limit - 1000) * 63 / 64 >= 6300 {
+                Some(1000 + 6300)
+            } else {
+                None
+            }
+        };
+        let (energy_limit, energy_used) = search_min_energy_limit(1_000_000, run).unwrap();
+        assert_eq!(energy_used, 7300);
+        assert!(run(energy_limit).is_some());
+        assert!(run(energy_limit - 1).is_none());
+
+        assert_eq!(search_min_energy_limit(1_000, run), None);
+        assert_eq!(search_min_energy_limit(1_000, |_| Some(500)), Some((500, 500)));
+    }
+
+    #[test]
+    fn test_caller_energy_limit() {
+        assert_eq!(caller_energy_limit(10_000, 100, 0), 10_000);
+        assert_eq!(caller_energy_limit(10_000, 0, 4_000), 6_000);
+        assert_eq!(caller_energy_limit(10_000, 0, 20_000), 0);
+        // at least `percent` of the energy limit is paid by the caller
+        assert_eq!(caller_energy_limit(10_000, 30, 9_000), 3_000);
+        assert_eq!(caller_energy_limit(10_000, 30, 2_000), 8_000);
+    }
+}
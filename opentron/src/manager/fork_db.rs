@@ -0,0 +1,150 @@
+//! In-memory store of unsolidified blocks, from all known branches.
+//!
+//! Renamed: KhaosDatabase
+
+use std::collections::{BTreeMap, HashMap};
+
+use chain::IndexedBlock;
+use primitive_types::H256;
+
+/// Blocks above the solid block number, indexed by hash.
+#[derive(Default)]
+pub struct ForkDB {
+    blocks: HashMap<H256, IndexedBlock>,
+    // block number => [block hash]
+    numbers: BTreeMap<i64, Vec<H256>>,
+}
+
+impl ForkDB {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &H256) -> Option<&IndexedBlock> {
+        self.blocks.get(hash)
+    }
+
+    pub fn insert(&mut self, block: IndexedBlock) {
+        let hash = *block.hash();
+        if self.blocks.contains_key(&hash) {
+            return;
+        }
+        self.numbers.entry(block.number()).or_default().push(hash);
+        self.blocks.insert(hash, block);
+    }
+
+    /// Remove a block and all its known descendants. Used when a block is found invalid.
+    pub fn remove_with_descendants(&mut self, hash: &H256) {
+        let mut bad = vec![*hash];
+        while let Some(hash) = bad.pop() {
+            if let Some(block) = self.blocks.remove(&hash) {
+                if let Some(hashes) = self.numbers.get_mut(&block.number()) {
+                    hashes.retain(|h| h != &hash);
+                }
+                let children = self
+                    .numbers
+                    .get(&(block.number() + 1))
+                    .map(|hashes| {
+                        hashes
+                            .iter()
+                            .filter(|h| self.blocks[h].parent_hash() == hash.as_bytes())
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                bad.extend(children);
+            }
+        }
+    }
+
+    /// Drop all blocks whose number is less than or equal to `block_number`.
+    pub fn prune(&mut self, block_number: i64) {
+        let kept = self.numbers.split_off(&(block_number + 1));
+        for hash in self.numbers.values().flatten() {
+            self.blocks.remove(hash);
+        }
+        self.numbers = kept;
+    }
+
+    /// Walk back from `head` until reaching a block accepted by `is_main_chain`.
+    ///
+    /// Returns the common ancestor and the branch in ascending order, or `None` if the branch
+    /// is not linked to the main chain.
+    pub fn branch_of<F>(&self, head: &H256, is_main_chain: F) -> Option<(H256, Vec<H256>)>
+    where
+        F: Fn(&H256) -> bool,
+    {
+        let mut branch = vec![];
+        let mut hash = *head;
+        while !is_main_chain(&hash) {
+            let block = self.blocks.get(&hash)?;
+            branch.push(hash);
+            hash = H256::from_slice(block.parent_hash());
+        }
+        branch.reverse();
+        Some((hash, branch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::{BlockHeader, IndexedBlockHeader};
+    use proto2::chain::block_header::Raw as BlockHeaderRaw;
+
+    fn block_of(number: i64, parent: &H256, timestamp: i64) -> IndexedBlock {
+        let header = BlockHeader {
+            raw_data: Some(BlockHeaderRaw {
+                number,
+                parent_hash: parent.as_bytes().to_vec(),
+                timestamp,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        IndexedBlock::new(IndexedBlockHeader::from_raw(header).unwrap(), vec![])
+    }
+
+    #[test]
+    fn test_branch_of_fork() {
+        let genesis = H256::repeat_byte(0xaa);
+        let mut db = ForkDB::new();
+
+        let b1 = block_of(1, &genesis, 3_000);
+        let b2 = block_of(2, b1.hash(), 6_000);
+        // competing branch from b1
+        let b2x = block_of(2, b1.hash(), 9_000);
+        let b3x = block_of(3, b2x.hash(), 12_000);
+
+        for blk in vec![b1.clone(), b2.clone(), b2x.clone(), b3x.clone()] {
+            db.insert(blk);
+        }
+        assert_eq!(db.len(), 4);
+
+        let main = vec![genesis, *b1.hash(), *b2.hash()];
+        let (ancestor, branch) = db.branch_of(b3x.hash(), |h| main.contains(h)).unwrap();
+        assert_eq!(ancestor, *b1.hash());
+        assert_eq!(branch, vec![*b2x.hash(), *b3x.hash()]);
+
+        // unlinked block
+        let orphan = block_of(5, &H256::repeat_byte(0xbb), 15_000);
+        db.insert(orphan.clone());
+        assert!(db.branch_of(orphan.hash(), |h| main.contains(h)).is_none());
+
+        db.remove_with_descendants(b2x.hash());
+        assert!(!db.contains(b3x.hash()));
+        assert!(db.contains(b2.hash()));
+
+        db.prune(1);
+        assert!(!db.contains(b1.hash()));
+        assert_eq!(db.len(), 2);
+    }
+}
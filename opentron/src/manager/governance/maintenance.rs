@@ -7,6 +7,7 @@ use log::{debug, info};
 use proto2::state::{Witness, WitnessVoterReward};
 use state::keys;
 
+use super::super::executor::ExecutionError;
 use super::super::Manager;
 
 /// Massive things done during maintenance.
@@ -19,7 +20,7 @@ impl MaintenanceManager<'_> {
         MaintenanceManager { manager }
     }
 
-    pub fn apply_block(mut self, block: &IndexedBlock) -> Result<(), String> {
+    pub fn apply_block(mut self, block: &IndexedBlock) -> Result<(), ExecutionError> {
         let next_maintenance_time = self
             .manager
             .state_db
@@ -78,7 +79,7 @@ impl MaintenanceManager<'_> {
         Ok(())
     }
 
-    fn do_maintenance(&mut self) -> Result<(), String> {
+    fn do_maintenance(&mut self) -> Result<(), ExecutionError> {
         // 0: default (unremoved)
         // 1: remove now
         // -1: removed
@@ -91,7 +92,7 @@ impl MaintenanceManager<'_> {
             .manager
             .state_db
             .get(&keys::DynamicProperty::HasNewVotesInCurrentEpoch)
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
             .unwrap_or(0) !=
             0;
 
@@ -103,7 +104,7 @@ impl MaintenanceManager<'_> {
             self.manager
                 .state_db
                 .put_key(keys::DynamicProperty::HasNewVotesInCurrentEpoch, 0)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
 
             let old_active_witnesses = self.manager.get_active_witnesses();
 
@@ -121,7 +122,7 @@ impl MaintenanceManager<'_> {
                 self.manager
                     .state_db
                     .put_key(keys::Witness(wit_addr), wit)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
 
             self.update_witness_schedule();
@@ -157,7 +158,7 @@ impl MaintenanceManager<'_> {
     ///
     /// NOTE: The implementation is different from java-tron.
     /// The votes are already counted and saved in Witness store.
-    fn count_votes(&self) -> Result<HashMap<Address, i64>, String> {
+    fn count_votes(&self) -> Result<HashMap<Address, i64>, ExecutionError> {
         let mut votes: HashMap<Address, i64> = HashMap::new();
         {
             let votes = &mut votes;
@@ -173,7 +174,7 @@ impl MaintenanceManager<'_> {
         &mut self,
         current_next_maintenance_time: i64,
         block_ts: i64,
-    ) -> Result<(), String> {
+    ) -> Result<(), ExecutionError> {
         let maintenance_interval = self
             .manager
             .state_db
@@ -192,7 +193,7 @@ impl MaintenanceManager<'_> {
     ///
     /// NOTE: Witness re-scheduling only occurs when new votes found.
     /// So when removing power of GR, witness schedule's vote_count should be updated as well.
-    fn remove_power_of_gr(&mut self) -> Result<(), String> {
+    fn remove_power_of_gr(&mut self) -> Result<(), ExecutionError> {
         let mut wit_sched = self.manager.state_db.must_get(&keys::WitnessSchedule);
         debug!("before => {:?}", wit_sched);
         for gr_wit in &self.manager.genesis_config.witnesses {
@@ -203,7 +204,7 @@ impl MaintenanceManager<'_> {
             self.manager
                 .state_db
                 .put_key(keys::Witness(addr), witness)
-                .map_err(|_| "insert db error")?;
+                .map_err(ExecutionError::db)?;
 
             wit_sched
                 .iter_mut()
@@ -213,11 +214,11 @@ impl MaintenanceManager<'_> {
         self.manager
             .state_db
             .put_key(keys::WitnessSchedule, wit_sched)
-            .map_err(|_| "insert db error")?;
+            .map_err(ExecutionError::db)?;
         self.manager
             .state_db
             .put_key(keys::ChainParameter::RemovePowerOfGr, -1)
-            .map_err(|_| "insert db error")?;
+            .map_err(ExecutionError::db)?;
         Ok(())
     }
 
@@ -267,14 +268,17 @@ impl MaintenanceManager<'_> {
         let pay_per_vote = total_pay as f64 / total_vote_count as f64;
 
         if total_pay != 0 {
+            let mut total_paid = 0;
             for (addr, vote_weight) in addrs.into_iter().zip(vote_counts.into_iter()) {
                 let pay = (vote_weight as f64 * pay_per_vote) as i64;
                 if pay != 0 {
                     let mut acct = self.manager.state_db.must_get(&keys::Account(addr));
                     acct.allowance += pay;
                     self.manager.state_db.put_key(keys::Account(addr), acct).unwrap();
+                    total_paid += pay;
                 }
             }
+            self.manager.add_issued_reward(total_paid);
         }
     }
 }
@@ -7,6 +7,7 @@ use proto2::state::{proposal::State as ProposalState, Proposal};
 use state::keys;
 use state::keys::ChainParameter;
 
+use super::super::executor::ExecutionError;
 use super::super::version_fork::ForkController;
 use super::super::Manager;
 
@@ -20,7 +21,7 @@ impl ProposalController<'_> {
         ProposalController { manager }
     }
 
-    pub fn process_proposals(&mut self) -> Result<(), String> {
+    pub fn process_proposals(&mut self) -> Result<(), ExecutionError> {
         let latest_proposal_id = self.manager.state_db.must_get(&keys::DynamicProperty::LatestProposalId);
         if latest_proposal_id == 0 {
             debug!("no proposal yet");
@@ -60,7 +61,7 @@ impl ProposalController<'_> {
         Ok(())
     }
 
-    fn process_proposal(&mut self, mut proposal: Proposal) -> Result<(), String> {
+    fn process_proposal(&mut self, mut proposal: Proposal) -> Result<(), ExecutionError> {
         let active_witnesses = self.manager.get_active_witnesses();
         if active_witnesses.len() != constants::MAX_NUM_OF_ACTIVE_WITNESSES {
             info!("current number of active witnesses: {}", active_witnesses.len());
@@ -82,13 +83,13 @@ impl ProposalController<'_> {
                 self.manager
                     .state_db
                     .put_key(keys::ChainParameter::from_i32(param as i32).unwrap(), value)
-                    .map_err(|_| "db insert error")?;
+                    .map_err(ExecutionError::db)?;
             }
             proposal.state = ProposalState::Approved as i32;
             self.manager
                 .state_db
                 .put_key(keys::Proposal(proposal.proposal_id), proposal)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
         } else {
             // disapprove
             info!(
@@ -99,7 +100,7 @@ impl ProposalController<'_> {
             self.manager
                 .state_db
                 .put_key(keys::Proposal(proposal.proposal_id), proposal)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
         }
         Ok(())
     }
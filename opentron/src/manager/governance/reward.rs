@@ -6,6 +6,7 @@ use log::debug;
 use proto2::state::Votes;
 use state::keys;
 
+use super::super::executor::ExecutionError;
 use super::super::Manager;
 
 /// Controller to handle rewards. Renamed from DelegationService, which is ambiguous.
@@ -21,7 +22,7 @@ impl RewardController<'_> {
     }
 
     // NOTE: this is a merged logic of `payBlockReward` + `payStandbyWitness`.
-    pub fn pay_reward(&mut self, block: &IndexedBlock) -> Result<(), String> {
+    pub fn pay_reward(&mut self, block: &IndexedBlock) -> Result<(), ExecutionError> {
         let curr_wit_addr = *Address::from_bytes(block.witness());
 
         let wit_sched = self.manager.state_db.must_get(&keys::WitnessSchedule);
@@ -50,6 +51,7 @@ impl RewardController<'_> {
         };
 
         let epoch = self.manager.state_db.must_get(&keys::DynamicProperty::CurrentEpoch);
+        let mut total_paid = 0;
         // payReward
         for ((wit_addr, vote_count, brokerage), mut wit_acct) in wit_sched.into_iter().zip(wit_accts.into_iter()) {
             let mut voters_reward = 0;
@@ -61,8 +63,9 @@ impl RewardController<'_> {
                 let brokerage_amount = (pay as f64 * brokerage_rate) as i64;
                 voters_reward += pay - brokerage_amount;
                 if wit_acct.adjust_allowance(brokerage_amount).is_err() {
-                    return Err("math overflow while adding brokerage amount".into());
+                    return Err(ExecutionError::Overflow("math overflow while adding brokerage amount".into()));
                 }
+                total_paid += pay;
             }
 
             // payBlockReward
@@ -70,8 +73,9 @@ impl RewardController<'_> {
                 let brokerage_amount = (block_reward as f64 * brokerage_rate) as i64;
                 voters_reward += block_reward - brokerage_amount;
                 if wit_acct.adjust_allowance(brokerage_amount).is_err() {
-                    return Err("math overflow while adding brokerage amount".into());
+                    return Err(ExecutionError::Overflow("math overflow while adding brokerage amount".into()));
                 }
+                total_paid += block_reward;
             }
             // save reward of voters
             // delegationStore.addReward(cycle, witnessAddress, value);
@@ -79,8 +83,9 @@ impl RewardController<'_> {
             self.manager
                 .state_db
                 .put_key(keys::Account(wit_addr), wit_acct)
-                .map_err(|_| "db insert error")?;
+                .map_err(ExecutionError::db)?;
         }
+        self.manager.add_issued_reward(total_paid);
 
         Ok(())
     }
@@ -95,7 +100,7 @@ impl RewardController<'_> {
 
     // withdrawReward
     /// Update an account's allowance and reset voting epoch status.
-    pub fn withdraw_reward(&mut self, addr: Address) -> Result<(), String> {
+    pub fn withdraw_reward(&mut self, addr: Address) -> Result<(), ExecutionError> {
         if self
             .manager
             .state_db
@@ -109,7 +114,7 @@ impl RewardController<'_> {
             .manager
             .state_db
             .get(&keys::Votes(addr))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
         {
             let curr_epoch = self.manager.state_db.must_get(&keys::DynamicProperty::CurrentEpoch);
             if votes.epoch == curr_epoch {
@@ -148,7 +153,7 @@ impl RewardUtil<'_> {
     }
 
     // DelegationService.queryReward.
-    pub fn query_reward(&self, addr: Address) -> Result<i64, String> {
+    pub fn query_reward(&self, addr: Address) -> Result<i64, ExecutionError> {
         let allow_change_delegation = self
             .manager
             .state_db
@@ -162,7 +167,7 @@ impl RewardUtil<'_> {
             .manager
             .state_db
             .get(&keys::Votes(addr))
-            .map_err(|_| "db query error")?
+            .map_err(ExecutionError::db)?
         {
             let curr_epoch = self.manager.state_db.must_get(&keys::DynamicProperty::CurrentEpoch);
             if votes.epoch == curr_epoch {
@@ -180,7 +185,7 @@ impl RewardUtil<'_> {
         }
     }
 
-    fn compute_reward(&self, epoch: i64, votes: &Votes) -> Result<i64, String> {
+    fn compute_reward(&self, epoch: i64, votes: &Votes) -> Result<i64, ExecutionError> {
         let mut reward_amount = 0_i64;
         for vote in &votes.votes {
             let wit_addr = *Address::from_bytes(&vote.vote_address);
@@ -188,7 +193,7 @@ impl RewardUtil<'_> {
                 .manager
                 .state_db
                 .get(&keys::VoterReward(epoch, wit_addr))
-                .map_err(|_| "db query error")?
+                .map_err(ExecutionError::db)?
             {
                 if total_reward.vote_count == 0 {
                     continue;
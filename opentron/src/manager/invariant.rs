@@ -0,0 +1,354 @@
+//! Invariants of the state-db, to catch silent state corruption. Used by `opentron check state`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use ::keys::Address;
+use log::info;
+use proto2::state::market_order::State as OrderState;
+use state::keys;
+
+use super::governance::reward::RewardUtil;
+use super::Manager;
+
+/// A broken invariant, with the offending key.
+#[derive(Debug)]
+pub struct Violation {
+    pub invariant: &'static str,
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.invariant, self.key, self.message)
+    }
+}
+
+/// Walks the whole state-db, collecting violations of all invariants.
+pub struct StateChecker<'m> {
+    manager: &'m Manager,
+    violations: Vec<Violation>,
+}
+
+impl StateChecker<'_> {
+    pub fn new<'a>(manager: &'a Manager) -> StateChecker<'a> {
+        StateChecker {
+            manager,
+            violations: vec![],
+        }
+    }
+
+    pub fn check(mut self) -> Vec<Violation> {
+        self.check_supply();
+        self.check_vote_counts();
+        self.check_resource_delegation_index();
+        self.check_contract_code();
+        self.check_account_index();
+        self.violations
+    }
+
+    fn violate<K: fmt::Display>(&mut self, invariant: &'static str, key: K, message: String) {
+        self.violations.push(Violation {
+            invariant,
+            key: key.to_string(),
+            message,
+        });
+    }
+
+    /// TRX owned by accounts, locked in exchanges and market orders, plus unclaimed voter rewards,
+    /// should be genesis allocations plus issued rewards.
+    ///
+    /// Fees are paid to the blackhole account, so only voter reward pools can lose TRX: rewards are truncated
+    /// when claimed, and the share of genesis votes is never claimed. The shortfall is bounded by what's left
+    /// in the pools.
+    ///
+    /// Issued rewards are only tracked by state-dbs synced from genesis by this version, on others the check is
+    /// reported as failed instead of passing silently.
+    fn check_supply(&mut self) {
+        let manager = self.manager;
+        let mut violations = vec![];
+
+        let mut account_supply = 0_i64;
+        let mut unclaimed_voter_reward = 0_i64;
+        manager.state_db.for_each(|key: &keys::Account, acct| {
+            let amounts = [
+                ("balance", acct.balance),
+                ("frozen_amount_for_bandwidth", acct.frozen_amount_for_bandwidth),
+                ("frozen_amount_for_energy", acct.frozen_amount_for_energy),
+                ("delegated_out_amount", acct.delegated_out_amount),
+                ("allowance", acct.allowance),
+            ];
+            for &(name, amount) in &amounts {
+                if amount < 0 {
+                    violations.push((key.0, format!("negative {}: {}", name, amount)));
+                }
+                account_supply += amount;
+            }
+        });
+        manager.state_db.for_each(|key: &keys::Votes, _| {
+            unclaimed_voter_reward += RewardUtil::new(manager).query_reward(key.0).unwrap_or_default();
+        });
+        let mut voter_reward_pools = 0_i64;
+        manager.state_db.for_each(|_: &keys::VoterReward, reward| {
+            voter_reward_pools += reward.reward_amount;
+        });
+        for (addr, message) in violations {
+            self.violate("supply", addr, message);
+        }
+
+        let mut exchange_supply = 0_i64;
+        manager.state_db.for_each(|_: &keys::Exchange, exchange| {
+            if exchange.first_token_id == 0 {
+                exchange_supply += exchange.first_token_balance;
+            }
+            if exchange.second_token_id == 0 {
+                exchange_supply += exchange.second_token_balance;
+            }
+        });
+
+        let mut market_supply = 0_i64;
+        manager.state_db.for_each(|_: &keys::MarketOrder, order| {
+            if order.state == OrderState::Active as i32 && order.sell_token_id == 0 {
+                market_supply += order.sell_token_quantity_remain;
+            }
+        });
+
+        let genesis_supply: i64 = manager.genesis_config.allocs.iter().map(|alloc| alloc.balance).sum();
+        let supply = account_supply + exchange_supply + market_supply + unclaimed_voter_reward;
+
+        info!(
+            "supply: accounts={} exchanges={} market_orders={} unclaimed_voter_reward={} genesis={}",
+            account_supply, exchange_supply, market_supply, unclaimed_voter_reward, genesis_supply
+        );
+
+        match manager.state_db.get(&keys::DynamicProperty::TotalIssuedReward).unwrap() {
+            Some(issued_reward) => {
+                let expected = genesis_supply + issued_reward;
+                // Claimed rewards are also counted, so this is an upper bound of the unclaimable rewards.
+                let max_unclaimable = voter_reward_pools - unclaimed_voter_reward;
+                info!(
+                    "supply: issued_reward={} voter_reward_pools={} shortfall={}",
+                    issued_reward,
+                    voter_reward_pools,
+                    expected - supply
+                );
+                if supply > expected {
+                    self.violate(
+                        "supply",
+                        "kTotalIssuedReward",
+                        format!(
+                            "supply {} exceeds genesis allocations plus issued rewards {} by {}",
+                            supply,
+                            expected,
+                            supply - expected
+                        ),
+                    );
+                } else if expected - supply > max_unclaimable {
+                    self.violate(
+                        "supply",
+                        "kTotalIssuedReward",
+                        format!(
+                            "supply {} is short of genesis allocations plus issued rewards {} by {}, \
+                             more than {} left in voter reward pools",
+                            supply,
+                            expected,
+                            expected - supply,
+                            max_unclaimable
+                        ),
+                    );
+                }
+            }
+            None => self.violate(
+                "supply",
+                "kTotalIssuedReward",
+                "issued rewards are not tracked in this state-db, supply can not be checked, \
+                 resync from genesis to track them"
+                    .into(),
+            ),
+        }
+    }
+
+    /// Vote count of each witness should be the sum of votes for it, plus its genesis votes
+    /// if the power of GR is not removed yet.
+    fn check_vote_counts(&mut self) {
+        let manager = self.manager;
+
+        let mut vote_counts: HashMap<Address, i64> = HashMap::new();
+        manager.state_db.for_each(|_: &keys::Votes, votes| {
+            for vote in &votes.votes {
+                *vote_counts.entry(*Address::from_bytes(&vote.vote_address)).or_default() += vote.vote_count;
+            }
+        });
+        if manager.state_db.must_get(&keys::ChainParameter::RemovePowerOfGr) != -1 {
+            for gr_wit in &manager.genesis_config.witnesses {
+                let addr = gr_wit.address.parse::<Address>().expect("address format error");
+                *vote_counts.entry(addr).or_default() += gr_wit.votes;
+            }
+        }
+
+        let mut witnesses = vec![];
+        manager.state_db.for_each(|key: &keys::Witness, wit| {
+            witnesses.push((key.0, wit.vote_count));
+        });
+        for (addr, vote_count) in witnesses {
+            let counted = vote_counts.remove(&addr).unwrap_or_default();
+            if vote_count != counted {
+                self.violate(
+                    "vote_count",
+                    addr,
+                    format!("witness vote count {}, but {} votes found", vote_count, counted),
+                );
+            }
+        }
+        for (addr, counted) in vote_counts {
+            if counted != 0 {
+                self.violate("vote_count", addr, format!("{} votes for a non-witness", counted));
+            }
+        }
+    }
+
+    /// `ResourceDelegationIndex` of a delegator should list exactly the receivers in `ResourceDelegation`.
+    fn check_resource_delegation_index(&mut self) {
+        let manager = self.manager;
+
+        let mut delegations: HashSet<(Address, Address)> = HashSet::new();
+        manager.state_db.for_each(|key: &keys::ResourceDelegation, _| {
+            // Frozen resource of oneself is saved as a delegation to oneself, which is not indexed.
+            if key.0 != key.1 {
+                delegations.insert((key.0, key.1));
+            }
+        });
+
+        let mut indexed = vec![];
+        manager
+            .state_db
+            .for_each(|key: &keys::ResourceDelegationIndex, to_addrs| {
+                indexed.push((key.0, to_addrs.clone()));
+            });
+        for (from, to_addrs) in indexed {
+            for to in to_addrs {
+                if !delegations.remove(&(from, to)) {
+                    self.violate("resource_delegation", from, format!("indexed delegation to {} not found", to));
+                }
+            }
+        }
+        for (from, to) in delegations {
+            self.violate("resource_delegation", from, format!("delegation to {} is not indexed", to));
+        }
+    }
+
+    /// Every `Contract` should have a `ContractCode`.
+    fn check_contract_code(&mut self) {
+        let manager = self.manager;
+
+        let mut addrs = vec![];
+        manager.state_db.for_each(|key: &keys::Contract, _| addrs.push(key.0));
+        for addr in addrs {
+            if manager.state_db.get(&keys::ContractCode(addr)).unwrap().is_none() {
+                self.violate("contract_code", addr, "contract code not found".into());
+            }
+        }
+    }
+
+    /// `AccountIndex` should point to an account of the same name.
+    fn check_account_index(&mut self) {
+        let manager = self.manager;
+
+        let mut indexed = vec![];
+        manager.state_db.for_each(|key: &keys::AccountIndex, addr| {
+            indexed.push((key.0.clone(), *addr));
+        });
+        for (name, addr) in indexed {
+            match manager.state_db.get(&keys::Account(addr)).unwrap() {
+                Some(acct) if acct.name == name => {}
+                Some(acct) => self.violate(
+                    "account_index",
+                    format!("{:?}", name),
+                    format!("points to {}, which is named {:?}", addr, acct.name),
+                ),
+                None => self.violate(
+                    "account_index",
+                    format!("{:?}", name),
+                    format!("points to {}, which is not found", addr),
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto2::state::WitnessVoterReward;
+
+    fn supply_violations(manager: &Manager) -> Vec<String> {
+        let mut checker = StateChecker::new(manager);
+        checker.check_supply();
+        checker.violations.into_iter().map(|v| v.message).collect()
+    }
+
+    fn adjust_balance(manager: &mut Manager, addr: Address, amount: i64) {
+        let mut acct = manager.state_db.must_get(&keys::Account(addr));
+        acct.balance += amount;
+        manager.state_db.put_key(keys::Account(addr), acct).unwrap();
+    }
+
+    #[test]
+    fn test_check_supply() {
+        let mut manager = Manager::new_for_test();
+        let addr: Address = manager.genesis_config.allocs[0].address.parse().unwrap();
+        manager.new_layer();
+        assert!(supply_violations(&manager).is_empty());
+
+        adjust_balance(&mut manager, addr, 1);
+        let violations = supply_violations(&manager);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("exceeds"));
+
+        adjust_balance(&mut manager, addr, -2);
+        let violations = supply_violations(&manager);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("is short of"));
+    }
+
+    #[test]
+    fn test_check_supply_without_issued_reward() {
+        let mut manager = Manager::new_for_test();
+        manager.new_layer();
+        manager
+            .state_db
+            .delete_key(&keys::DynamicProperty::TotalIssuedReward)
+            .unwrap();
+        let violations = supply_violations(&manager);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("supply can not be checked"));
+    }
+
+    #[test]
+    fn test_check_supply_with_voter_reward_pools() {
+        let mut manager = Manager::new_for_test();
+        let addr: Address = manager.genesis_config.allocs[0].address.parse().unwrap();
+        let wit_addr: Address = manager.genesis_config.witnesses[0].address.parse().unwrap();
+        manager.new_layer();
+
+        // A reward pool no one votes for, all of it is unclaimable.
+        manager.add_issued_reward(100);
+        manager
+            .state_db
+            .put_key(
+                keys::VoterReward(1, wit_addr),
+                WitnessVoterReward {
+                    vote_count: 1,
+                    reward_amount: 100,
+                },
+            )
+            .unwrap();
+        assert!(supply_violations(&manager).is_empty());
+
+        adjust_balance(&mut manager, addr, -1);
+        let violations = supply_violations(&manager);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("more than 100 left in voter reward pools"));
+    }
+}
@@ -5,20 +5,30 @@ use config::{Config, GenesisConfig};
 use log::{debug, info, trace, warn};
 use primitive_types::H256;
 use prost::Message;
+use proto2::chain::ContractType;
 use proto2::state::TransactionReceipt;
-use state::db::StateDB;
+use state::db::{StateDB, StateView};
 use state::keys;
+use std::collections::VecDeque;
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::sync::Arc;
 
-use self::executor::TransactionExecutor;
+use self::executor::{ExecutionError, ResultMismatch, TransactionExecutor};
+use self::fork_db::ForkDB;
 use self::governance::maintenance::MaintenanceManager;
 use self::governance::proposal::ProposalController;
 use self::governance::reward::RewardController;
 use self::resource::EnergyProcessor;
+use self::tracer::SharedTracer;
 
 pub mod executor;
+pub mod fork_db;
 pub mod governance;
+pub mod invariant;
+pub mod producer;
 pub mod resource;
+pub mod tracer;
 pub mod version_fork;
 pub mod vm;
 
@@ -33,29 +43,95 @@ fn new_error(msg: &str) -> Error {
     Box::new(io::Error::new(io::ErrorKind::Other, msg))
 }
 
+/// Force solidifying of the oldest blocks when the solid block number stops moving forward.
+const MAX_NUM_OF_UNSOLIDIFIED_BLOCKS: usize = 512;
+
+/// Transactions are indexed for duplication check in this number of recent blocks.
+///
+/// Block slots are at least one interval apart, so a transaction included earlier than this has expired.
+const NUM_OF_RECENT_TRANSACTION_BLOCKS: i64 =
+    constants::MAX_TRANSACTION_EXPIRATION / constants::BLOCK_PRODUCING_INTERVAL + 1;
+
+/// A block applied to the state-db but not yet solidified.
+#[derive(Clone)]
+struct BlockLayer {
+    hash: H256,
+    parent_hash: H256,
+    number: i64,
+    /// Number of state-db layers created while applying the block.
+    layers: usize,
+    /// The TaPoS ref slot overwritten by this block, `None` if the block was appended.
+    replaced_ref_hash: Option<H256>,
+    /// `block_energy_usage` and `maintenance_started_at` before the block, restored when undone.
+    prev_block_energy_usage: i64,
+    prev_maintenance_started_at: i64,
+}
+
+/// Error of a block whose parent is neither in the fork db nor on the main chain, it can be pushed again once the
+/// parent is pushed.
+#[derive(Debug)]
+pub struct UnlinkedBlock {
+    pub number: i64,
+    pub parent_hash: H256,
+}
+
+impl fmt::Display for UnlinkedBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unlinked block #{}, parent {:?} not found", self.number, self.parent_hash)
+    }
+}
+
+impl ::std::error::Error for UnlinkedBlock {}
+
 /// State DB Manager.
 pub struct Manager {
     state_db: StateDB,
     genesis_block_timestamp: i64,
+    // Block id of block #0, used as the chain id of TVM.
+    genesis_block_hash: H256,
     blackhole: Address,
-    my_witness: Vec<u8>,
 
     block_energy_usage: i64,
-    // TaPoS check, size = 65536, 2MB.
-    ref_block_hashes: Vec<H256>,
+    // TaPoS check, size = 65536, 2MB. Shared with snapshots.
+    ref_block_hashes: Arc<Vec<H256>>,
     config: Config,
     genesis_config: GenesisConfig,
     maintenance_started_at: i64,
 
+    // Layers created since the last block boundary.
     layers: usize,
+    // Blocks on the main chain above the solidified state, oldest first.
+    unsolidified_blocks: VecDeque<BlockLayer>,
+    // All known blocks above the solid block, from every branch.
+    fork_db: ForkDB,
+
+    // Fail transactions on any result mismatch, used when replaying.
+    strict_result_check: bool,
+    result_mismatch: Option<ResultMismatch>,
+
+    // Tracer of TVM executions, only set while tracing.
+    tracer: Option<SharedTracer>,
 }
 
 impl Manager {
     pub fn new(config: &Config, genesis_config: &GenesisConfig) -> Self {
-        let mut state_db = StateDB::new(&config.storage.state_data_dir);
+        let mut state_db = StateDB::open(&config.storage.engine, &config.storage.state_data_dir).unwrap();
 
+        state_db.migrate(&config.chain, false).unwrap();
         state_db.init_genesis(&genesis_config, &config.chain).unwrap();
+        state_db.set_archive_mode(config.storage.archive);
+
+        Manager::with_state_db(state_db, config, genesis_config)
+    }
+
+    /// Manager on an initialized state-db, e.g. the read-only state-db of API nodes.
+    pub fn with_state_db(state_db: StateDB, config: &Config, genesis_config: &GenesisConfig) -> Self {
         let genesis_block_timestamp = genesis_config.timestamp;
+        let genesis_block_hash = genesis_config
+            .to_indexed_block()
+            .expect("invalid genesis block config")
+            .header
+            .hash;
 
         let blackhole = genesis_config
             .allocs
@@ -69,37 +145,104 @@ impl Manager {
         Manager {
             state_db,
             genesis_block_timestamp,
+            genesis_block_hash,
             blackhole,
-            my_witness: vec![],
             block_energy_usage: 0,
-            ref_block_hashes: Vec::with_capacity(65536),
+            ref_block_hashes: Arc::new(Vec::with_capacity(65536)),
             config: config.clone(),
             genesis_config: genesis_config.clone(),
             maintenance_started_at: 0,
             layers: 0,
+            unsolidified_blocks: VecDeque::new(),
+            fork_db: ForkDB::new(),
+            strict_result_check: false,
+            result_mismatch: None,
+            tracer: None,
+        }
+    }
+
+    /// Manager on an in-memory state-db inited from the Nile testnet genesis.
+    #[cfg(test)]
+    pub(crate) fn new_for_test() -> Self {
+        let config = Config::load_from_file("./config/conf.nile.toml").unwrap();
+        let genesis_config = GenesisConfig::load_from_file("./config/genesis.nile.json").unwrap();
+        let mut state_db = StateDB::new_in_memory();
+        state_db.init_genesis(&genesis_config, &config.chain).unwrap();
+
+        Manager::with_state_db(state_db, &config, &genesis_config)
+    }
+
+    /// A throwaway manager on a consistent snapshot of the state, for API queries, constant calls and dry runs.
+    ///
+    /// The snapshot is detached from the node, so the block writer can proceed concurrently.
+    /// All changes made through it are discarded when dropped.
+    pub fn snapshot(&self) -> Manager {
+        Manager {
+            state_db: self.state_db.snapshot(),
+            genesis_block_timestamp: self.genesis_block_timestamp,
+            genesis_block_hash: self.genesis_block_hash,
+            blackhole: self.blackhole,
+            block_energy_usage: self.block_energy_usage,
+            ref_block_hashes: self.ref_block_hashes.clone(),
+            config: self.config.clone(),
+            genesis_config: self.genesis_config.clone(),
+            maintenance_started_at: self.maintenance_started_at,
+            layers: self.layers,
+            unsolidified_blocks: self.unsolidified_blocks.clone(),
+            fork_db: ForkDB::new(),
+            strict_result_check: false,
+            result_mismatch: None,
+            tracer: None,
         }
     }
 
+    /// Replace the state-db, used by API nodes to follow the solid state of a running node.
+    pub fn set_state_db(&mut self, state_db: StateDB) {
+        self.state_db = state_db;
+    }
+
     pub(crate) fn state(&self) -> &StateDB {
         &self.state_db
     }
 
+    pub(crate) fn state_mut(&mut self) -> &mut StateDB {
+        &mut self.state_db
+    }
+
+    /// Fail blocks on the first transaction result mismatch, and record it.
+    pub fn set_strict_result_check(&mut self, strict: bool) {
+        self.strict_result_check = strict;
+    }
+
+    /// Take the recorded result mismatch, in strict mode.
+    pub fn take_result_mismatch(&mut self) -> Option<ResultMismatch> {
+        self.result_mismatch.take()
+    }
+
     pub fn init_ref_blocks(&mut self, hashes: Vec<H256>) {
         debug!("update num of ref_hashes => {:?}", hashes.len());
-        self.ref_block_hashes = hashes;
+        self.ref_block_hashes = Arc::new(hashes);
     }
 
-    fn update_ref_blocks(&mut self, new_hash: H256) {
-        if self.ref_block_hashes.len() < 65536 {
-            self.ref_block_hashes.push(new_hash);
+    /// Returns the replaced ref block hash, if any.
+    fn update_ref_blocks(&mut self, new_hash: H256) -> Option<H256> {
+        let ref_block_hashes = Arc::make_mut(&mut self.ref_block_hashes);
+        if ref_block_hashes.len() < 65536 {
+            ref_block_hashes.push(new_hash);
+            None
         } else {
-            let ref_block_bytes = {
-                let mut raw = [0u8; 2];
-                raw.copy_from_slice(&new_hash.as_bytes()[6..8]);
-                raw
-            };
-            let ref_slot_index = u16::from_be_bytes(ref_block_bytes) as usize;
-            self.ref_block_hashes[ref_slot_index] = new_hash;
+            let ref_slot_index = ref_slot_index_of(&new_hash);
+            Some(std::mem::replace(&mut ref_block_hashes[ref_slot_index], new_hash))
+        }
+    }
+
+    fn revert_ref_blocks(&mut self, hash: H256, replaced_hash: Option<H256>) {
+        let ref_block_hashes = Arc::make_mut(&mut self.ref_block_hashes);
+        match replaced_hash {
+            Some(replaced_hash) => ref_block_hashes[ref_slot_index_of(&hash)] = replaced_hash,
+            None => {
+                ref_block_hashes.pop();
+            }
         }
     }
 
@@ -124,13 +267,6 @@ impl Manager {
         self.state_db.new_layer();
     }
 
-    fn commit_current_layers(&mut self) {
-        for _ in 0..self.layers {
-            self.state_db.solidify_layer();
-        }
-        self.layers = 0;
-    }
-
     fn rollback_layers(&mut self, n: usize) {
         for _ in 0..n {
             self.state_db.discard_last_layer().unwrap();
@@ -140,17 +276,23 @@ impl Manager {
 
     // Entry of db manager.
     pub fn push_block(&mut self, block: &IndexedBlock) -> Result<bool> {
+        self.push_block_inner(block, true)
+    }
+
+    /// Push a block produced and signed by this node, the witness signature is not verified again.
+    pub(crate) fn push_produced_block(&mut self, block: &IndexedBlock) -> Result<bool> {
+        self.push_block_inner(block, false)
+    }
+
+    fn push_block_inner(&mut self, block: &IndexedBlock, verify_signature: bool) -> Result<bool> {
         if block.number() <= 0 {
             panic!("only accepts block number > 1");
         }
 
         // . verify witness signature
-        if self.my_witness.is_empty() || block.witness() != &*self.my_witness {
+        if verify_signature {
             let recovered = block.recover_witness()?;
-            if self.state_db.must_get(&keys::ChainParameter::AllowMultisig) == 1 {
-                // warn!("TODO: handle multisig witness");
-            }
-            if recovered.as_bytes() != block.witness() {
+            if !self.validate_block_witness_signature(block, &recovered)? {
                 return Err(new_error("verifying block witness signature failed"));
             }
         }
@@ -163,25 +305,24 @@ impl Manager {
             )));
         }
 
-        // TODO: check dup block? (StateManager.receiveBlock)
+        // . check dup block (StateManager.receiveBlock)
+        if self.fork_db.contains(block.hash()) {
+            debug!("block #{} already received", block.number());
+            return Ok(false);
+        }
 
         // NOTE: mainnet does not support shielded TRC10 transaction. No need to check shielded transaction count.
 
-        // . reject smaller block number
-        if block.number() <= self.latest_block_number() {
+        // . reject blocks that can not change the solidified state
+        if block.number() <= self.solid_block_number() {
             warn!(
-                "reject smaller block number latest={}, got={}",
-                self.latest_block_number(),
+                "reject block below solid block, solid={}, got={}",
+                self.solid_block_number(),
                 block.number()
             );
             return Ok(false);
         }
 
-        if block.parent_hash() != self.latest_block_hash().as_bytes() {
-            warn!("TODO: handle chain fork!");
-            return Err(new_error("chain fork!"));
-        }
-
         // . block version check
         if block.version() > constants::CURRENT_BLOCK_VERSION as i32 {
             warn!(
@@ -192,14 +333,44 @@ impl Manager {
 
         // basic check finished, begin process block
         let started_at = Utc::now().timestamp_nanos();
-        self.new_layer();
 
-        // . applyBlock = processBlock + updateFork
-        self.process_block(block)?;
+        if block.parent_hash() == self.latest_block_hash().as_bytes() {
+            // . applyBlock = processBlock + updateFork
+            self.apply_block(block)?;
+            self.fork_db.insert(block.clone());
+        } else {
+            let parent_hash = H256::from_slice(block.parent_hash());
+            if !self.fork_db.contains(&parent_hash) && !self.is_on_main_chain(&parent_hash) {
+                if block.number() <= self.latest_block_number() {
+                    warn!(
+                        "reject smaller block number latest={}, got={}",
+                        self.latest_block_number(),
+                        block.number()
+                    );
+                    return Ok(false);
+                }
+                return Err(Box::new(UnlinkedBlock {
+                    number: block.number(),
+                    parent_hash,
+                }));
+            }
+            self.fork_db.insert(block.clone());
+
+            // Only switch to a strictly longer branch.
+            if block.number() <= self.latest_block_number() {
+                info!(
+                    "received fork block #{}, latest={}, keep current branch",
+                    block.number(),
+                    self.latest_block_number()
+                );
+                return Ok(false);
+            }
+            self.switch_to_branch(block.hash())?;
+        }
 
         // NOTE: OpenTron use different logic to handle verson fork. So `updateFork` is removed.
         // And no need to updateFork.
-        self.commit_current_layers();
+        self.solidify_blocks()?;
 
         let elapsed = (Utc::now().timestamp_nanos() - started_at) as f64 / 1_000_000.0;
         if !block.transactions.is_empty() {
@@ -222,6 +393,178 @@ impl Manager {
         Ok(true)
     }
 
+    /// Process the block in new state-db layers. Layers are kept until the block is solidified.
+    fn apply_block(&mut self, block: &IndexedBlock) -> Result<()> {
+        debug_assert_eq!(self.layers, 0, "dangling layers before applying block");
+        let prev_block_energy_usage = self.block_energy_usage;
+        let prev_maintenance_started_at = self.maintenance_started_at;
+        self.new_layer();
+
+        if let Err(e) = self.process_block(block) {
+            self.rollback_layers(self.layers);
+            self.block_energy_usage = prev_block_energy_usage;
+            self.maintenance_started_at = prev_maintenance_started_at;
+            return Err(e);
+        }
+
+        let replaced_ref_hash = self.update_ref_blocks(*block.hash());
+        self.unsolidified_blocks.push_back(BlockLayer {
+            hash: *block.hash(),
+            parent_hash: H256::from_slice(block.parent_hash()),
+            number: block.number(),
+            layers: self.layers,
+            replaced_ref_hash,
+            prev_block_energy_usage,
+            prev_maintenance_started_at,
+        });
+        self.layers = 0;
+        Ok(())
+    }
+
+    /// Undo the latest unsolidified block, returns its hash.
+    fn undo_block(&mut self) -> Result<H256> {
+        let blk = self
+            .unsolidified_blocks
+            .pop_back()
+            .ok_or_else(|| new_error("no unsolidified block to roll back"))?;
+        for _ in 0..blk.layers {
+            self.state_db.discard_last_layer()?;
+        }
+        self.revert_ref_blocks(blk.hash, blk.replaced_ref_hash);
+        self.block_energy_usage = blk.prev_block_energy_usage;
+        self.maintenance_started_at = blk.prev_maintenance_started_at;
+        debug!("rolled back block #{} {:?}", blk.number, blk.hash);
+        Ok(blk.hash)
+    }
+
+    /// Roll back to the common ancestor, then apply blocks of the new branch.
+    ///
+    /// If any block of the new branch fails, the branch is dropped and the original branch is restored.
+    fn switch_to_branch(&mut self, head: &H256) -> Result<()> {
+        let (ancestor, branch) = {
+            let blocks = &self.unsolidified_blocks;
+            let solid_hash = self.solidified_block_hash();
+            self.fork_db
+                .branch_of(head, |h| h == &solid_hash || blocks.iter().any(|blk| &blk.hash == h))
+                .ok_or_else(|| new_error("fork branch is not linked to main chain"))?
+        };
+
+        warn!(
+            "switching chain fork, ancestor={:?}, new head=#{}",
+            ancestor,
+            self.fork_db.get(head).map(|blk| blk.number()).unwrap_or_default(),
+        );
+
+        let mut discarded = vec![];
+        while self.latest_block_hash() != ancestor {
+            discarded.push(self.undo_block()?);
+        }
+        discarded.reverse();
+
+        for hash in &branch {
+            let block = self.fork_db.get(hash).cloned().expect("block in branch");
+            if let Err(e) = self.apply_block(&block) {
+                warn!("invalid block #{} in fork branch: {}", block.number(), e);
+                self.fork_db.remove_with_descendants(hash);
+
+                while self.latest_block_hash() != ancestor {
+                    self.undo_block()?;
+                }
+                for hash in &discarded {
+                    let block = self.fork_db.get(hash).cloned().expect("block in original branch");
+                    self.apply_block(&block)?;
+                }
+                return Err(e);
+            }
+        }
+
+        info!(
+            "switched to fork branch, rolled back {} blocks, applied {} blocks",
+            discarded.len(),
+            branch.len()
+        );
+        Ok(())
+    }
+
+    /// Write layers of blocks below solid block number into db.
+    fn solidify_blocks(&mut self) -> Result<()> {
+        let solid_block_num = self.solid_block_number();
+        let mut solidified = None;
+        while let Some(blk) = self.unsolidified_blocks.front() {
+            if blk.number > solid_block_num && self.unsolidified_blocks.len() <= MAX_NUM_OF_UNSOLIDIFIED_BLOCKS {
+                break;
+            }
+            let blk = self.unsolidified_blocks.pop_front().unwrap();
+            self.state_db.solidify_block_layers(blk.number, blk.layers)?;
+            solidified = Some(blk.number);
+        }
+        if let Some(block_num) = solidified {
+            self.fork_db.prune(block_num);
+        }
+        Ok(())
+    }
+
+    /// Write layers of all applied blocks into db, regardless of the solid block number.
+    ///
+    /// Only safe for blocks known to be final, i.e. replayed from chain-db.
+    pub fn solidify_all_blocks(&mut self) -> Result<()> {
+        let mut solidified = None;
+        while let Some(blk) = self.unsolidified_blocks.pop_front() {
+            self.state_db.solidify_block_layers(blk.number, blk.layers)?;
+            solidified = Some(blk.number);
+        }
+        if let Some(block_num) = solidified {
+            self.fork_db.prune(block_num);
+        }
+        Ok(())
+    }
+
+    /// Hash of the latest block whose state is written into db.
+    fn solidified_block_hash(&self) -> H256 {
+        self.unsolidified_blocks
+            .front()
+            .map(|blk| blk.parent_hash)
+            .unwrap_or_else(|| self.latest_block_hash())
+    }
+
+    /// Read view of state as of a block on the main chain.
+    pub fn state_view_at(&self, block_number: i64) -> Result<StateView> {
+        if block_number < 0 || block_number > self.latest_block_number() {
+            return Err(new_error(&format!("block #{} is not applied to state", block_number)));
+        }
+        let skipped_layers = self.layers +
+            self.unsolidified_blocks
+                .iter()
+                .filter(|blk| blk.number > block_number)
+                .map(|blk| blk.layers)
+                .sum::<usize>();
+        let solidified_block_number = self
+            .unsolidified_blocks
+            .front()
+            .map(|blk| blk.number - 1)
+            .unwrap_or_else(|| self.latest_block_number());
+        self.state_db
+            .view_at(block_number, skipped_layers, block_number < solidified_block_number)
+    }
+
+    /// Run `f` with all state reads as of the block. Layers added by `f` are rolled back.
+    pub fn with_state_at<F, R>(&mut self, block_number: i64, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Manager) -> R,
+    {
+        let view = self.state_view_at(block_number)?;
+        let old_layers = self.layers;
+        self.state_db.enter_view(view);
+        let ret = f(self);
+        self.rollback_layers(self.layers - old_layers);
+        self.state_db.leave_view();
+        Ok(ret)
+    }
+
+    fn is_on_main_chain(&self, hash: &H256) -> bool {
+        hash == &self.solidified_block_hash() || self.unsolidified_blocks.iter().any(|blk| &blk.hash == hash)
+    }
+
     fn process_block(&mut self, block: &IndexedBlock) -> Result<()> {
         // 1. checkWitness - check block producing schedule
         // Block producer is strictly scheduled except block #1(where needSyncCheck=false).
@@ -246,6 +589,7 @@ impl Manager {
             );
             self.process_transaction(&txn, recovered_addrs, block)?;
         }
+        self.update_recent_transactions(block)?;
 
         // 4. Adaptive energy processor:
         if self.block_energy_usage > 0 {
@@ -274,8 +618,6 @@ impl Manager {
         MaintenanceManager::new(self).apply_block(block)?;
         self.update_solid_block(block)?;
 
-        self.update_ref_blocks(*block.hash());
-
         // 8. update latest block - updateDynamicProperties
         self.state_db
             .put_key(keys::DynamicProperty::LatestBlockNumber, block.number())?;
@@ -283,6 +625,10 @@ impl Manager {
             .put_key(keys::DynamicProperty::LatestBlockTimestamp, block.timestamp())?;
         self.state_db.put_key(keys::LatestBlockHash, *block.hash())?;
 
+        // NOTE: `account_state_root` of block headers is neither computed nor checked. java-tron builds it from the
+        // accounts written by each transaction since AllowAccountStateRoot is activated, which can only be reproduced
+        // and verified against a chain that has it activated.
+
         Ok(())
     }
 
@@ -314,19 +660,65 @@ impl Manager {
         // 6.cusumeMultiSigFee (NOTE: move to BandwidthProcessor)
 
         // 7. transaction is executed by TransactionTrace.
-        let txn_receipt = TransactionExecutor::new(self).execute(txn, recovered_addrs, &block.header)?;
+        // In strict mode, each transaction has its own layer, so touched state keys can be reported.
+        let layers_before_txn = self.layers;
+        if self.strict_result_check {
+            self.new_layer();
+        }
+        let txn_receipt = match TransactionExecutor::new(self).execute(txn, recovered_addrs, &block.header) {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Some(mismatch) = self.result_mismatch.as_mut() {
+                    mismatch.touched_keys = self.state_db.touched_keys(self.layers - layers_before_txn);
+                }
+                return Err(e.into());
+            }
+        };
+        for internal_txn in &txn_receipt.vm_internal_transactions {
+            self.state_db
+                .put_key(keys::InternalTransaction(H256::from_slice(&internal_txn.hash)), internal_txn.clone())?;
+        }
         self.state_db.put_key(keys::TransactionReceipt(txn.hash), txn_receipt)?;
+        self.state_db
+            .put_key(keys::RecentTransaction(txn.hash), block.number())?;
+        Ok(())
+    }
+
+    /// Index transactions of the block, and expire those out of the duplication check window.
+    fn update_recent_transactions(&mut self, block: &IndexedBlock) -> Result<()> {
+        if !block.transactions.is_empty() {
+            let hashes = block.transactions.iter().map(|txn| txn.hash).collect();
+            self.state_db
+                .put_key(keys::RecentBlockTransactions(block.number()), hashes)?;
+        }
+
+        let expired_block_number = block.number() - NUM_OF_RECENT_TRANSACTION_BLOCKS;
+        if expired_block_number > 0 {
+            let key = keys::RecentBlockTransactions(expired_block_number);
+            if let Some(hashes) = self.state_db.get(&key)? {
+                for hash in hashes {
+                    self.state_db.delete_key(&keys::RecentTransaction(hash))?;
+                }
+                self.state_db.delete_key(&key)?;
+            }
+        }
         Ok(())
     }
 
     /// Dry run the transaction, return Receipt.
-    pub fn dry_run_transaction(&mut self, txn: &IndexedTransaction) -> Result<TransactionReceipt> {
+    pub fn dry_run_transaction(&mut self, txn: &IndexedTransaction) -> Result<TransactionReceipt, ExecutionError> {
         /*if !self.validate_transaction_tapos(txn) {
             return Err(new_error("tapos validation failed"));
         }
         if !self.valide_transaction_common(txn) {
             return Err(new_error("message size or expiration validation failed"));
         }*/
+        if !self.validate_duplicated_transaction(txn) {
+            return Err(ExecutionError::DuplicatedTransaction);
+        }
+        let recovered_addrs = txn
+            .recover_owner()
+            .map_err(|e| ExecutionError::Permission(e.to_string()))?;
         let fake_block_number = self.latest_block_number() + 1;
         let block_header = IndexedBlockHeader::dummy(
             fake_block_number,
@@ -336,20 +728,82 @@ impl Manager {
         let old_layers = self.layers;
         self.new_layer();
 
-        let maybe_receipt = TransactionExecutor::new(self).execute(txn, txn.recover_owner()?, &block_header);
+        let maybe_receipt = TransactionExecutor::new(self).execute(txn, recovered_addrs, &block_header);
 
         let added_layers = self.layers - old_layers;
         debug!("dry run, rollback layers={}", added_layers);
         self.rollback_layers(added_layers);
-        Ok(maybe_receipt?)
+        maybe_receipt
+    }
+
+    /// Run `f` with the tracer attached to all TVM executions.
+    pub fn with_tracer<F, R>(&mut self, tracer: SharedTracer, f: F) -> R
+    where
+        F: FnOnce(&mut Manager) -> R,
+    {
+        let old_tracer = self.tracer.replace(tracer);
+        let ret = f(self);
+        self.tracer = old_tracer;
+        ret
+    }
+
+    /// Re-execute the transaction in the block with the tracer attached, return Receipt.
+    ///
+    /// Transactions before it in the same block are re-executed untraced. State is left untouched.
+    pub fn trace_transaction(
+        &mut self,
+        block: &IndexedBlock,
+        txn_hash: &H256,
+        tracer: SharedTracer,
+    ) -> Result<TransactionReceipt> {
+        let pos = block
+            .transactions
+            .iter()
+            .position(|txn| &txn.hash == txn_hash)
+            .ok_or_else(|| new_error("transaction not found in block"))?;
+        let recovered_owners = block.recover_transaction_owners();
+        let block_energy_usage = self.block_energy_usage;
+
+        let ret = self.with_state_at(block.number() - 1, |manager| -> Result<TransactionReceipt> {
+            // energy usage of the block is counted from its first transaction, as in `process_block`
+            manager.block_energy_usage = 0;
+            manager.new_layer();
+            for (i, (txn, recovered_addrs)) in block.transactions.iter().zip(recovered_owners).enumerate() {
+                let recovered_addrs =
+                    recovered_addrs.map_err(|_| new_error("error while recover address from signature"))?;
+                if i < pos {
+                    TransactionExecutor::new(manager).execute(txn, recovered_addrs, &block.header)?;
+                } else {
+                    return manager.with_tracer(tracer, |manager| {
+                        Ok(TransactionExecutor::new(manager).execute(txn, recovered_addrs, &block.header)?)
+                    });
+                }
+            }
+            unreachable!()
+        });
+        self.block_energy_usage = block_energy_usage;
+        ret?
+    }
+
+    /// Validate a transaction before adding it to the transaction pool, returns the dry run receipt.
+    pub fn validate_pending_transaction(&mut self, txn: &IndexedTransaction) -> Result<TransactionReceipt> {
+        validate_transaction_contract(txn)?;
+        if !self.validate_transaction_tapos(txn) {
+            return Err(new_error("tapos validation failed"));
+        }
+        if !self.valide_transaction_common(txn) {
+            return Err(new_error("message size or expiration validation failed"));
+        }
+        // NOTE: duplication is checked in dry run
+        Ok(self.dry_run_transaction(txn)?)
     }
 
     fn validate_transaction_tapos(&self, txn: &IndexedTransaction) -> bool {
-        let ref_block_hash = &txn.raw.raw_data.as_ref().unwrap().ref_block_hash;
-        let ref_block_bytes = {
-            let mut raw = [0u8; 2];
-            raw.copy_from_slice(&txn.raw.raw_data.as_ref().unwrap().ref_block_bytes);
-            raw
+        let raw = txn.raw.raw_data.as_ref().unwrap();
+        let ref_block_hash = &raw.ref_block_hash;
+        let ref_block_bytes: [u8; 2] = match raw.ref_block_bytes[..].try_into() {
+            Ok(ref_block_bytes) => ref_block_bytes,
+            Err(_) => return false,
         };
         // debug!("ref block bytes=> {:?}", hex::encode(&ref_block_bytes[..]));
         // debug!("ref block hash=> {:?}", hex::encode(&ref_block_hash[..]));
@@ -376,9 +830,34 @@ impl Manager {
         true
     }
 
-    fn validate_duplicated_transaction(&self, _txn: &IndexedTransaction) -> bool {
-        // TODO: not used in sync. used in block producing
-        true
+    fn validate_duplicated_transaction(&self, txn: &IndexedTransaction) -> bool {
+        match self.state_db.get(&keys::RecentTransaction(txn.hash)) {
+            Ok(Some(block_number)) => {
+                warn!("duplicated transaction {:?}, included in block #{}", txn.hash, block_number);
+                false
+            }
+            Ok(None) => true,
+            Err(e) => {
+                warn!("read recent transaction error: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Block is signed by the witness permission address, as `getWitnessPermissionAddress` of java-tron.
+    ///
+    /// When multisig is allowed, it's the key of witness permission if set, otherwise the witness itself.
+    fn validate_block_witness_signature(&self, block: &IndexedBlock, recovered: &Address) -> Result<bool> {
+        if self.state_db.must_get(&keys::ChainParameter::AllowMultisig) == 0 {
+            return Ok(recovered.as_bytes() == block.witness());
+        }
+        let witness = Address::try_from(block.witness()).map_err(|_| new_error("invalid witness address"))?;
+        // NOTE: Witness permission has exactly one key, which is saved as `signature_key`.
+        let permission_address = match self.state_db.get(&keys::Witness(witness))? {
+            Some(wit) if !wit.signature_key.is_empty() => wit.signature_key,
+            _ => block.witness().to_vec(),
+        };
+        Ok(recovered.as_bytes() == &permission_address[..])
     }
 
     // consensus.validBlock
@@ -471,6 +950,15 @@ impl Manager {
             let reward_per_block = self.state_db.must_get(&keys::ChainParameter::WitnessPayPerBlock);
             wit_acct.allowance += reward_per_block;
             self.state_db.put_key(wit_key, wit_acct).unwrap();
+            self.add_issued_reward(reward_per_block);
+        }
+    }
+
+    /// Track the total issued reward, if it's tracked in the state-db.
+    fn add_issued_reward(&mut self, amount: i64) {
+        let key = keys::DynamicProperty::TotalIssuedReward;
+        if let Some(total) = self.state_db.get(&key).unwrap() {
+            self.state_db.put_key(key, total + amount).unwrap();
         }
     }
 
@@ -512,6 +1000,15 @@ impl Manager {
         ts + constants::BLOCK_PRODUCING_INTERVAL * slot
     }
 
+    /// Returns the slot timestamp and the scheduled witness of the slot `timestamp` falls in.
+    pub fn scheduled_witness_at(&self, timestamp: i64) -> Option<(i64, Address)> {
+        let slot = self.get_slot(timestamp);
+        if slot == 0 {
+            return None;
+        }
+        Some((self.get_slot_timestamp(slot), self.get_scheduled_witness(slot)))
+    }
+
     fn get_active_witnesses(&self) -> Vec<Address> {
         let mut witnesses = self.state_db.get(&keys::WitnessSchedule).unwrap().unwrap();
         if witnesses.is_empty() {
@@ -557,7 +1054,7 @@ impl Manager {
     }
 
     #[inline]
-    fn latest_block_timestamp(&self) -> i64 {
+    pub fn latest_block_timestamp(&self) -> i64 {
         self.state_db.must_get(&keys::DynamicProperty::LatestBlockTimestamp)
     }
 
@@ -577,6 +1074,32 @@ impl Manager {
     }
 }
 
+#[inline]
+fn ref_slot_index_of(block_hash: &H256) -> usize {
+    let mut raw = [0u8; 2];
+    raw.copy_from_slice(&block_hash.as_bytes()[6..8]);
+    u16::from_be_bytes(raw) as usize
+}
+
+/// Transactions from peers and the API are checked to have a known contract before execution.
+fn validate_transaction_contract(txn: &IndexedTransaction) -> Result<()> {
+    let cntr = txn
+        .raw
+        .raw_data
+        .as_ref()
+        .unwrap()
+        .contract
+        .as_ref()
+        .ok_or_else(|| new_error("missing contract"))?;
+    if ContractType::from_i32(cntr.r#type).is_none() {
+        return Err(new_error(&format!("unknown contract type {}", cntr.r#type)));
+    }
+    if cntr.parameter.is_none() {
+        return Err(new_error("missing contract parameter"));
+    }
+    Ok(())
+}
+
 /// Update witnesses' statistics, and BlockFilledSlots.
 pub struct WitnessStatisticManager<'m> {
     manager: &'m mut Manager,
@@ -648,3 +1171,56 @@ impl WitnessStatisticManager<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::BlockHeader;
+    use proto2::chain::block_header::Raw as BlockHeaderRaw;
+
+    fn address_of(b: u8) -> Address {
+        let mut raw = [b; 21];
+        raw[0] = 0x41;
+        *Address::from_bytes(&raw)
+    }
+
+    #[test]
+    fn test_validate_block_witness_signature() {
+        let mut manager = Manager::new_for_test();
+        let owner = manager.genesis_config.witnesses[0].address.parse::<Address>().unwrap();
+        let permission_key = address_of(0xaa);
+        let wrong = address_of(0xbb);
+
+        let header = BlockHeader {
+            raw_data: Some(BlockHeaderRaw {
+                number: 1,
+                witness_address: owner.as_bytes().to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let block = IndexedBlock::new(IndexedBlockHeader::from_raw(header).unwrap(), vec![]);
+
+        manager.new_layer();
+        let accepted = |manager: &mut Manager, allow_multisig: i64| {
+            manager
+                .state_db
+                .put_key(keys::ChainParameter::AllowMultisig, allow_multisig)
+                .unwrap();
+            [owner, permission_key, wrong]
+                .iter()
+                .map(|signer| manager.validate_block_witness_signature(&block, signer).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(accepted(&mut manager, 0), vec![true, false, false]);
+        assert_eq!(accepted(&mut manager, 1), vec![true, false, false]);
+
+        let mut wit = manager.state_db.must_get(&keys::Witness(owner));
+        wit.signature_key = permission_key.as_bytes().to_vec();
+        manager.state_db.put_key(keys::Witness(owner), wit).unwrap();
+
+        assert_eq!(accepted(&mut manager, 0), vec![true, false, false]);
+        assert_eq!(accepted(&mut manager, 1), vec![false, true, false]);
+    }
+}
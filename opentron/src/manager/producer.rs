@@ -0,0 +1,186 @@
+//! Block producing of DPoS witnesses.
+
+use std::time::{Duration, Instant};
+
+use ::keys::KeyPair;
+use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
+use log::{debug, info, warn};
+use primitive_types::H256;
+use prost::Message;
+use proto2::chain::{
+    block_header::Raw as BlockHeaderRaw, transaction::result::ContractStatus, transaction::Result as TransactionResult,
+    Block, BlockHeader,
+};
+use proto2::state::TransactionReceipt;
+use state::keys;
+
+use super::executor::TransactionExecutor;
+use super::{new_error, Manager, Result};
+
+/// Stop packing transactions when half of the producing interval is used.
+const BLOCK_PRODUCING_TIMEOUT: Duration = Duration::from_millis(constants::BLOCK_PRODUCING_INTERVAL as u64 / 2);
+
+/// Reserved for the block header and the signature.
+const BLOCK_HEADER_RESERVED_SIZE: usize = 256;
+
+pub struct BlockProducer<'m> {
+    manager: &'m mut Manager,
+}
+
+impl BlockProducer<'_> {
+    pub fn new<'a>(manager: &'a mut Manager) -> BlockProducer<'a> {
+        BlockProducer { manager }
+    }
+
+    /// Produce a block at slot `timestamp`, then sign and push it.
+    ///
+    /// Transactions are packed in the given order. Returns the block and hashes of transactions that failed and
+    /// should be dropped. Transactions postponed by the block size limit are not dropped.
+    pub fn produce_block<I>(self, keypair: &KeyPair, timestamp: i64, txns: I) -> Result<(IndexedBlock, Vec<H256>)>
+    where
+        I: IntoIterator<Item = IndexedTransaction>,
+    {
+        let manager = self.manager;
+        let witness = keypair.address();
+
+        match manager.scheduled_witness_at(timestamp) {
+            Some((slot_timestamp, scheduled)) if slot_timestamp == timestamp && scheduled == witness => {}
+            _ => return Err(new_error("witness is not scheduled at this slot")),
+        }
+
+        let raw_header = BlockHeaderRaw {
+            timestamp,
+            parent_hash: manager.latest_block_hash().as_bytes().to_vec(),
+            number: manager.latest_block_number() + 1,
+            witness_address: witness.as_bytes().to_vec(),
+            version: constants::CURRENT_BLOCK_VERSION as i32,
+            ..Default::default()
+        };
+        // Header used while executing transactions, without merkle root.
+        let header = IndexedBlockHeader::from_raw(BlockHeader {
+            raw_data: Some(raw_header.clone()),
+            ..Default::default()
+        })
+        .ok_or_else(|| new_error("invalid block header"))?;
+
+        let max_cpu_time =
+            Duration::from_millis(manager.state_db.must_get(&keys::ChainParameter::MaxCpuTimeOfOneTxn) as u64);
+        let started_at = Instant::now();
+
+        let mut block_size = BLOCK_HEADER_RESERVED_SIZE;
+        let mut packed = vec![];
+        let mut dropped = vec![];
+
+        // All trial executions are discarded, the block is applied again by `push_produced_block`.
+        let old_layers = manager.layers;
+        manager.new_layer();
+
+        for mut txn in txns {
+            if started_at.elapsed() > BLOCK_PRODUCING_TIMEOUT {
+                warn!("block producing timeout, postpone remaining transactions");
+                break;
+            }
+            // NOTE: 4 bytes for protobuf field tag and length.
+            let txn_size = txn.raw.encoded_len() + 4;
+            if block_size + txn_size > constants::MAX_BLOCK_SIZE {
+                debug!("block size exceeds, postpone transaction {:?}", txn.hash);
+                continue;
+            }
+            if !manager.validate_transaction_tapos(&txn) ||
+                !manager.valide_transaction_common(&txn) ||
+                !manager.validate_duplicated_transaction(&txn)
+            {
+                dropped.push(txn.hash);
+                continue;
+            }
+            let recovered_addrs = match txn.recover_owner() {
+                Ok(addrs) => addrs,
+                Err(_) => {
+                    dropped.push(txn.hash);
+                    continue;
+                }
+            };
+
+            let layers_before_txn = manager.layers;
+            manager.new_layer();
+            let txn_started_at = Instant::now();
+            let maybe_receipt = TransactionExecutor::new(manager).execute(&txn, recovered_addrs, &header);
+            let elapsed = txn_started_at.elapsed();
+
+            match maybe_receipt {
+                Ok(receipt) if elapsed <= max_cpu_time => {
+                    txn.raw.result = vec![transaction_result_of(&receipt)];
+                    block_size += txn_size;
+                    packed.push(txn.raw);
+                }
+                Ok(_) => {
+                    warn!("transaction {:?} exceeds max cpu time, elapsed={:?}", txn.hash, elapsed);
+                    manager.rollback_layers(manager.layers - layers_before_txn);
+                    dropped.push(txn.hash);
+                }
+                Err(e) => {
+                    debug!("transaction {:?} failed: {}", txn.hash, e);
+                    manager.rollback_layers(manager.layers - layers_before_txn);
+                    dropped.push(txn.hash);
+                }
+            }
+        }
+
+        manager.rollback_layers(manager.layers - old_layers);
+
+        let mut block = IndexedBlock::from_raw(Block {
+            block_header: Some(BlockHeader {
+                raw_data: Some(raw_header),
+                ..Default::default()
+            }),
+            transactions: packed,
+        })
+        .ok_or_else(|| new_error("invalid block"))?;
+
+        // NOTE: `account_state_root` is left empty, it's not computed by OpenTron. See `process_block`.
+
+        // . sign block header
+        let mut buf = Vec::with_capacity(255);
+        block.header.raw.raw_data.as_ref().unwrap().encode(&mut buf)?;
+        let signature = keypair.private().sign(&buf)?;
+        block.header.raw.witness_signature = signature.as_bytes().to_vec();
+
+        if !manager.push_produced_block(&block)? {
+            return Err(new_error("produced block is not accepted"));
+        }
+
+        info!(
+            "produced block #{} txns={} dropped={} size={} time={:?}",
+            block.number(),
+            block.transactions.len(),
+            dropped.len(),
+            block_size,
+            started_at.elapsed()
+        );
+        Ok((block, dropped))
+    }
+}
+
+/// Transaction result saved in block, derived from execution receipt.
+fn transaction_result_of(receipt: &TransactionReceipt) -> TransactionResult {
+    let contract_status = if receipt.vm_status == ContractStatus::Default as i32 {
+        ContractStatus::Success as i32
+    } else {
+        receipt.vm_status
+    };
+    TransactionResult {
+        contract_status,
+        asset_issue_id: if receipt.asset_created_token_id != 0 {
+            receipt.asset_created_token_id.to_string()
+        } else {
+            "".into()
+        },
+        withdraw_amount: receipt.withdrawal_amount,
+        unfreeze_amount: receipt.unfrozen_amount,
+        exchange_id: receipt.exchange_created_exchange_id,
+        exchange_received_amount: receipt.exchange_received_amount,
+        exchange_inject_another_amount: receipt.exchange_injected_amount,
+        exchange_withdraw_another_amount: receipt.exchange_withdrawal_amount,
+        ..Default::default()
+    }
+}
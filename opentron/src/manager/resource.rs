@@ -12,7 +12,7 @@ use state::keys;
 
 use super::executor::actuators::asset::find_asset_by_name;
 use super::executor::actuators::BuiltinContractExt;
-use super::executor::TransactionContext;
+use super::executor::{ExecutionError, TransactionContext};
 use super::version_fork::ForkController;
 use super::Manager;
 
@@ -39,7 +39,7 @@ impl<C: BuiltinContractExt> BandwidthProcessor<'_, C> {
         manager: &'a mut Manager,
         txn: &'a IndexedTransaction,
         cntr: &'a C,
-    ) -> Result<BandwidthProcessor<'a, C>, String> {
+    ) -> Result<BandwidthProcessor<'a, C>, ExecutionError> {
         // NOTE: only first result is used.
         if !txn.raw.result.is_empty() && txn.raw.result[0].encoded_len() > constants::MAX_TRANSACTION_RESULT_SIZE {
             return Err("transaction result is too big".into());
@@ -56,7 +56,7 @@ impl<C: BuiltinContractExt> BandwidthProcessor<'_, C> {
         })
     }
 
-    pub fn consume(mut self, ctx: &mut TransactionContext) -> Result<(), String> {
+    pub fn consume(mut self, ctx: &mut TransactionContext) -> Result<(), ExecutionError> {
         let byte_size = if self.manager.state_db.must_get(&keys::ChainParameter::AllowTvm) == 1 {
             if self.txn.raw.result.is_empty() {
                 self.txn.raw.encoded_len() + constants::MAX_TRANSACTION_RESULT_SIZE
@@ -74,9 +74,14 @@ impl<C: BuiltinContractExt> BandwidthProcessor<'_, C> {
         // NOTE: multisig_fee is consumed in BandwidthProcessor
         if ctx.multisig_fee != 0 {
             debug!("consume multisig fee");
-            self.acct
-                .adjust_balance(-ctx.multisig_fee)
-                .map_err(|_| "insufficient balance to multisig")?;
+            if self.acct.adjust_balance(-ctx.multisig_fee).is_err() {
+                return Err(ExecutionError::insufficient_balance(
+                    "insufficient balance to multisig",
+                    self.addr,
+                    ctx.multisig_fee,
+                    self.acct.balance,
+                ));
+            }
         }
 
         // NOTE: `now` is not a timestamp, it's a `slot`.
@@ -90,7 +95,13 @@ impl<C: BuiltinContractExt> BandwidthProcessor<'_, C> {
                 // covers all bw expense
                 return Ok(());
             } else {
-                return Err("insufficient balance to create new account".into());
+                let creation_fee = self.manager.state_db.must_get(&keys::ChainParameter::AccountCreateFee);
+                return Err(ExecutionError::insufficient_bandwidth(
+                    "insufficient balance to create new account",
+                    self.addr,
+                    creation_fee,
+                    self.acct.balance,
+                ));
             }
         }
 
@@ -116,7 +127,13 @@ impl<C: BuiltinContractExt> BandwidthProcessor<'_, C> {
             return Ok(());
         }
 
-        Err("insufficient bandwidth".into())
+        let bw_fee = self.manager.state_db.must_get(&keys::ChainParameter::BandwidthFee) * byte_size;
+        Err(ExecutionError::insufficient_bandwidth(
+            "insufficient bandwidth, and insufficient balance to burn for it",
+            self.addr,
+            bw_fee,
+            self.acct.balance,
+        ))
     }
 
     // Renamed: useTransactionFee
@@ -497,7 +514,7 @@ impl EnergyProcessor<'_> {
         caller_percent: i64,
         origin_energy_limit: i64,
         ctx: &mut TransactionContext,
-    ) -> Result<(), String> {
+    ) -> Result<(), ExecutionError> {
         if energy_used <= 0 {
             return Ok(());
         }
@@ -541,7 +558,7 @@ impl EnergyProcessor<'_> {
         energy_used: i64,
         now: i64,
         ctx: &mut TransactionContext,
-    ) -> Result<(), String> {
+    ) -> Result<(), ExecutionError> {
         let energy_left = EnergyUtil::new(self.manager).get_left_frozen_energy(&acct);
 
         if energy_left >= energy_used {
@@ -566,7 +583,12 @@ impl EnergyProcessor<'_> {
             let energy_fee = (energy_used - energy_left) * energy_price;
 
             if acct.adjust_balance(-energy_fee).is_err() {
-                return Err("insufficient balance to burn for energy".into());
+                return Err(ExecutionError::insufficient_energy(
+                    "insufficient balance to burn for energy",
+                    addr,
+                    energy_fee,
+                    acct.balance,
+                ));
             }
             ctx.energy_fee = energy_fee;
             self.manager.add_to_blackhole(energy_fee).unwrap();
@@ -0,0 +1,245 @@
+//! Call tracer, a nested tree of calls of a TVM execution.
+
+use primitive_types::{H160, U256};
+use tvm::tracing::{self, gasometer, Event};
+use tvm::ExitReason;
+
+use super::Tracer;
+
+/// Function selector of `Error(string)`, used as revert reason.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+#[derive(Debug, Clone, Default)]
+pub struct CallFrame {
+    /// CALL, CALLCODE, DELEGATECALL, STATICCALL, CREATE or SELFDESTRUCT.
+    pub call_type: &'static str,
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+    pub token_id: U256,
+    pub token_value: U256,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    /// Energy provided to the call.
+    pub energy: u64,
+    pub energy_used: u64,
+    /// Exit reason if the call fails.
+    pub error: Option<String>,
+    pub revert_reason: Option<String>,
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    fn exit(&mut self, reason: &ExitReason, return_value: &[u8]) {
+        self.output = return_value.to_vec();
+        match reason {
+            ExitReason::Succeed(_) => {}
+            ExitReason::Revert(_) => {
+                self.error = Some("execution reverted".into());
+                self.revert_reason = decode_revert_reason(return_value);
+            }
+            _ => self.error = Some(format!("{:?}", reason)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CallTracer {
+    // Executing frames, the root frame is at the bottom.
+    frames: Vec<CallFrame>,
+    // Address of the executing code's storage, of each frame.
+    addresses: Vec<H160>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Take the root call frame, after the execution finishes.
+    pub fn take_root(&mut self) -> Option<CallFrame> {
+        self.root.take()
+    }
+
+    fn enter(&mut self, frame: CallFrame, address: H160) {
+        self.frames.push(frame);
+        self.addresses.push(address);
+    }
+}
+
+impl Tracer for CallTracer {
+    fn on_start(&mut self, context: &tvm::Context, input: &[u8], energy_limit: usize, is_create: bool) {
+        let frame = CallFrame {
+            call_type: if is_create { "CREATE" } else { "CALL" },
+            from: context.caller,
+            to: context.address,
+            value: context.call_value,
+            token_id: context.call_token_id,
+            token_value: context.call_token_value,
+            input: input.to_vec(),
+            energy: energy_limit as u64,
+            ..Default::default()
+        };
+        self.frames.clear();
+        self.addresses.clear();
+        self.enter(frame, context.address);
+    }
+
+    fn on_finish(&mut self, reason: &ExitReason, return_value: &[u8], used_energy: usize) {
+        // Frames left by a fatal error are folded into the root.
+        while self.frames.len() > 1 {
+            let frame = self.frames.pop().unwrap();
+            self.addresses.pop();
+            self.frames.last_mut().unwrap().calls.push(frame);
+        }
+        if let Some(mut root) = self.frames.pop() {
+            root.exit(reason, return_value);
+            root.energy_used = used_energy as u64;
+            self.root = Some(root);
+        }
+        self.addresses.clear();
+    }
+
+    fn on_event(&mut self, event: tracing::Event) {
+        let current_address = match self.addresses.last() {
+            Some(&addr) => addr,
+            None => return,
+        };
+        match event {
+            Event::Call {
+                code_address,
+                input,
+                target_gas,
+                is_static,
+                context,
+                ..
+            } => {
+                let call_type = if is_static {
+                    "STATICCALL"
+                } else if context.address == code_address {
+                    "CALL"
+                } else if context.caller == current_address {
+                    "CALLCODE"
+                } else {
+                    "DELEGATECALL"
+                };
+                let frame = CallFrame {
+                    call_type,
+                    from: current_address,
+                    to: code_address,
+                    value: context.call_value,
+                    token_id: context.call_token_id,
+                    token_value: context.call_token_value,
+                    input: input.to_vec(),
+                    energy: target_gas.unwrap_or_default(),
+                    ..Default::default()
+                };
+                self.enter(frame, context.address);
+            }
+            Event::Create {
+                caller,
+                address,
+                value,
+                init_code,
+                target_gas,
+                ..
+            } => {
+                let frame = CallFrame {
+                    call_type: "CREATE",
+                    from: caller,
+                    to: address,
+                    value,
+                    input: init_code.to_vec(),
+                    energy: target_gas.unwrap_or_default(),
+                    ..Default::default()
+                };
+                self.enter(frame, address);
+            }
+            Event::Suicide {
+                address,
+                target,
+                balance,
+            } => {
+                let frame = CallFrame {
+                    call_type: "SELFDESTRUCT",
+                    from: address,
+                    to: target,
+                    value: balance,
+                    ..Default::default()
+                };
+                self.frames.last_mut().unwrap().calls.push(frame);
+            }
+            Event::Exit { reason, return_value } => {
+                // The top-level exit is handled by `on_finish`.
+                if self.frames.len() > 1 {
+                    let mut frame = self.frames.pop().unwrap();
+                    self.addresses.pop();
+                    frame.exit(reason, return_value);
+                    self.frames.last_mut().unwrap().calls.push(frame);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_gasometer_event(&mut self, event: gasometer::Event) {
+        // Energy used by an inner call, as of its last recorded cost.
+        let used = match event {
+            gasometer::Event::RecordCost {
+                cost,
+                snapshot: Some(s),
+            } => s.used_gas + s.memory_gas + cost,
+            gasometer::Event::RecordDynamicCost {
+                gas_cost,
+                memory_gas,
+                snapshot: Some(s),
+                ..
+            } => s.used_gas + gas_cost + memory_gas.max(s.memory_gas),
+            _ => return,
+        };
+        if let Some(frame) = self.frames.last_mut() {
+            frame.energy_used = used;
+        }
+    }
+}
+
+/// Decode ABI encoded `Error(string)`.
+fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 4 + 32 + 32 || data[..4] != ERROR_SELECTOR {
+        return None;
+    }
+    let data = &data[4..];
+    let offset = U256::from_big_endian(&data[..32]);
+    if offset > U256::from(data.len() - 32) {
+        return None;
+    }
+    let offset = offset.as_usize();
+    let len = U256::from_big_endian(&data[offset..offset + 32]);
+    if len > U256::from(data.len() - offset - 32) {
+        return None;
+    }
+    let reason = &data[offset + 32..offset + 32 + len.as_usize()];
+    Some(String::from_utf8_lossy(reason).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_revert_reason() {
+        // revert("not owner")
+        let data = hex::decode(
+            "08c379a0\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             0000000000000000000000000000000000000000000000000000000000000009\
+             6e6f74206f776e65720000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        assert_eq!(decode_revert_reason(&data), Some("not owner".into()));
+
+        assert_eq!(decode_revert_reason(&data[..40]), None);
+        assert_eq!(decode_revert_reason(&[]), None);
+    }
+}
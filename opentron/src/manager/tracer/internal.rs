@@ -0,0 +1,125 @@
+//! Internal transactions, aka. CALL/CREATE/SUICIDE and value transfers inside TVM.
+
+use ::keys::Address;
+use crypto::keccak256;
+use primitive_types::{H160, H256, U256};
+use proto2::state::InternalTransaction;
+use tvm::tracing::{Event, EventListener};
+use tvm::ExitReason;
+
+/// Records internal transactions of a TVM execution.
+pub struct InternalTransactionRecorder {
+    root_hash: H256,
+    // The top-level contract address.
+    root_address: H160,
+    // Executing inner frames, (address, index of the internal transaction which entered the frame).
+    frames: Vec<(H160, usize)>,
+    transactions: Vec<InternalTransaction>,
+}
+
+impl InternalTransactionRecorder {
+    pub fn new(root_hash: H256, root_address: H160) -> Self {
+        InternalTransactionRecorder {
+            root_hash,
+            root_address,
+            frames: vec![],
+            transactions: vec![],
+        }
+    }
+
+    /// Take the recorded internal transactions. All of them are rejected when the execution fails.
+    pub fn into_transactions(mut self, succeed: bool) -> Vec<InternalTransaction> {
+        if !succeed {
+            self.transactions.iter_mut().for_each(|txn| txn.accepted = false);
+        }
+        self.transactions
+    }
+
+    fn current_address(&self) -> H160 {
+        self.frames.last().map(|&(addr, _)| addr).unwrap_or(self.root_address)
+    }
+
+    fn push(
+        &mut self,
+        caller: H160,
+        to: H160,
+        call_value: U256,
+        call_token: Option<(U256, U256)>,
+        data: &[u8],
+        note: &str,
+    ) -> usize {
+        let index = self.transactions.len();
+        // Derived from the root transaction hash, unique in a transaction.
+        let mut raw = self.root_hash.as_bytes().to_vec();
+        raw.extend_from_slice(&(index as u64).to_be_bytes());
+
+        let (call_token_id, call_token_value) = call_token.unwrap_or_default();
+        self.transactions.push(InternalTransaction {
+            hash: keccak256(&raw).as_bytes().to_vec(),
+            caller_address: Address::from_tvm_bytes(caller.as_bytes()).as_bytes().to_vec(),
+            to_address: Address::from_tvm_bytes(to.as_bytes()).as_bytes().to_vec(),
+            call_value: saturating_i64(call_value),
+            call_token_id: saturating_i64(call_token_id),
+            call_token_value: saturating_i64(call_token_value),
+            data: data.to_vec(),
+            note: note.as_bytes().to_vec(),
+            accepted: true,
+        });
+        index
+    }
+}
+
+/// Convert a TVM value to i64. Values above `i64::MAX` are saturated instead of truncated, such calls always fail.
+fn saturating_i64(value: U256) -> i64 {
+    if value > U256::from(i64::max_value()) {
+        i64::max_value()
+    } else {
+        value.as_u64() as i64
+    }
+}
+
+impl EventListener for InternalTransactionRecorder {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Call {
+                code_address,
+                input,
+                context,
+                ..
+            } => {
+                let caller = self.current_address();
+                let call_token = if context.call_token_value.is_zero() {
+                    None
+                } else {
+                    Some((context.call_token_id, context.call_token_value))
+                };
+                let index = self.push(caller, code_address, context.call_value, call_token, input, "call");
+                self.frames.push((context.address, index));
+            }
+            Event::Create {
+                caller, address, value, ..
+            } => {
+                let index = self.push(caller, address, value, None, &[], "create");
+                self.frames.push((address, index));
+            }
+            Event::Suicide {
+                address,
+                target,
+                balance,
+            } => {
+                self.push(address, target, balance, None, &[], "suicide");
+            }
+            Event::Exit { reason, .. } => {
+                if let Some((_, index)) = self.frames.pop() {
+                    // A failed frame rejects itself and all internal transactions inside.
+                    if !matches!(reason, ExitReason::Succeed(_)) {
+                        for txn in &mut self.transactions[index..] {
+                            txn.accepted = false;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
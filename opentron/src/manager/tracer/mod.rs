@@ -0,0 +1,135 @@
+//! Listeners of TVM execution events.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use proto2::state::InternalTransaction;
+use tvm::backend::Backend;
+use tvm::tracing::{self, gasometer, runtime, EventListener};
+use tvm::ExitReason;
+
+use super::vm::BuiltinJournal;
+
+pub use self::call::{CallFrame, CallTracer};
+pub use self::internal::InternalTransactionRecorder;
+pub use self::opcode::{opcode_name, OpcodeTraceOptions, OpcodeTracer, StructLog};
+pub use self::prestate::{AccountState, AccountValues, PrestateTracer};
+
+mod call;
+mod internal;
+mod opcode;
+mod prestate;
+
+/// A tracer of TVM execution, attached to the manager while tracing.
+pub trait Tracer: Send {
+    /// Start of the top-level execution.
+    fn on_start(&mut self, _context: &tvm::Context, _input: &[u8], _energy_limit: usize, _is_create: bool) {}
+
+    /// End of the top-level execution.
+    fn on_finish(&mut self, _reason: &ExitReason, _return_value: &[u8], _used_energy: usize) {}
+
+    /// State touched by the execution, captured before and after TVM changes are applied.
+    fn on_state(&mut self, _backend: &dyn Backend, _applied: bool) {}
+
+    /// Executor events, CALL, CREATE, SUICIDE and exits of inner calls.
+    fn on_event(&mut self, _event: tracing::Event) {}
+
+    /// Interpreter events, steps and storage accesses.
+    fn on_runtime_event(&mut self, _event: runtime::Event) {}
+
+    /// Gasometer events, energy costs of steps.
+    fn on_gasometer_event(&mut self, _event: gasometer::Event) {}
+}
+
+pub type SharedTracer = Arc<Mutex<dyn Tracer>>;
+
+/// Listens to a TVM execution, records internal transactions, and feeds the tracer if any.
+pub struct VmListener {
+    recorder: InternalTransactionRecorder,
+    tracer: Option<SharedTracer>,
+    builtins: Option<Rc<RefCell<BuiltinJournal>>>,
+}
+
+impl VmListener {
+    pub fn new(recorder: InternalTransactionRecorder, tracer: Option<SharedTracer>) -> Self {
+        VmListener {
+            recorder,
+            tracer,
+            builtins: None,
+        }
+    }
+
+    /// Feed call frames to the builtin contracts journal of the backend, so reverted ones are rolled back.
+    pub fn track_builtins(&mut self, journal: Rc<RefCell<BuiltinJournal>>) {
+        self.builtins = Some(journal);
+    }
+
+    /// Run the execution `f` with all events listened.
+    pub fn listen<R, F: FnOnce() -> R>(&mut self, f: F) -> R {
+        match self.tracer.clone() {
+            Some(tracer) => {
+                let mut runtime_listener = RuntimeListener(tracer.clone());
+                let mut gasometer_listener = GasometerListener(tracer);
+                tracing::using(self, || {
+                    runtime::using(&mut runtime_listener, || gasometer::using(&mut gasometer_listener, f))
+                })
+            }
+            None => tracing::using(self, f),
+        }
+    }
+
+    pub fn start(&self, context: &tvm::Context, input: &[u8], energy_limit: usize, is_create: bool) {
+        if let Some(ref tracer) = self.tracer {
+            tracer.lock().unwrap().on_start(context, input, energy_limit, is_create);
+        }
+    }
+
+    pub fn finish(&self, reason: &ExitReason, return_value: &[u8], used_energy: usize) {
+        if let Some(ref tracer) = self.tracer {
+            tracer.lock().unwrap().on_finish(reason, return_value, used_energy);
+        }
+    }
+
+    pub fn capture_state(&self, backend: &dyn Backend, applied: bool) {
+        if let Some(ref tracer) = self.tracer {
+            tracer.lock().unwrap().on_state(backend, applied);
+        }
+    }
+
+    pub fn into_internal_transactions(self, succeed: bool) -> Vec<InternalTransaction> {
+        self.recorder.into_transactions(succeed)
+    }
+}
+
+impl EventListener for VmListener {
+    fn event(&mut self, event: tracing::Event) {
+        self.recorder.event(event);
+        if let Some(ref builtins) = self.builtins {
+            match event {
+                tracing::Event::Call { .. } | tracing::Event::Create { .. } => builtins.borrow_mut().enter(),
+                tracing::Event::Exit { reason, .. } => builtins.borrow_mut().exit(reason.is_succeed()),
+                _ => {}
+            }
+        }
+        if let Some(ref tracer) = self.tracer {
+            tracer.lock().unwrap().on_event(event);
+        }
+    }
+}
+
+struct RuntimeListener(SharedTracer);
+
+impl runtime::EventListener for RuntimeListener {
+    fn event(&mut self, event: runtime::Event) {
+        self.0.lock().unwrap().on_runtime_event(event);
+    }
+}
+
+struct GasometerListener(SharedTracer);
+
+impl gasometer::EventListener for GasometerListener {
+    fn event(&mut self, event: gasometer::Event) {
+        self.0.lock().unwrap().on_gasometer_event(event);
+    }
+}
@@ -0,0 +1,322 @@
+//! Opcode level tracer, aka. struct logs.
+
+use primitive_types::H256;
+use tvm::tracing::{self, gasometer, runtime};
+
+use super::Tracer;
+
+#[derive(Debug, Clone, Default)]
+pub struct OpcodeTraceOptions {
+    pub disable_stack: bool,
+    pub disable_memory: bool,
+    pub disable_storage: bool,
+}
+
+/// Trace of a single step.
+#[derive(Debug, Clone, Default)]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: u8,
+    /// Call depth, starts from 1.
+    pub depth: usize,
+    /// Remaining energy before the step.
+    pub energy: i64,
+    pub energy_cost: i64,
+    pub stack: Option<Vec<H256>>,
+    /// Changed memory since the last step of the same call, as (offset, data).
+    pub memory_diff: Option<Vec<(usize, Vec<u8>)>>,
+    /// Storage written by the step, as (index, value).
+    pub storage: Option<Vec<(H256, H256)>>,
+}
+
+pub struct OpcodeTracer {
+    options: OpcodeTraceOptions,
+    logs: Vec<StructLog>,
+    // Whether energy of the last step is filled from a gasometer snapshot.
+    energy_filled: bool,
+    // Memory of each call at its last step.
+    memories: Vec<Vec<u8>>,
+}
+
+impl OpcodeTracer {
+    pub fn new(options: OpcodeTraceOptions) -> Self {
+        OpcodeTracer {
+            options,
+            logs: vec![],
+            energy_filled: false,
+            memories: vec![vec![]],
+        }
+    }
+
+    pub fn take_logs(&mut self) -> Vec<StructLog> {
+        std::mem::replace(&mut self.logs, vec![])
+    }
+
+    fn record_cost(&mut self, cost: i64, remaining: Option<i64>) {
+        if let Some(log) = self.logs.last_mut() {
+            log.energy_cost += cost;
+            if !self.energy_filled {
+                if let Some(remaining) = remaining {
+                    log.energy = remaining;
+                    self.energy_filled = true;
+                }
+            }
+        }
+    }
+}
+
+impl Tracer for OpcodeTracer {
+    fn on_event(&mut self, event: tracing::Event) {
+        match event {
+            tracing::Event::Call { .. } | tracing::Event::Create { .. } => self.memories.push(vec![]),
+            tracing::Event::Exit { .. } => {
+                if self.memories.len() > 1 {
+                    self.memories.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_runtime_event(&mut self, event: runtime::Event) {
+        match event {
+            runtime::Event::Step {
+                opcode,
+                position,
+                stack,
+                memory,
+                ..
+            } => {
+                let pc = match position {
+                    Ok(pc) => *pc,
+                    Err(_) => return,
+                };
+                let stack = if self.options.disable_stack {
+                    None
+                } else {
+                    Some(stack.data().clone())
+                };
+                let memory_diff = if self.options.disable_memory {
+                    None
+                } else {
+                    let last_memory = self.memories.last_mut().unwrap();
+                    let diff = memory_diff(last_memory, memory.data());
+                    *last_memory = memory.data().clone();
+                    Some(diff)
+                };
+                self.logs.push(StructLog {
+                    pc,
+                    op: opcode.as_u8(),
+                    depth: self.memories.len(),
+                    stack,
+                    memory_diff,
+                    storage: if self.options.disable_storage {
+                        None
+                    } else {
+                        Some(vec![])
+                    },
+                    ..Default::default()
+                });
+                self.energy_filled = false;
+            }
+            runtime::Event::SStore { index, value, .. } => {
+                if let Some(storage) = self.logs.last_mut().and_then(|log| log.storage.as_mut()) {
+                    storage.push((index, value));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_gasometer_event(&mut self, event: gasometer::Event) {
+        match event {
+            gasometer::Event::RecordCost { cost, snapshot } => {
+                let remaining = snapshot.map(|s| s.gas_limit as i64 - s.used_gas as i64 - s.memory_gas as i64);
+                self.record_cost(cost as i64, remaining);
+            }
+            gasometer::Event::RecordDynamicCost {
+                gas_cost,
+                memory_gas,
+                snapshot,
+                ..
+            } => {
+                // Memory energy is charged by the expansion part only.
+                let memory_cost = snapshot
+                    .map(|s| (memory_gas as i64 - s.memory_gas as i64).max(0))
+                    .unwrap_or_default();
+                let remaining = snapshot.map(|s| s.gas_limit as i64 - s.used_gas as i64 - s.memory_gas as i64);
+                self.record_cost(gas_cost as i64 + memory_cost, remaining);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Diff of memory in 32-byte words, adjacent changed words are merged.
+fn memory_diff(old: &[u8], new: &[u8]) -> Vec<(usize, Vec<u8>)> {
+    const WORD_SIZE: usize = 32;
+
+    let mut diff: Vec<(usize, Vec<u8>)> = vec![];
+    for (i, word) in new.chunks(WORD_SIZE).enumerate() {
+        let offset = i * WORD_SIZE;
+        if old.get(offset..offset + word.len()) == Some(word) {
+            continue;
+        }
+        match diff.last_mut() {
+            Some((last_offset, data)) if *last_offset + data.len() == offset => data.extend_from_slice(word),
+            _ => diff.push((offset, word.to_vec())),
+        }
+    }
+    diff
+}
+
+/// Mnemonic of a TVM opcode.
+pub fn opcode_name(op: u8) -> &'static str {
+    match op {
+        0x00 => "STOP",
+        0x01 => "ADD",
+        0x02 => "MUL",
+        0x03 => "SUB",
+        0x04 => "DIV",
+        0x05 => "SDIV",
+        0x06 => "MOD",
+        0x07 => "SMOD",
+        0x08 => "ADDMOD",
+        0x09 => "MULMOD",
+        0x0a => "EXP",
+        0x0b => "SIGNEXTEND",
+        0x10 => "LT",
+        0x11 => "GT",
+        0x12 => "SLT",
+        0x13 => "SGT",
+        0x14 => "EQ",
+        0x15 => "ISZERO",
+        0x16 => "AND",
+        0x17 => "OR",
+        0x18 => "XOR",
+        0x19 => "NOT",
+        0x1a => "BYTE",
+        0x1b => "SHL",
+        0x1c => "SHR",
+        0x1d => "SAR",
+        0x20 => "SHA3",
+        0x30 => "ADDRESS",
+        0x31 => "BALANCE",
+        0x32 => "ORIGIN",
+        0x33 => "CALLER",
+        0x34 => "CALLVALUE",
+        0x35 => "CALLDATALOAD",
+        0x36 => "CALLDATASIZE",
+        0x37 => "CALLDATACOPY",
+        0x38 => "CODESIZE",
+        0x39 => "CODECOPY",
+        0x3a => "GASPRICE",
+        0x3b => "EXTCODESIZE",
+        0x3c => "EXTCODECOPY",
+        0x3d => "RETURNDATASIZE",
+        0x3e => "RETURNDATACOPY",
+        0x3f => "EXTCODEHASH",
+        0x40 => "BLOCKHASH",
+        0x41 => "COINBASE",
+        0x42 => "TIMESTAMP",
+        0x43 => "NUMBER",
+        0x44 => "DIFFICULTY",
+        0x45 => "GASLIMIT",
+        0x46 => "CHAINID",
+        0x47 => "SELFBALANCE",
+        0x50 => "POP",
+        0x51 => "MLOAD",
+        0x52 => "MSTORE",
+        0x53 => "MSTORE8",
+        0x54 => "SLOAD",
+        0x55 => "SSTORE",
+        0x56 => "JUMP",
+        0x57 => "JUMPI",
+        0x58 => "PC",
+        0x59 => "MSIZE",
+        0x5a => "GAS",
+        0x5b => "JUMPDEST",
+        0x60..=0x7f => PUSH_NAMES[(op - 0x60) as usize],
+        0x80..=0x8f => DUP_NAMES[(op - 0x80) as usize],
+        0x90..=0x9f => SWAP_NAMES[(op - 0x90) as usize],
+        0xa0 => "LOG0",
+        0xa1 => "LOG1",
+        0xa2 => "LOG2",
+        0xa3 => "LOG3",
+        0xa4 => "LOG4",
+        // TRON extensions.
+        0xd0 => "CALLTOKEN",
+        0xd1 => "TOKENBALANCE",
+        0xd2 => "CALLTOKENVALUE",
+        0xd3 => "CALLTOKENID",
+        0xd4 => "ISCONTRACT",
+        0xd5 => "STAKE",
+        0xd6 => "UNSTAKE",
+        0xd7 => "WITHDRAWREWARD",
+        0xd8 => "REWARDBALANCE",
+        0xd9 => "ISSRCANDIDATE",
+        0xda => "TOKENISSUE",
+        0xdb => "UPDATEASSET",
+        0xf0 => "CREATE",
+        0xf1 => "CALL",
+        0xf2 => "CALLCODE",
+        0xf3 => "RETURN",
+        0xf4 => "DELEGATECALL",
+        0xf5 => "CREATE2",
+        0xfa => "STATICCALL",
+        0xfd => "REVERT",
+        0xfe => "INVALID",
+        0xff => "SUICIDE",
+        _ => "UNKNOWN",
+    }
+}
+
+const PUSH_NAMES: [&str; 32] = [
+    "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10", "PUSH11", "PUSH12",
+    "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19", "PUSH20", "PUSH21", "PUSH22", "PUSH23",
+    "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28", "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+];
+
+const DUP_NAMES: [&str; 16] = [
+    "DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11", "DUP12", "DUP13",
+    "DUP14", "DUP15", "DUP16",
+];
+
+const SWAP_NAMES: [&str; 16] = [
+    "SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10", "SWAP11", "SWAP12",
+    "SWAP13", "SWAP14", "SWAP15", "SWAP16",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_diff() {
+        let old = vec![0u8; 64];
+        let mut new = vec![0u8; 128];
+        assert_eq!(memory_diff(&old, &old), vec![]);
+
+        new[40] = 1;
+        new[70] = 2;
+        new[100] = 3;
+        let diff = memory_diff(&old, &new);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].0, 32);
+        assert_eq!(diff[0].1.len(), 96);
+
+        // expanded memory with zeros is also changed
+        let diff = memory_diff(&old, &vec![0u8; 96]);
+        assert_eq!(diff, vec![(64, vec![0u8; 32])]);
+    }
+
+    #[test]
+    fn test_opcode_name() {
+        assert_eq!(opcode_name(0x60), "PUSH1");
+        assert_eq!(opcode_name(0x7f), "PUSH32");
+        assert_eq!(opcode_name(0x8f), "DUP16");
+        assert_eq!(opcode_name(0x90), "SWAP1");
+        assert_eq!(opcode_name(0xd4), "ISCONTRACT");
+        assert_eq!(opcode_name(0x0c), "UNKNOWN");
+    }
+}
@@ -0,0 +1,138 @@
+//! Prestate tracer, all state read or written by a TVM execution, with values before and after it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use primitive_types::{H160, H256, U256};
+use tvm::backend::Backend;
+use tvm::tracing::{self, runtime, Event};
+
+use super::Tracer;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountValues {
+    pub balance: U256,
+    pub code: Vec<u8>,
+    /// Values of touched `ContractStorage` slots, missing slots are 0.
+    pub storage: BTreeMap<H256, H256>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountState {
+    pub address: H160,
+    pub before: AccountValues,
+    pub after: AccountValues,
+}
+
+#[derive(Default)]
+pub struct PrestateTracer {
+    // Touched accounts and storage slots.
+    touched: BTreeMap<H160, BTreeSet<H256>>,
+    before: BTreeMap<H160, AccountValues>,
+    after: BTreeMap<H160, AccountValues>,
+}
+
+impl PrestateTracer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Take states of all touched accounts. If the execution is not applied, states after are the same as before.
+    pub fn take_states(&mut self) -> Vec<AccountState> {
+        let mut after = std::mem::replace(&mut self.after, BTreeMap::new());
+        std::mem::replace(&mut self.before, BTreeMap::new())
+            .into_iter()
+            .map(|(address, before)| AccountState {
+                address,
+                after: after.remove(&address).unwrap_or_else(|| before.clone()),
+                before,
+            })
+            .collect()
+    }
+
+    fn touch(&mut self, address: H160) {
+        self.touched.entry(address).or_default();
+    }
+
+    fn touch_storage(&mut self, address: H160, index: H256) {
+        self.touched.entry(address).or_default().insert(index);
+    }
+}
+
+impl Tracer for PrestateTracer {
+    fn on_start(&mut self, context: &tvm::Context, _input: &[u8], _energy_limit: usize, _is_create: bool) {
+        self.touch(context.caller);
+        self.touch(context.address);
+    }
+
+    fn on_event(&mut self, event: tracing::Event) {
+        match event {
+            Event::Call {
+                code_address,
+                transfer,
+                context,
+                ..
+            } => {
+                self.touch(code_address);
+                self.touch(context.address);
+                if let Some(transfer) = transfer {
+                    self.touch(transfer.source);
+                    self.touch(transfer.target);
+                }
+            }
+            Event::Create { caller, address, .. } => {
+                self.touch(caller);
+                self.touch(address);
+            }
+            Event::Suicide { address, target, .. } => {
+                self.touch(address);
+                self.touch(target);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_runtime_event(&mut self, event: runtime::Event) {
+        match event {
+            runtime::Event::Step { opcode, stack, .. } => {
+                // Position of the address operand of instructions reading other accounts.
+                let address_pos = match opcode.as_u8() {
+                    // BALANCE, EXTCODESIZE, EXTCODECOPY, EXTCODEHASH, ISCONTRACT, REWARDBALANCE
+                    0x31 | 0x3b | 0x3c | 0x3f | 0xd4 | 0xd8 => 0,
+                    // TOKENBALANCE(address, tokenId)
+                    0xd1 => 1,
+                    _ => return,
+                };
+                if let Ok(value) = stack.peek(address_pos) {
+                    self.touch(H160::from(value));
+                }
+            }
+            runtime::Event::SLoad { address, index, .. } | runtime::Event::SStore { address, index, .. } => {
+                self.touch_storage(address, index)
+            }
+            _ => {}
+        }
+    }
+
+    fn on_state(&mut self, backend: &dyn Backend, applied: bool) {
+        let states = self
+            .touched
+            .iter()
+            .map(|(&address, slots)| {
+                let values = AccountValues {
+                    balance: backend.basic(address).balance,
+                    code: backend.code(address),
+                    storage: slots
+                        .iter()
+                        .map(|&index| (index, backend.storage(address, index).unwrap_or_default()))
+                        .collect(),
+                };
+                (address, values)
+            })
+            .collect();
+        if applied {
+            self.after = states;
+        } else {
+            self.before = states;
+        }
+    }
+}
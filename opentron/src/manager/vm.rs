@@ -1,20 +1,28 @@
 //! The TVM backend.
 
-use std::collections::HashSet;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::mem;
+use std::rc::Rc;
 
 use ::keys::Address;
 use crypto::keccak256;
 use lazy_static::lazy_static;
 use log::debug;
 use primitive_types::{H160, H256, U256};
+use proto2::common::{ResourceCode, Vote};
+use proto2::contract as contract_pb;
 use proto2::state::{Account, AccountType, SmartContract, TransactionLog};
 use state::db::StateDB;
 use state::keys;
 use tvm::backend::{Apply, ApplyBackend, Backend, Basic, Log};
+use tvm::ExitError;
 
-use super::executor::actuators::validate_multisig;
-use super::executor::TransactionContext;
+use super::executor::actuators::asset::validate_asset_issue;
+use super::executor::actuators::{validate_multisig, BuiltinContractExecutorExt};
+use super::executor::{ExecutionError, TransactionContext};
+use super::governance::reward::RewardUtil;
 use super::Manager;
 
 lazy_static! {
@@ -29,27 +37,276 @@ lazy_static! {
     };
 }
 
+/// Frozen duration in days of STAKE.
+const STAKE_FROZEN_DURATION: i64 = 3;
+
+/// Balance changes of an account made by a builtin contract.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BalanceChange {
+    pub balance: i64,
+    pub token_balance: HashMap<i64, i64>,
+}
+
+/// Builtin contracts run by TVM instructions, e.g. STAKE, and their balance changes.
+///
+/// Builtin contracts write to the state-db directly, each in its own layer, while TVM keeps balances in its own
+/// substate. Their balance changes are hidden from `Backend::basic`, and added back in `ApplyBackend::apply`, so
+/// TVM does not see them until the execution ends. Layers of builtin contracts in a reverted call frame are
+/// rolled back.
+#[derive(Debug, Default)]
+pub struct BuiltinJournal {
+    // Balance changes of each builtin contract run, in the order of state-db layers.
+    changes: Vec<HashMap<Address, BalanceChange>>,
+    // Number of changes when entering each inner call frame.
+    frames: Vec<usize>,
+    // Layers of builtin contracts in reverted frames, not yet rolled back.
+    reverted_layers: usize,
+}
+
+impl BuiltinJournal {
+    /// Enter an inner call frame.
+    pub fn enter(&mut self) {
+        self.frames.push(self.changes.len());
+    }
+
+    /// Exit an inner call frame, builtin contracts run inside are reverted unless it succeeds.
+    pub fn exit(&mut self, succeed: bool) {
+        if let Some(start) = self.frames.pop() {
+            if !succeed {
+                self.reverted_layers += self.changes.len() - start;
+                self.changes.truncate(start);
+            }
+        }
+    }
+
+    fn balance_change(&self, addr: &Address) -> BalanceChange {
+        let mut total = BalanceChange::default();
+        for change in self.changes.iter().filter_map(|changes| changes.get(addr)) {
+            total.balance += change.balance;
+            for (&token_id, &amount) in &change.token_balance {
+                *total.token_balance.entry(token_id).or_default() += amount;
+            }
+        }
+        total
+    }
+}
+
+/// The manager behind a backend.
+enum ManagerHandle<'m> {
+    // Stake instructions write to the state-db during execution, while `Backend` is shared.
+    Mutable(RefCell<&'m mut Manager>),
+    // Constant calls. Wrapped in `RefCell` to share `Ref` with the mutable one.
+    ReadOnly(RefCell<&'m Manager>),
+}
+
 /// StateDB backend, storing all state values in a RocksDB instance.
 pub struct StateBackend<'m, 'c, 'ctx> {
-    manager: &'m mut Manager,
+    manager: ManagerHandle<'m>,
     ctx: &'c mut TransactionContext<'ctx>,
     sender: Address,
+    builtins: Rc<RefCell<BuiltinJournal>>,
 }
 
 impl<'m, 'c, 'ctx> StateBackend<'m, 'c, 'ctx> {
     /// Create a new StateDB backend.
     pub fn new(sender: Address, manager: &'m mut Manager, ctx: &'c mut TransactionContext<'ctx>) -> Self {
-        Self { manager, ctx, sender }
+        Self {
+            manager: ManagerHandle::Mutable(RefCell::new(manager)),
+            ctx,
+            sender,
+            builtins: Default::default(),
+        }
+    }
+
+    /// Create a read-only StateDB backend, for constant calls. Changes can only be discarded.
+    pub fn new_read_only(sender: Address, manager: &'m Manager, ctx: &'c mut TransactionContext<'ctx>) -> Self {
+        Self {
+            manager: ManagerHandle::ReadOnly(RefCell::new(manager)),
+            ctx,
+            sender,
+            builtins: Default::default(),
+        }
+    }
+
+    fn manager(&self) -> Ref<Manager> {
+        self.sync_builtins();
+        match self.manager {
+            ManagerHandle::Mutable(ref manager) => Ref::map(manager.borrow(), |manager| &**manager),
+            ManagerHandle::ReadOnly(ref manager) => Ref::map(manager.borrow(), |manager| *manager),
+        }
     }
 
     /// Get the underlying `StateDB` storing the state.
-    fn state(&self) -> &StateDB {
-        &self.manager.state_db
+    fn state(&self) -> Ref<StateDB> {
+        Ref::map(self.manager(), |manager| &manager.state_db)
+    }
+
+    /// Builtin contracts run in this execution, to be fed with call frames by the listener.
+    pub fn builtin_journal(&self) -> Rc<RefCell<BuiltinJournal>> {
+        self.builtins.clone()
+    }
+
+    /// Number of state-db layers created by builtin contracts, to be rolled back with the execution.
+    pub fn builtin_layers(&self) -> usize {
+        let builtins = self.builtins.borrow();
+        builtins.changes.len() + builtins.reverted_layers
+    }
+
+    /// Check that balances changed by both TVM and builtin contracts are not overdrawn.
+    pub fn check_builtin_balances<I>(&self, values: &[Apply<I>]) -> Result<(), ExitError> {
+        let builtins = self.builtins.borrow();
+        for apply in values {
+            if let Apply::Modify { address, basic, .. } = apply {
+                let change = builtins.balance_change(&Address::from_tvm_bytes(address.as_bytes()));
+                if basic.balance.as_u64() as i64 + change.balance < 0 {
+                    return Err(ExitError::OutOfFund);
+                }
+                for (token_id, amount) in change.token_balance {
+                    let token_value = match basic.token_balance.get(&U256::from(token_id)) {
+                        Some(value) => value.as_u64() as i64,
+                        None => 0,
+                    };
+                    if token_value + amount < 0 {
+                        return Err(ExitError::OutOfFund);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Roll back layers of builtin contracts in reverted call frames.
+    fn sync_builtins(&self) {
+        let reverted_layers = mem::take(&mut self.builtins.borrow_mut().reverted_layers);
+        if reverted_layers > 0 {
+            if let ManagerHandle::Mutable(ref manager) = self.manager {
+                manager.borrow_mut().rollback_layers(reverted_layers);
+            }
+        }
     }
 
     /// Get the underlying `StateDB` storing the state, mutable.
     fn state_mut(&mut self) -> &mut StateDB {
-        &mut self.manager.state_db
+        match self.manager {
+            ManagerHandle::Mutable(ref mut manager) => &mut manager.get_mut().state_db,
+            ManagerHandle::ReadOnly(_) => panic!("state-db is read-only in constant call"),
+        }
+    }
+
+    /// Discard changes of a constant call, logs are kept. Returns whether the changes modify the state.
+    pub fn discard<A, I, L>(&mut self, values: A, logs: L) -> bool
+    where
+        A: IntoIterator<Item = Apply<I>>,
+        I: IntoIterator<Item = (H256, H256)>,
+        L: IntoIterator<Item = Log>,
+    {
+        let mut modified = false;
+        for apply in values {
+            let changed = match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => {
+                    let current = self.basic(address);
+                    code.is_some() ||
+                        reset_storage ||
+                        storage.into_iter().next().is_some() ||
+                        basic.balance != current.balance ||
+                        basic.token_balance.iter().any(|(token_id, token_value)| {
+                            current.token_balance.get(token_id).copied().unwrap_or_default() != *token_value
+                        })
+                }
+                Apply::Delete { .. } => true,
+            };
+            modified = modified || changed;
+        }
+        let num_logs = self.ctx.logs.len();
+        self.push_logs(logs);
+        modified || self.ctx.logs.len() > num_logs
+    }
+
+    fn push_logs<L: IntoIterator<Item = Log>>(&mut self, logs: L) {
+        for Log { address, topics, data } in logs {
+            // let addr = Address::from_tvm_bytes(address.as_bytes());
+            self.ctx.logs.push(TransactionLog {
+                address: Address::from_tvm_bytes(address.as_bytes()).as_bytes().to_vec(),
+                topics: topics.iter().map(|t| t.as_bytes().to_vec()).collect(),
+                data: data,
+            });
+        }
+    }
+
+    /// Run a builtin contract on behalf of a contract account, with the same logic as the builtin actuator.
+    fn run_builtin_contract<C: BuiltinContractExecutorExt>(
+        &self,
+        cntr: C,
+    ) -> Result<TransactionContext<'ctx>, ExecutionError> {
+        self.run_builtin_contract_with(cntr, C::validate)
+    }
+
+    /// Run a builtin contract on behalf of a contract account, with a different validation.
+    ///
+    /// The builtin contract runs in its own state-db layer, and its balance changes are recorded in the journal.
+    fn run_builtin_contract_with<C, F>(&self, cntr: C, validate: F) -> Result<TransactionContext<'ctx>, ExecutionError>
+    where
+        C: BuiltinContractExecutorExt,
+        F: FnOnce(&C, &Manager, &mut TransactionContext<'ctx>) -> Result<(), ExecutionError>,
+    {
+        let mut ctx = TransactionContext::dummy(self.ctx.block_header);
+        ctx.transaction_hash = self.ctx.transaction_hash;
+
+        self.sync_builtins();
+        let mut manager = match self.manager {
+            ManagerHandle::Mutable(ref manager) => manager.borrow_mut(),
+            ManagerHandle::ReadOnly(_) => {
+                return Err(ExecutionError::Vm("state-db is read-only in constant call".into()))
+            }
+        };
+        let owner_address = Address::try_from(cntr.owner_address()).map_err(|_| "invalid owner_address")?;
+        // Builtin contracts only change balances of the owner and the blackhole.
+        let mut watched = vec![owner_address];
+        if manager.blackhole != owner_address {
+            watched.push(manager.blackhole);
+        }
+        let balances_before = watched
+            .iter()
+            .map(|&addr| manager.state_db.get(&keys::Account(addr)).map_err(ExecutionError::db))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        manager.new_layer();
+        if let Err(e) = validate(&cntr, &manager, &mut ctx).and_then(|_| cntr.execute(&mut manager, &mut ctx)) {
+            manager.rollback_layers(1);
+            return Err(e);
+        }
+
+        let mut changes = HashMap::new();
+        for (addr, before) in watched.into_iter().zip(balances_before) {
+            let before = before.unwrap_or_default();
+            let after = manager
+                .state_db
+                .get(&keys::Account(addr))
+                .map_err(ExecutionError::db)?
+                .unwrap_or_default();
+            let mut change = BalanceChange {
+                balance: after.balance - before.balance,
+                token_balance: HashMap::new(),
+            };
+            for (&token_id, &amount) in &after.token_balance {
+                change.token_balance.insert(token_id, amount);
+            }
+            for (&token_id, &amount) in &before.token_balance {
+                *change.token_balance.entry(token_id).or_default() -= amount;
+            }
+            change.token_balance.retain(|_, amount| *amount != 0);
+            if change != BalanceChange::default() {
+                changes.insert(addr, change);
+            }
+        }
+        self.builtins.borrow_mut().changes.push(changes);
+        Ok(ctx)
     }
 }
 
@@ -72,7 +329,7 @@ impl Backend for StateBackend<'_, '_, '_> {
         }
 
         let idx = (number.as_u64() & 0xffff) as usize;
-        self.manager.ref_block_hashes.get(idx).copied().unwrap_or_default()
+        self.manager().ref_block_hashes.get(idx).copied().unwrap_or_default()
     }
 
     fn block_number(&self) -> U256 {
@@ -92,8 +349,9 @@ impl Backend for StateBackend<'_, '_, '_> {
         0.into()
     }
 
+    // CHAINID: block id of the genesis block, since AllowTvmIstanbulUpgrade.
     fn chain_id(&self) -> U256 {
-        unimplemented!("hash of block #0")
+        U256::from_big_endian(self.manager().genesis_block_hash.as_bytes())
     }
 
     fn exists(&self, address: H160) -> bool {
@@ -106,15 +364,21 @@ impl Backend for StateBackend<'_, '_, '_> {
         self.state().get(&keys::Account(addr)).unwrap().is_some()
     }
 
+    // NOTE: Balance changes of builtin contracts are hidden, see `BuiltinJournal`.
     fn basic(&self, address: H160) -> Basic {
         let addr = Address::from_tvm_bytes(address.as_bytes());
+        let change = self.builtins.borrow().balance_change(&addr);
         self.state()
             .get(&keys::Account(addr))
             .unwrap()
             .map(|a| Basic {
-                balance: a.balance.into(),
+                balance: (a.balance - change.balance).into(),
                 nonce: 0.into(),
-                token_balance: a.token_balance.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
+                token_balance: a
+                    .token_balance
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), (v - change.token_balance.get(&k).copied().unwrap_or_default()).into()))
+                    .collect(),
             })
             .unwrap_or_default()
     }
@@ -181,6 +445,149 @@ impl Backend for StateBackend<'_, '_, '_> {
             .map_err(|e| log::error!("validata multisig error: {:?}", e))
             .is_ok()
     }
+
+    // ISSRCANDIDATE: whether the address is a witness.
+    fn is_witness(&self, address: H160) -> bool {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        self.state().get(&keys::Witness(addr)).unwrap().is_some()
+    }
+
+    // REWARDBALANCE: allowance plus the voting reward not yet withdrawn.
+    fn reward_balance(&self, address: H160) -> U256 {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        let manager = self.manager();
+        let allowance = manager
+            .state_db
+            .get(&keys::Account(addr))
+            .unwrap()
+            .map(|acct| acct.allowance)
+            .unwrap_or_default();
+        let reward = RewardUtil::new(&manager).query_reward(addr).unwrap_or_default();
+        (allowance + reward).into()
+    }
+
+    // STAKE: freeze balance for bandwidth up to `amount`, then vote all of it for the witness.
+    fn stake(&self, address: H160, witness: H160, amount: U256) -> bool {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        let wit_addr = Address::from_tvm_bytes(witness.as_bytes());
+        if amount > U256::from(i64::max_value()) {
+            return false;
+        }
+        let amount = amount.as_u64() as i64;
+
+        let frozen_amount = match self.state().get(&keys::Account(addr)).unwrap() {
+            Some(acct) => acct.frozen_amount_for_bandwidth,
+            None => return false,
+        };
+        if amount > frozen_amount {
+            let freeze = contract_pb::FreezeBalanceContract {
+                owner_address: addr.as_bytes().to_vec(),
+                frozen_balance: amount - frozen_amount,
+                frozen_duration: STAKE_FROZEN_DURATION,
+                resource: ResourceCode::Bandwidth as i32,
+                ..Default::default()
+            };
+            if let Err(e) = self.run_builtin_contract(freeze) {
+                debug!("STAKE freeze failed: {}", e);
+                return false;
+            }
+        }
+
+        let vote = contract_pb::VoteWitnessContract {
+            owner_address: addr.as_bytes().to_vec(),
+            votes: vec![Vote {
+                vote_address: wit_addr.as_bytes().to_vec(),
+                vote_count: amount / 1_000_000,
+            }],
+            ..Default::default()
+        };
+        self.run_builtin_contract(vote)
+            .map_err(|e| debug!("STAKE vote failed: {}", e))
+            .is_ok()
+    }
+
+    // UNSTAKE: unfreeze balance for bandwidth, which also clears all votes.
+    fn unstake(&self, address: H160) -> bool {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        let unfreeze = contract_pb::UnfreezeBalanceContract {
+            owner_address: addr.as_bytes().to_vec(),
+            resource: ResourceCode::Bandwidth as i32,
+            ..Default::default()
+        };
+        self.run_builtin_contract(unfreeze)
+            .map_err(|e| debug!("UNSTAKE failed: {}", e))
+            .is_ok()
+    }
+
+    // WITHDRAWREWARD: withdraw allowance and voting reward to balance, returns the amount withdrawn.
+    fn withdraw_reward(&self, address: H160) -> U256 {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        let withdraw = contract_pb::WithdrawBalanceContract {
+            owner_address: addr.as_bytes().to_vec(),
+        };
+        self.run_builtin_contract(withdraw)
+            .map(|ctx| ctx.withdrawal_amount.into())
+            .map_err(|e| debug!("WITHDRAWREWARD failed: {}", e))
+            .unwrap_or_default()
+    }
+
+    // TOKENISSUE: issue a TRC10 asset owned by the contract, returns the token id, or 0 on failure.
+    fn issue_token(&self, address: H160, name: H256, abbr: H256, total_supply: U256, precision: U256) -> U256 {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        if total_supply > U256::from(i64::max_value()) || precision > U256::from(i32::max_value()) {
+            return U256::zero();
+        }
+        let issue = contract_pb::AssetIssueContract {
+            owner_address: addr.as_bytes().to_vec(),
+            name: bytes32_to_string(&name),
+            abbr: bytes32_to_string(&abbr),
+            total_supply: total_supply.as_u64() as i64,
+            precision: precision.as_u32() as i32,
+            ..Default::default()
+        };
+        match self.run_builtin_contract_with(issue, |cntr, manager, ctx| validate_asset_issue(cntr, manager, ctx, true))
+        {
+            Ok(_) => self.state().must_get(&keys::DynamicProperty::LatestTokenId).into(),
+            Err(e) => {
+                debug!("TOKENISSUE failed: {}", e);
+                U256::zero()
+            }
+        }
+    }
+
+    // UPDATEASSET: update url and description of the asset issued by the contract.
+    fn update_asset(&self, address: H160, url: &[u8], description: &[u8]) -> bool {
+        let addr = Address::from_tvm_bytes(address.as_bytes());
+        // Bandwidth limits are kept as is.
+        let (new_limit, new_public_limit) = {
+            let state = self.state();
+            let maybe_asset = state
+                .get(&keys::Account(addr))
+                .unwrap()
+                .and_then(|acct| state.get(&keys::Asset(acct.issued_asset_id)).unwrap());
+            match maybe_asset {
+                Some(asset) => (asset.free_asset_bandwidth_limit, asset.public_free_asset_bandwidth_limit),
+                None => return false,
+            }
+        };
+        let update = contract_pb::UpdateAssetContract {
+            owner_address: addr.as_bytes().to_vec(),
+            description: description.to_vec(),
+            url: String::from_utf8_lossy(url).into_owned(),
+            new_limit,
+            new_public_limit,
+        };
+        self.run_builtin_contract(update)
+            .map_err(|e| debug!("UPDATEASSET failed: {}", e))
+            .is_ok()
+    }
+}
+
+/// Convert a bytes32 argument to string, with trailing zeros removed.
+fn bytes32_to_string(raw: &H256) -> String {
+    let raw = raw.as_bytes();
+    let len = raw.iter().rposition(|&b| b != 0).map(|pos| pos + 1).unwrap_or(0);
+    String::from_utf8_lossy(&raw[..len]).into_owned()
 }
 
 impl ApplyBackend for StateBackend<'_, '_, '_> {
@@ -190,6 +597,7 @@ impl ApplyBackend for StateBackend<'_, '_, '_> {
         I: IntoIterator<Item = (H256, H256)>,
         L: IntoIterator<Item = Log>,
     {
+        self.sync_builtins();
         for apply in values {
             match apply {
                 Apply::Modify {
@@ -200,6 +608,7 @@ impl ApplyBackend for StateBackend<'_, '_, '_> {
                     reset_storage,
                 } => {
                     let addr = Address::from_tvm_bytes(address.as_bytes());
+                    let change = self.builtins.borrow().balance_change(&addr);
                     if delete_empty &&
                         basic.balance == U256::zero() &&
                         basic.nonce == U256::zero() &&
@@ -216,17 +625,19 @@ impl ApplyBackend for StateBackend<'_, '_, '_> {
                         .map(|acct| (acct, false))
                         .unwrap_or_else(|| {
                             log::debug!("create new account in TVM: {} {:?}", addr, address);
-                            (Account::new(self.manager.latest_block_timestamp()), true)
+                            (Account::new(self.manager().latest_block_timestamp()), true)
                         });
 
-                    account.balance = basic.balance.as_u64() as i64;
+                    // Tokens not loaded by TVM are left as changed by builtin contracts.
+                    account.balance = basic.balance.as_u64() as i64 + change.balance;
                     for (token_id, token_value) in basic.token_balance {
-                        if token_value == U256::zero() {
-                            account.token_balance.remove(&(token_id.as_u64() as i64));
+                        let token_id = token_id.as_u64() as i64;
+                        let token_value = token_value.as_u64() as i64 +
+                            change.token_balance.get(&token_id).copied().unwrap_or_default();
+                        if token_value == 0 {
+                            account.token_balance.remove(&token_id);
                         } else {
-                            account
-                                .token_balance
-                                .insert(token_id.as_u64() as i64, token_value.as_u64() as i64);
+                            account.token_balance.insert(token_id, token_value);
                         }
                     }
                     // account.nonce = basic.nonce;
@@ -286,13 +697,102 @@ impl ApplyBackend for StateBackend<'_, '_, '_> {
             }
         }
 
-        for Log { address, topics, data } in logs {
-            // let addr = Address::from_tvm_bytes(address.as_bytes());
-            self.ctx.logs.push(TransactionLog {
-                address: Address::from_tvm_bytes(address.as_bytes()).as_bytes().to_vec(),
-                topics: topics.iter().map(|t| t.as_bytes().to_vec()).collect(),
-                data: data,
-            });
-        }
+        self.push_logs(logs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::IndexedBlockHeader;
+
+    const BALANCE: i64 = 2000_000_000;
+    const STAKE_AMOUNT: i64 = 10_000_000;
+
+    fn new_manager_with_account() -> (Manager, Address) {
+        let mut manager = Manager::new_for_test();
+        let addr: Address = "TN21Wx2yoNYiZ7znuQonmZMJnH5Vdfxu78".parse().unwrap();
+        manager.new_layer();
+        let mut acct = Account::new(manager.latest_block_timestamp());
+        acct.balance = BALANCE;
+        manager.state_db.put_key(keys::Account(addr), acct).unwrap();
+        (manager, addr)
+    }
+
+    /// Apply the account as loaded by TVM, returns the number of builtin layers kept.
+    fn apply_basic(backend: &mut StateBackend, address: H160) -> usize {
+        let basic = backend.basic(address);
+        let applies = vec![Apply::Modify {
+            address,
+            basic,
+            code: None,
+            storage: Vec::<(H256, H256)>::new(),
+            reset_storage: false,
+        }];
+        assert!(backend.check_builtin_balances(&applies).is_ok());
+        backend.apply(applies, vec![], false);
+        backend.builtin_layers()
+    }
+
+    fn stake_in_frame(succeed: bool) -> Account {
+        let (mut manager, addr) = new_manager_with_account();
+        let wit_addr: Address = manager.genesis_config.witnesses[0].address.parse().unwrap();
+
+        let block_header = IndexedBlockHeader::dummy(1, manager.latest_block_timestamp() + 3_000);
+        let mut ctx = TransactionContext::dummy(&block_header);
+        let mut backend = StateBackend::new(addr, &mut manager, &mut ctx);
+        let address = H160::from_slice(addr.as_tvm_bytes());
+        let journal = backend.builtin_journal();
+
+        journal.borrow_mut().enter();
+        assert!(backend.stake(address, H160::from_slice(wit_addr.as_tvm_bytes()), STAKE_AMOUNT.into()));
+        // TVM does not see balance changes of builtin contracts
+        assert_eq!(backend.basic(address).balance, BALANCE.into());
+        journal.borrow_mut().exit(succeed);
+
+        assert_eq!(apply_basic(&mut backend, address), if succeed { 2 } else { 0 });
+        drop(backend);
+
+        manager.state_db.must_get(&keys::Account(addr))
+    }
+
+    #[test]
+    fn test_stake_then_revert() {
+        let acct = stake_in_frame(false);
+        assert_eq!(acct.balance, BALANCE);
+        assert_eq!(acct.frozen_amount_for_bandwidth, 0);
+    }
+
+    #[test]
+    fn test_stake_then_apply() {
+        let acct = stake_in_frame(true);
+        assert_eq!(acct.balance, BALANCE - STAKE_AMOUNT);
+        assert_eq!(acct.frozen_amount_for_bandwidth, STAKE_AMOUNT);
+        assert_eq!(acct.balance + acct.frozen_amount_for_bandwidth, BALANCE);
+    }
+
+    #[test]
+    fn test_issue_token_pays_fee() {
+        let (mut manager, addr) = new_manager_with_account();
+        let fee = manager.state_db.must_get(&keys::ChainParameter::AssetIssueFee);
+        let blackhole_balance = manager.state_db.must_get(&keys::Account(manager.blackhole)).balance;
+
+        let block_header = IndexedBlockHeader::dummy(1, manager.latest_block_timestamp() + 3_000);
+        let mut ctx = TransactionContext::dummy(&block_header);
+        let mut backend = StateBackend::new(addr, &mut manager, &mut ctx);
+        let address = H160::from_slice(addr.as_tvm_bytes());
+
+        let name = H256::from_slice(&[&b"TOKEN"[..], &[0u8; 27][..]].concat());
+        let token_id = backend.issue_token(address, name, name, 1_000_000.into(), 0.into());
+        assert!(!token_id.is_zero());
+        assert_eq!(backend.basic(address).balance, BALANCE.into());
+        assert_eq!(apply_basic(&mut backend, address), 1);
+        drop(backend);
+
+        let acct = manager.state_db.must_get(&keys::Account(addr));
+        assert_eq!(acct.balance, BALANCE - fee);
+        assert_eq!(acct.token_balance.get(&(token_id.as_u64() as i64)), Some(&1_000_000));
+        let blackhole_acct = manager.state_db.must_get(&keys::Account(manager.blackhole));
+        assert_eq!(blackhole_acct.balance, blackhole_balance + fee);
     }
 }
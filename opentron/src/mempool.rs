@@ -0,0 +1,246 @@
+//! Pending transaction pool.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::sync::RwLock;
+
+use chain::IndexedTransaction;
+use log::debug;
+use primitive_types::H256;
+use prost::Message;
+use state::keys;
+
+use crate::manager::Manager;
+
+/// Max number of pending transactions. Lowest priority transactions are evicted first.
+pub const MAX_NUM_OF_PENDING_TRANSACTIONS: usize = 20_000;
+
+struct PendingTransaction {
+    txn: IndexedTransaction,
+    expiration: i64,
+    priority: i64,
+    seq: u64,
+}
+
+impl PendingTransaction {
+    fn queue_key(&self) -> (Reverse<i64>, u64, H256) {
+        (Reverse(self.priority), self.seq, self.txn.hash)
+    }
+}
+
+/// Transactions validated against current state, waiting to be packed into a block.
+#[derive(Default)]
+pub struct TransactionPool {
+    txns: HashMap<H256, PendingTransaction>,
+    // Ordered by priority, then arrival order.
+    queue: BTreeSet<(Reverse<i64>, u64, H256)>,
+    next_seq: u64,
+}
+
+impl TransactionPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.txns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txns.is_empty()
+    }
+
+    pub fn contains(&self, hash: &H256) -> bool {
+        self.txns.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &H256) -> Option<&IndexedTransaction> {
+        self.txns.get(hash).map(|pending| &pending.txn)
+    }
+
+    /// Pending transactions, highest priority first.
+    pub fn pending(&self) -> impl Iterator<Item = &IndexedTransaction> {
+        self.queue.iter().map(move |(_, _, hash)| &self.txns[hash].txn)
+    }
+
+    /// Validate the transaction against current state and add it to the pool.
+    ///
+    /// Returns false if the transaction is already pending.
+    pub fn add(&mut self, manager: &mut Manager, txn: IndexedTransaction) -> Result<bool, Box<dyn Error>> {
+        if self.contains(&txn.hash) {
+            return Ok(false);
+        }
+        if manager.state().get(&keys::TransactionReceipt(txn.hash))?.is_some() {
+            return Err("transaction is already in block".into());
+        }
+
+        let receipt = manager.validate_pending_transaction(&txn)?;
+        debug!("pending transaction {:?}, fee={}", txn.hash, receipt.fee);
+        self.insert(txn, receipt.fee)
+    }
+
+    fn insert(&mut self, txn: IndexedTransaction, fee: i64) -> Result<bool, Box<dyn Error>> {
+        if self.contains(&txn.hash) {
+            return Ok(false);
+        }
+        // Fee per KB of transaction size, i.e. bandwidth.
+        let priority = fee * 1_000 / txn.raw.encoded_len().max(1) as i64;
+
+        if self.txns.len() >= MAX_NUM_OF_PENDING_TRANSACTIONS {
+            let lowest = *self.queue.iter().next_back().expect("pool is not empty");
+            if (lowest.0).0 >= priority {
+                return Err("transaction pool is full".into());
+            }
+            self.remove(&lowest.2);
+        }
+
+        let pending = PendingTransaction {
+            expiration: txn.expiration(),
+            txn,
+            priority,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        self.queue.insert(pending.queue_key());
+        self.txns.insert(pending.txn.hash, pending);
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, hash: &H256) -> Option<IndexedTransaction> {
+        self.txns.remove(hash).map(|pending| {
+            self.queue.remove(&pending.queue_key());
+            pending.txn
+        })
+    }
+
+    /// Evict transactions that are expired, already included in blocks, or no longer valid against the new state.
+    ///
+    /// Remaining transactions are validated again on `snapshot`, each with its own dry run. The pool is only locked to
+    /// collect and to evict transactions, so neither block import nor incoming transactions wait for the dry runs.
+    pub fn prune(pool: &RwLock<TransactionPool>, mut snapshot: Manager) {
+        let latest_block_ts = snapshot.latest_block_timestamp();
+        let pending: Vec<(IndexedTransaction, i64)> = pool
+            .read()
+            .unwrap()
+            .txns
+            .values()
+            .map(|pending| (pending.txn.clone(), pending.expiration))
+            .collect();
+
+        let stale: Vec<H256> = pending
+            .into_iter()
+            .filter(|(txn, expiration)| {
+                if *expiration <= latest_block_ts {
+                    return true;
+                }
+                if let Err(e) = snapshot.validate_pending_transaction(txn) {
+                    debug!("pending transaction {:?} is no longer valid: {}", txn.hash, e);
+                    return true;
+                }
+                false
+            })
+            .map(|(txn, _)| txn.hash)
+            .collect();
+        if stale.is_empty() {
+            return;
+        }
+
+        let mut pool = pool.write().unwrap();
+        for hash in &stale {
+            pool.remove(hash);
+        }
+        debug!("evicted {} transactions, pending={}", stale.len(), pool.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto2::chain::transaction::{Contract, Raw as TransactionRaw};
+    use proto2::chain::{ContractType, Transaction};
+
+    fn txn_of(expiration: i64) -> IndexedTransaction {
+        IndexedTransaction::from_raw(Transaction {
+            raw_data: Some(TransactionRaw {
+                expiration,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pool_priority_and_eviction() {
+        let mut pool = TransactionPool::new();
+
+        let free = txn_of(1);
+        let cheap = txn_of(2);
+        let expensive = txn_of(3);
+        assert!(pool.insert(free.clone(), 0).unwrap());
+        assert!(pool.insert(cheap.clone(), 100_000).unwrap());
+        assert!(pool.insert(expensive.clone(), 10_000_000).unwrap());
+        assert!(!pool.insert(cheap.clone(), 100_000).unwrap());
+
+        let ordered: Vec<_> = pool.pending().map(|txn| txn.hash).collect();
+        assert_eq!(ordered, vec![expensive.hash, cheap.hash, free.hash]);
+
+        // fill up the pool with free transactions
+        for i in 3..MAX_NUM_OF_PENDING_TRANSACTIONS as i64 {
+            pool.insert(txn_of(100 + i), 0).unwrap();
+        }
+        assert_eq!(pool.len(), MAX_NUM_OF_PENDING_TRANSACTIONS);
+        assert!(pool.insert(txn_of(-1), 0).is_err());
+
+        // evicts the latest free transaction
+        let newest_free = txn_of(100 + MAX_NUM_OF_PENDING_TRANSACTIONS as i64 - 1);
+        assert!(pool.insert(txn_of(-2), 1_000).unwrap());
+        assert_eq!(pool.len(), MAX_NUM_OF_PENDING_TRANSACTIONS);
+        assert!(!pool.contains(&newest_free.hash));
+        assert!(pool.contains(&free.hash));
+
+        assert_eq!(pool.remove(&cheap.hash), Some(cheap));
+        assert_eq!(pool.len(), MAX_NUM_OF_PENDING_TRANSACTIONS - 1);
+    }
+
+    #[test]
+    fn test_reject_malformed_transactions() {
+        let mut manager = Manager::new_for_test();
+        let mut pool = TransactionPool::new();
+
+        let txn_with = |contract: Option<Contract>, ref_block_bytes: Vec<u8>| {
+            IndexedTransaction::from_raw(Transaction {
+                raw_data: Some(TransactionRaw {
+                    contract,
+                    ref_block_bytes,
+                    ref_block_hash: vec![0; 8],
+                    expiration: manager.latest_block_timestamp() + 60_000,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let transfer = Contract {
+            r#type: ContractType::TransferContract as i32,
+            parameter: Some(Default::default()),
+            ..Default::default()
+        };
+        let unknown = Contract {
+            r#type: 9999,
+            ..transfer.clone()
+        };
+
+        let malformed = vec![
+            txn_with(None, vec![0; 2]),
+            txn_with(Some(unknown), vec![0; 2]),
+            txn_with(Some(transfer.clone()), vec![]),
+            txn_with(Some(transfer), vec![0; 3]),
+        ];
+        for txn in malformed {
+            assert!(pool.add(&mut manager, txn).is_err());
+        }
+        assert!(pool.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Block producing service of witness nodes.
+
+use std::error::Error;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use chain::IndexedBlock;
+use chrono::Utc;
+use futures::future::FutureExt;
+use futures::select;
+use keys::{KeyPair, Private};
+use log::{debug, info, warn};
+use proto2::channel::Inventory;
+use tokio::sync::broadcast;
+use tokio::time::{delay_for, Duration};
+
+use crate::channel::protocol::ChannelMessage;
+use crate::context::AppContext;
+use crate::manager::producer::BlockProducer;
+use crate::mempool::TransactionPool;
+
+/// Interval of checking whether it's our turn to produce.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub async fn block_producer_server(
+    ctx: Arc<AppContext>,
+    mut signal: broadcast::Receiver<()>,
+) -> Result<(), Box<dyn Error>> {
+    let config = &ctx.config.witness;
+    if config.private_key.is_empty() {
+        info!("block producing disabled, no witness private key");
+        return Ok(());
+    }
+
+    let keypair = KeyPair::from_private(config.private_key.parse::<Private>()?)?;
+    info!("block producing enabled, witness={}", keypair.address());
+
+    loop {
+        select! {
+            _ = signal.recv().fuse() => {
+                warn!("block producing service closed");
+                break;
+            }
+            _ = delay_for(CHECK_INTERVAL).fuse() => {}
+        }
+
+        if !ctx.running.load(Ordering::Relaxed) {
+            break;
+        }
+        if ctx.importing.load(Ordering::Relaxed) {
+            debug!("node is importing blocks, skip block producing");
+            continue;
+        }
+
+        let now = Utc::now().timestamp_millis();
+        match tokio::task::block_in_place(|| try_produce_block(&ctx, &keypair, now)) {
+            Ok(Some(block)) => {
+                ctx.recent_blk_ids.write().unwrap().insert(*block.hash());
+                ctx.chain_db.insert_block(&block)?;
+                ctx.chain_db.update_block_height(block.number());
+
+                // Peers fetch the block from chain-db.
+                let inv = Inventory {
+                    r#type: 1, // BLOCK
+                    ids: vec![block.hash().as_bytes().to_vec()],
+                };
+                let _ = ctx.gossip.send(ChannelMessage::BlockInventory(inv));
+            }
+            Ok(None) => {}
+            Err(e) => warn!("produce block failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn try_produce_block(ctx: &AppContext, keypair: &KeyPair, now: i64) -> Result<Option<IndexedBlock>, Box<dyn Error>> {
+    let mut manager = ctx.manager.write().unwrap();
+
+    let (slot_timestamp, scheduled) = match manager.scheduled_witness_at(now) {
+        Some(scheduled) => scheduled,
+        None => return Ok(None),
+    };
+    if scheduled != keypair.address() || slot_timestamp <= manager.latest_block_timestamp() {
+        return Ok(None);
+    }
+    if now - slot_timestamp > constants::BLOCK_PRODUCING_INTERVAL / 2 {
+        warn!("missed slot at {}, lagged {}ms", slot_timestamp, now - slot_timestamp);
+        return Ok(None);
+    }
+
+    let txns: Vec<_> = ctx.mempool.read().unwrap().pending().cloned().collect();
+    let (block, dropped) = BlockProducer::new(&mut manager).produce_block(keypair, slot_timestamp, txns)?;
+
+    let snapshot = manager.snapshot();
+    drop(manager);
+
+    {
+        let mut mempool = ctx.mempool.write().unwrap();
+        for txn_hash in &dropped {
+            mempool.remove(txn_hash);
+        }
+    }
+    TransactionPool::prune(&ctx.mempool, snapshot);
+
+    Ok(Some(block))
+}
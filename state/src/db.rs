@@ -1,102 +1,65 @@
 //! The state-db implementation.
 
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::io;
 use std::iter;
 use std::path::Path;
+use std::sync::Arc;
 
 use ::keys::Address;
 use config::genesis::GenesisConfig;
 use config::ChainConfig;
+use kvdb::{KeyValue, KeyValueDB, MemoryDB, RocksDB, WriteBatch};
 use log::info;
 use proto2::common::AccountType;
 use proto2::state as state_pb;
-use rocks::prelude::*;
+use rocks::prelude::{
+    ColumnFamilyDescriptor, ColumnFamilyOptions, CompressionType, DBOptions, DEFAULT_COLUMN_FAMILY_NAME,
+};
 
 use super::keys;
+use super::migration::{Migration, MigrationStats, Rewrite, CURRENT_DB_VERSION, MIGRATIONS};
 use super::parameter::default_parameters_from_config;
+use super::snapshot::{SnapshotHeader, SnapshotReader, SnapshotWriter, SNAPSHOT_VERSION};
 use super::DynamicProperty;
 
 pub type BoxError = Box<dyn ::std::error::Error>;
 
-pub trait KeyValueDB {
-    type Column;
-
-    /// Get a value by key.
-    fn get(&self, col: &Self::Column, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
-
-    /// Get the first value matching the given prefix.
-    fn get_by_prefix(&self, col: &Self::Column, prefix: &[u8]) -> Option<Box<[u8]>>;
-
-    /// Write a batch of changes to the backing store.
-    fn write(&self, batch: &WriteBatch) -> io::Result<()>;
-
-    /// Iterate over the data for a given column.
-    fn iter<'a>(&'a self, col: &Self::Column) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
-
-    /// Iterate over the data for a given column, returning all key/value pairs
-    /// where the key starts with the given prefix.
-    fn iter_with_prefix<'a>(
-        &'a self,
-        col: &Self::Column,
-        prefix: &'a [u8],
-    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
-}
-
+#[derive(Clone)]
 pub struct OverlayWriteBatch {
-    wb: WriteBatch,
     // CF => (Key => Value)
     // TODO: replace with VecMap
-    cache: HashMap<u32, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
-}
-
-impl std::ops::Deref for OverlayWriteBatch {
-    type Target = WriteBatch;
-    fn deref(&self) -> &Self::Target {
-        &self.wb
-    }
+    cache: HashMap<usize, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
 }
 
 impl OverlayWriteBatch {
     pub fn new() -> Self {
-        OverlayWriteBatch {
-            wb: WriteBatch::new(),
-            cache: HashMap::new(),
-        }
-    }
-
-    pub fn with_capacity(cap: usize) -> Self {
-        OverlayWriteBatch {
-            wb: WriteBatch::with_reserved_bytes(cap),
-            cache: HashMap::new(),
-        }
+        OverlayWriteBatch { cache: HashMap::new() }
     }
 
-    pub fn put(&mut self, col: &ColumnFamilyHandle, key: &[u8], value: &[u8]) {
-        self.wb.put_cf(col, key, value);
+    pub fn put(&mut self, col: usize, key: &[u8], value: &[u8]) {
         self.cache
-            .entry(col.id())
+            .entry(col)
             .or_default()
             .insert(key.to_owned(), Some(value.to_owned()));
     }
 
-    pub fn delete(&mut self, col: &ColumnFamilyHandle, key: &[u8]) {
-        self.wb.delete_cf(col, key);
-        self.cache.entry(col.id()).or_default().insert(key.to_owned(), None);
+    pub fn delete(&mut self, col: usize, key: &[u8]) {
+        self.cache.entry(col).or_default().insert(key.to_owned(), None);
     }
 
     // Ok(None) => deleted
     // Err(_)   => non-exist
-    pub fn get(&self, col: &ColumnFamilyHandle, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    pub fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
         self.cache
-            .get(&col.id())
+            .get(&col)
             .and_then(|cf| cf.get(key).cloned())
             .ok_or(io::Error::new(io::ErrorKind::NotFound, ""))
     }
 
     // None => deleted or not-found
-    pub fn get_by_prefix(&self, col: &ColumnFamilyHandle, prefix: &[u8]) -> Option<Box<[u8]>> {
-        self.cache.get(&col.id()).and_then(|cf| {
+    pub fn get_by_prefix(&self, col: usize, prefix: &[u8]) -> Option<Box<[u8]>> {
+        self.cache.get(&col).and_then(|cf| {
             cf.iter()
                 .filter(|(key, value)| key.starts_with(prefix) && value.is_some())
                 .map(|(_, value)| value.clone().unwrap().into_boxed_slice())
@@ -104,29 +67,24 @@ impl OverlayWriteBatch {
         })
     }
 
-    pub fn iter<'a>(&'a self, col: &ColumnFamilyHandle) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+    pub fn iter<'a>(&'a self, col: usize) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
         self.cache
-            .get(&col.id())
+            .get(&col)
             .map(|cf| {
-                Box::new(cf.iter().filter(|(_, value)| value.is_some()).map(|(key, value)| {
-                    (
-                        key.to_vec().into_boxed_slice(),
-                        value.clone().unwrap().into_boxed_slice(),
-                    )
-                })) as Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)>>
+                Box::new(
+                    cf.iter().filter(|(_, value)| value.is_some()).map(|(key, value)| {
+                        (key.to_vec().into_boxed_slice(), value.clone().unwrap().into_boxed_slice())
+                    }),
+                ) as Box<dyn Iterator<Item = KeyValue>>
             })
-            .unwrap_or_else(|| Box::new(iter::empty()) as Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)>>)
+            .unwrap_or_else(|| Box::new(iter::empty()) as Box<dyn Iterator<Item = KeyValue>>)
     }
 
     /// Iterate over the data for a given column, returning all key/value pairs
     /// where the key starts with the given prefix.
-    pub fn iter_with_prefix<'a>(
-        &'a self,
-        col: &ColumnFamilyHandle,
-        prefix: &'a [u8],
-    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+    pub fn iter_with_prefix<'a>(&'a self, col: usize, prefix: &'a [u8]) -> Box<dyn Iterator<Item = KeyValue> + 'a> {
         self.cache
-            .get(&col.id())
+            .get(&col)
             .map(|cf| {
                 Box::new(
                     cf.iter()
@@ -137,21 +95,41 @@ impl OverlayWriteBatch {
                                 value.clone().unwrap().into_boxed_slice(),
                             )
                         }),
-                ) as Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)>>
+                ) as Box<dyn Iterator<Item = KeyValue>>
             })
-            .unwrap_or_else(|| Box::new(iter::empty()) as Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)>>)
+            .unwrap_or_else(|| Box::new(iter::empty()) as Box<dyn Iterator<Item = KeyValue>>)
+    }
+
+    /// All changes of the layer, as a batch to the backing store.
+    pub fn to_write_batch(&self) -> WriteBatch {
+        let mut wb = WriteBatch::new();
+        self.write_to(&mut wb);
+        wb
+    }
+
+    /// Append all changes to a `WriteBatch`.
+    pub fn write_to(&self, wb: &mut WriteBatch) {
+        for (&col, cache) in &self.cache {
+            for (key, value) in cache {
+                match value {
+                    Some(val) => wb.put(col, key, val),
+                    None => wb.delete(col, key),
+                }
+            }
+        }
     }
 }
 
 pub struct OverlayDB {
-    inner: DB,
+    inner: Box<dyn KeyValueDB>,
     // Use push_back to add to the queue, and pop_front to remove from the queue.
     // push_back to add a new layer, pop_front to sync a layer to db, clear to discard all layers.
-    layers: VecDeque<OverlayWriteBatch>,
+    // Layers are shared with snapshots, and copied on write.
+    layers: VecDeque<Arc<OverlayWriteBatch>>,
 }
 
 impl OverlayDB {
-    pub fn new(inner: DB) -> Self {
+    pub fn new(inner: Box<dyn KeyValueDB>) -> Self {
         OverlayDB {
             inner,
             // ceiling(27 - 27 * 70%) = 9
@@ -161,17 +139,17 @@ impl OverlayDB {
 
     /// Fake `write` an OverlayWriteBath.
     pub fn write(&mut self, wb: OverlayWriteBatch) -> io::Result<()> {
-        self.layers.push_back(wb);
+        self.layers.push_back(Arc::new(wb));
         Ok(())
     }
 
     pub fn push_layer(&mut self, wb: OverlayWriteBatch) {
-        self.layers.push_back(wb);
+        self.layers.push_back(Arc::new(wb));
     }
 
     pub fn solidify_layers(&mut self) -> Result<(), BoxError> {
         for layer in self.layers.drain(..) {
-            self.inner.write(WriteOptions::default_instance(), &layer.wb)?;
+            self.inner.write(&layer.to_write_batch())?;
         }
         Ok(())
     }
@@ -182,41 +160,31 @@ impl OverlayDB {
     }
 
     /// Get a value by key.
-    pub fn get(&self, col: &ColumnFamilyHandle, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    pub fn get(&self, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
         for layer in self.layers.iter().rev() {
             if let Ok(val) = layer.get(col, key) {
                 return Ok(val);
             }
         }
-        match self.inner.get_cf(ReadOptions::default_instance(), col, key) {
-            Ok(val) => Ok(Some(val.to_vec())),
-            Err(e) if e.is_not_found() => Ok(None),
-            // Err(e) if e.is_not_found() => Err(io::Error::new(io::ErrorKind::NotFound, "")),
-            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
-        }
+        self.inner.get(col, key)
     }
 
     /// Get a value by key, skip top n layers.
-    pub fn get_skipped(&self, n: usize, col: &ColumnFamilyHandle, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    pub fn get_skipped(&self, n: usize, col: usize, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
         for layer in self.layers.iter().rev().skip(n) {
             if let Ok(val) = layer.get(col, key) {
                 return Ok(val);
             }
         }
-        match self.inner.get_cf(ReadOptions::default_instance(), col, key) {
-            Ok(val) => Ok(Some(val.to_vec())),
-            Err(e) if e.is_not_found() => Ok(None),
-            // Err(e) if e.is_not_found() => Err(io::Error::new(io::ErrorKind::NotFound, "")),
-            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
-        }
+        self.inner.get(col, key)
     }
 
     /// Get the first value matching the given prefix.
-    pub fn get_by_prefix(&self, col: &ColumnFamilyHandle, prefix: &[u8]) -> Option<Box<[u8]>> {
+    pub fn get_by_prefix(&self, col: usize, prefix: &[u8]) -> Option<Box<[u8]>> {
         let mut deleted = HashSet::<&[u8]>::new();
 
         for layer in self.layers.iter().rev() {
-            if let Some(cache) = layer.cache.get(&col.id()) {
+            if let Some(cache) = layer.cache.get(&col) {
                 for (key, value) in cache.iter().filter(|(key, _)| key.starts_with(prefix)) {
                     if deleted.contains(&**key) {
                         continue;
@@ -233,29 +201,23 @@ impl OverlayDB {
             }
         }
 
-        for (key, value) in self
-            .inner
-            .new_iterator_cf(&ReadOptions::default().iterate_lower_bound(prefix), col)
-        {
-            if !key.starts_with(prefix) {
-                return None;
-            }
-            if deleted.contains(key) {
+        for (key, value) in self.inner.iter_with_prefix(col, prefix) {
+            if deleted.contains(&*key) {
                 continue;
             }
-            return Some(value.to_vec().into_boxed_slice());
+            return Some(value);
         }
         None
     }
 
-    pub fn for_each<F>(&self, col: &ColumnFamilyHandle, mut func: F)
+    pub fn for_each<F>(&self, col: usize, mut func: F)
     where
         F: FnMut(&[u8], &[u8]) -> (),
     {
         let mut visited: HashSet<&[u8]> = HashSet::new();
 
         for layer in self.layers.iter().rev() {
-            if let Some(cache) = layer.cache.get(&col.id()) {
+            if let Some(cache) = layer.cache.get(&col) {
                 for (key, value) in cache.iter() {
                     if visited.contains(&**key) {
                         continue;
@@ -271,24 +233,24 @@ impl OverlayDB {
             }
         }
 
-        for (key, value) in self.inner.new_iterator_cf(&ReadOptions::default(), col) {
-            if visited.contains(key) {
+        for (key, value) in self.inner.iter(col) {
+            if visited.contains(&*key) {
                 continue;
             }
-            func(key, value);
+            func(&key, &value);
         }
     }
 
     /// Iterate over the data for a given column, returning all key/value pairs
     /// where the key starts with the given prefix.
-    pub fn for_each_by_prefix<F>(&self, col: &ColumnFamilyHandle, prefix: &[u8], mut func: F)
+    pub fn for_each_by_prefix<F>(&self, col: usize, prefix: &[u8], mut func: F)
     where
         F: FnMut(&[u8], &[u8]) -> (),
     {
         let mut visited = HashSet::<&[u8]>::new();
 
         for layer in self.layers.iter().rev() {
-            if let Some(cache) = layer.cache.get(&col.id()) {
+            if let Some(cache) = layer.cache.get(&col) {
                 for (key, value) in cache.iter().filter(|(key, _)| key.starts_with(prefix)) {
                     if !key.starts_with(prefix) {
                         continue;
@@ -307,35 +269,40 @@ impl OverlayDB {
             }
         }
 
-        for (key, value) in self
-            .inner
-            .new_iterator_cf(&ReadOptions::default().iterate_lower_bound(prefix), col)
-        {
-            if !key.starts_with(prefix) {
-                return;
-            }
-            if visited.contains(key) {
+        for (key, value) in self.inner.iter_with_prefix(col, prefix) {
+            if visited.contains(&*key) {
                 continue;
             }
-            func(key, value);
+            func(&key, &value);
         }
     }
 
-    pub fn delete(&mut self, col: &ColumnFamilyHandle, key: &[u8]) -> io::Result<()> {
+    /// Keys changed in the top n layers, including deleted ones.
+    pub fn changed_keys(&self, n: usize, col: usize) -> BTreeSet<Vec<u8>> {
+        self.layers
+            .iter()
+            .rev()
+            .take(n)
+            .filter_map(|layer| layer.cache.get(&col))
+            .flat_map(|cache| cache.keys().cloned())
+            .collect()
+    }
+
+    pub fn delete(&mut self, col: usize, key: &[u8]) -> io::Result<()> {
         let wb = self
             .layers
             .back_mut()
             .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no db layers found"))?;
-        wb.delete(col, key);
+        Arc::make_mut(wb).delete(col, key);
         Ok(())
     }
 
-    pub fn delete_by_prefix(&mut self, col: &ColumnFamilyHandle, prefix: &[u8]) -> io::Result<()> {
+    pub fn delete_by_prefix(&mut self, col: usize, prefix: &[u8]) -> io::Result<()> {
         let mut visited = HashSet::<&[u8]>::new();
         let mut deleted = HashSet::<Vec<u8>>::new();
 
         for layer in self.layers.iter().rev() {
-            if let Some(cache) = layer.cache.get(&col.id()) {
+            if let Some(cache) = layer.cache.get(&col) {
                 for (key, value) in cache.iter().filter(|(key, _)| key.starts_with(prefix)) {
                     if !key.starts_with(prefix) {
                         continue;
@@ -354,18 +321,11 @@ impl OverlayDB {
             }
         }
 
-        for key in self
-            .inner
-            .new_iterator_cf(&ReadOptions::default().iterate_lower_bound(prefix), col)
-            .keys()
-        {
-            if !key.starts_with(prefix) {
-                return Ok(());
-            }
-            if visited.contains(key) {
+        for (key, _) in self.inner.iter_with_prefix(col, prefix) {
+            if visited.contains(&*key) {
                 continue;
             }
-            deleted.insert(key.to_vec());
+            deleted.insert(key.into_vec());
         }
         for key in &deleted {
             self.delete(col, key)?;
@@ -393,15 +353,47 @@ pub const COL_TRANSACTION_LOG: usize = 13;
 pub const COL_ACCOUNT_INDEX: usize = 14;
 pub const COL_VOTER_REWARD: usize = 15;
 pub const COL_EXCHANGE: usize = 16;
+/// Hashes of transactions in recent blocks, for duplication check.
+pub const COL_RECENT_TRANSACTION: usize = 17;
+/// Change sets of solidified blocks, in archive mode.
+pub const COL_ARCHIVE: usize = 18;
+/// Orders of the market(on-chain order book).
+pub const COL_MARKET_ORDER: usize = 19;
+/// Order lists of market price levels.
+pub const COL_MARKET_PRICE_LEVEL: usize = 20;
+/// Active market orders of accounts.
+pub const COL_MARKET_ACCOUNT_ORDERS: usize = 21;
+/// Number of columns, the storage engine must provide all of them.
+pub const NUM_OF_COLUMNS: usize = 22;
 
 /// The State DB derived from Chain DB.
 pub struct StateDB {
     db: OverlayDB,
-    cols: Vec<ColumnFamily>,
+    // save change sets of solidified blocks
+    archive: bool,
+    // read state as of a historical block
+    view: Option<StateView>,
+    // a read-only copy, see `StateDB::snapshot`
+    snapshot: bool,
+}
+
+/// A read view of state as of a historical block.
+#[derive(Debug, Clone)]
+pub struct StateView {
+    pub block_number: i64,
+    // layers of blocks after `block_number`, counted from the top when the view is created
+    skipped_layers: usize,
+    // number of layers when the view is created, newer layers are visible
+    base_layers: usize,
+    // solidified state is newer than `block_number`, read from change sets
+    from_archive: bool,
 }
 
 impl Drop for StateDB {
     fn drop(&mut self) {
+        if self.snapshot {
+            return;
+        }
         info!("state-db closed successfully, all cached layers will be droped");
     }
 }
@@ -508,6 +500,25 @@ fn col_descs_for_state_db() -> Vec<ColumnFamilyDescriptor> {
                 .optimize_for_small_db()
                 .optimize_for_point_lookup(16),
         ),
+        ColumnFamilyDescriptor::new(
+            "recent-transaction",
+            ColumnFamilyOptions::default()
+                .optimize_for_point_lookup(32)
+                .compression(CompressionType::NoCompression),
+        ),
+        // <<col: u8, key_len: u32, key, block_number: i64>> => <<exists: u8, previous_value>>
+        ColumnFamilyDescriptor::new("archive", ColumnFamilyOptions::default()),
+        // order_id: H256 => MarketOrder
+        ColumnFamilyDescriptor::new("market-order", ColumnFamilyOptions::default().optimize_for_point_lookup(32)),
+        // <<sell_token_id: i64, buy_token_id: i64, sell_token_quantity: i64, buy_token_quantity: i64>> => MarketOrderIdList
+        ColumnFamilyDescriptor::new("market-price-level", ColumnFamilyOptions::default().prefix_extractor_fixed(16)),
+        // owner_address: Address => MarketAccountOrders
+        ColumnFamilyDescriptor::new(
+            "market-account-orders",
+            ColumnFamilyOptions::default()
+                .optimize_for_small_db()
+                .optimize_for_point_lookup(16),
+        ),
     ]
 }
 
@@ -520,28 +531,186 @@ impl StateDB {
             .allow_mmap_reads(true) // for Cuckoo table
             .max_open_files(1024);
 
-        let column_families = col_descs_for_state_db();
+        let db = RocksDB::open(&db_options, db_path, col_descs_for_state_db()).unwrap();
 
-        let (db, cols) = DB::open_with_column_families(&db_options, db_path, column_families).unwrap();
+        StateDB::from_kvdb(Box::new(db))
+    }
+
+    /// State-db kept in memory, for tests and throwaway dev chains.
+    pub fn new_in_memory() -> StateDB {
+        StateDB::from_kvdb(Box::new(MemoryDB::new(NUM_OF_COLUMNS)))
+    }
 
+    /// Open state-db with the storage engine, `rocksdb` or `memory`.
+    ///
+    /// Fails if a snapshot import was interrupted, since the state-db is then partly wiped.
+    pub fn open<P: AsRef<Path>>(engine: &str, db_path: P) -> Result<StateDB, BoxError> {
+        let db = match engine {
+            "rocksdb" => StateDB::new(db_path),
+            "memory" => StateDB::new_in_memory(),
+            _ => return Err(format!("unknown storage engine: {:?}", engine).into()),
+        };
+        if let Some(block_number) = db.get(&keys::ImportingSnapshot)? {
+            return Err(format!(
+                "state-db has an interrupted snapshot import of block #{}, remove state-data-dir and import again",
+                block_number
+            )
+            .into());
+        }
+        Ok(db)
+    }
+
+    /// State-db on top of any storage engine with `NUM_OF_COLUMNS` columns.
+    pub fn from_kvdb(inner: Box<dyn KeyValueDB>) -> StateDB {
         StateDB {
-            db: OverlayDB::new(db),
-            cols,
+            db: OverlayDB::new(inner),
+            archive: false,
+            view: None,
+            snapshot: false,
+        }
+    }
+
+    /// A consistent snapshot of the current state, solidified data plus all cached layers.
+    ///
+    /// Writes to the snapshot only go to its own layers, and are discarded when dropped.
+    pub fn snapshot(&self) -> StateDB {
+        StateDB {
+            db: OverlayDB {
+                inner: self.db.inner.snapshot(),
+                layers: self.db.layers.clone(),
+            },
+            archive: self.archive,
+            view: self.view.clone(),
+            snapshot: true,
         }
     }
 }
 
 impl StateDB {
     pub fn new_layer(&mut self) -> &mut OverlayWriteBatch {
-        self.db.push_layer(OverlayWriteBatch::with_capacity(4 * 1024));
-        self.db.layers.back_mut().unwrap()
+        self.db.push_layer(OverlayWriteBatch::new());
+        Arc::make_mut(self.db.layers.back_mut().unwrap())
     }
 
     pub fn solidify_layer(&mut self) {
         self.db
             .layers
             .pop_front()
-            .map(|wb| self.db.inner.write(WriteOptions::default_instance(), &wb));
+            .map(|wb| self.db.inner.write(&wb.to_write_batch()));
+    }
+
+    /// Solidify the oldest n layers, which are all layers of a block. Saves the change set in archive mode.
+    ///
+    /// The block is written in one batch, so secondary instances never see a partly solidified block.
+    pub fn solidify_block_layers(&mut self, block_number: i64, n: usize) -> Result<(), BoxError> {
+        let mut wb = WriteBatch::new();
+        if self.archive {
+            let mut visited = HashSet::new();
+            for layer in self.db.layers.iter().take(n) {
+                for col in 0..NUM_OF_COLUMNS {
+                    if col == COL_ARCHIVE {
+                        continue;
+                    }
+                    for key in layer.cache.get(&col).into_iter().flat_map(|cache| cache.keys()) {
+                        if visited.insert((col, key)) {
+                            let prev_value = self.get_solidified(col, key)?;
+                            wb.put(
+                                COL_ARCHIVE,
+                                &archive_key_of(col, key, block_number),
+                                &archive_value_of(prev_value.as_ref().map(|val| &val[..])),
+                            );
+                        }
+                    }
+                }
+            }
+            if self.get(&keys::ArchiveStartBlock)?.is_none() {
+                wb.put(
+                    COL_DEFAULT,
+                    <keys::ArchiveStartBlock as keys::Key<i64>>::key(&keys::ArchiveStartBlock).as_bytes(),
+                    &block_number.to_be_bytes(),
+                );
+            }
+        }
+        for layer in self.db.layers.iter().take(n) {
+            layer.write_to(&mut wb);
+        }
+        self.db.inner.write(&wb)?;
+        for _ in 0..n {
+            self.db.layers.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Enable archive mode, change sets of blocks are saved when solidified.
+    pub fn set_archive_mode(&mut self, enabled: bool) {
+        self.archive = enabled;
+    }
+
+    /// Create a view of state as of `block_number`. Top `skipped_layers` layers are of newer blocks.
+    ///
+    /// `from_archive` means the solidified state is newer than `block_number`.
+    pub fn view_at(&self, block_number: i64, skipped_layers: usize, from_archive: bool) -> Result<StateView, BoxError> {
+        if from_archive {
+            let start_block = self.get(&keys::ArchiveStartBlock)?;
+            match start_block {
+                Some(start) if start <= block_number + 1 => {}
+                Some(start) => return Err(format!("state before block #{} is not archived", start - 1).into()),
+                None => return Err("archive mode is not enabled".into()),
+            }
+        }
+        Ok(StateView {
+            block_number,
+            skipped_layers,
+            base_layers: self.db.layers.len(),
+            from_archive,
+        })
+    }
+
+    /// Read all state as of the view, until `leave_view`, including iteration. Writes go to layers as usual.
+    pub fn enter_view(&mut self, view: StateView) {
+        self.view = Some(view);
+    }
+
+    pub fn leave_view(&mut self) {
+        self.view = None;
+    }
+
+    /// Get a value as of the view.
+    pub fn get_with_view<T, K: keys::Key<T>>(&self, key: &K, view: &StateView) -> Result<Option<T>, BoxError> {
+        self.get_raw_with_view(K::COL, key.key().as_ref(), view)
+            .map(|maybe_raw| maybe_raw.map(|raw| K::parse_value(&raw)))
+    }
+
+    fn get_raw_with_view(&self, col: usize, key: &[u8], view: &StateView) -> Result<Option<Vec<u8>>, BoxError> {
+        let num_of_new_layers = self.db.layers.len().saturating_sub(view.base_layers);
+        let new_layers = self.db.layers.iter().rev().take(num_of_new_layers);
+        let old_layers = self
+            .db
+            .layers
+            .iter()
+            .rev()
+            .skip(num_of_new_layers + view.skipped_layers);
+        let visible_layers = new_layers.chain(old_layers);
+        for layer in visible_layers {
+            if let Ok(val) = layer.get(col, key) {
+                return Ok(val);
+            }
+        }
+        if view.from_archive {
+            // the first change after the block holds the value as of the block
+            let prefix = archive_key_prefix_of(col, key);
+            let lower_bound = archive_key_of(col, key, view.block_number + 1);
+            if let Some((archive_key, value)) = self.db.inner.iter_from(COL_ARCHIVE, &lower_bound).next() {
+                if archive_key.starts_with(&prefix) {
+                    return Ok(parse_archive_value(&value));
+                }
+            }
+        }
+        self.get_solidified(col, key)
+    }
+
+    fn get_solidified(&self, col: usize, key: &[u8]) -> Result<Option<Vec<u8>>, BoxError> {
+        Ok(self.db.inner.get(col, key)?)
     }
 
     pub fn discard_last_layer(&mut self) -> io::Result<()> {
@@ -558,48 +727,47 @@ impl StateDB {
             .layers
             .back_mut()
             .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no db layers found"))?;
-        wb.put(&self.cols[K::COL], key.key().as_ref(), &*K::value(&value));
+        Arc::make_mut(wb).put(K::COL, key.key().as_ref(), &*K::value(&value));
         Ok(())
     }
 
     pub fn delete_key<T, K: keys::Key<T>>(&mut self, key: &K) -> Result<(), BoxError> {
-        self.db.delete(&self.cols[K::COL], key.key().as_ref())?;
+        self.db.delete(K::COL, key.key().as_ref())?;
         Ok(())
     }
 
-    pub fn delete_by_prefix(&mut self, col: &ColumnFamilyHandle, prefix: &[u8]) -> Result<(), BoxError> {
+    pub fn delete_by_prefix(&mut self, col: usize, prefix: &[u8]) -> Result<(), BoxError> {
         self.db.delete_by_prefix(col, prefix)?;
         Ok(())
     }
 
     pub fn get<T, K: keys::Key<T>>(&self, key: &K) -> Result<Option<T>, BoxError> {
+        if let Some(ref view) = self.view {
+            return self.get_with_view(key, view);
+        }
         self.db
-            .get(&self.cols[K::COL], key.key().as_ref())
+            .get(K::COL, key.key().as_ref())
             .map(|maybe_raw| maybe_raw.map(|raw| K::parse_value(&raw)))
             .map_err(|e| e.into())
     }
 
     pub fn get_skipped<T, K: keys::Key<T>>(&self, n: usize, key: &K) -> Result<Option<T>, BoxError> {
         self.db
-            .get_skipped(n, &self.cols[K::COL], key.key().as_ref())
+            .get_skipped(n, K::COL, key.key().as_ref())
             .map(|maybe_raw| maybe_raw.map(|raw| K::parse_value(&raw)))
             .map_err(|e| e.into())
     }
 
     pub fn must_get_skipped<T, K: keys::Key<T>>(&self, n: usize, key: &K) -> T {
         self.db
-            .get_skipped(n, &self.cols[K::COL], key.key().as_ref())
+            .get_skipped(n, K::COL, key.key().as_ref())
             .map(|maybe_raw| maybe_raw.map(|raw| K::parse_value(&raw)))
             .expect("corrupted db")
             .expect("key must exist")
     }
 
     pub fn must_get<T, K: keys::Key<T>>(&self, key: &K) -> T {
-        self.db
-            .get(&self.cols[K::COL], key.key().as_ref())
-            .map(|maybe_raw| maybe_raw.map(|raw| K::parse_value(&raw)))
-            .expect("corrupted db")
-            .expect("key must exist")
+        self.get(key).expect("corrupted db").expect("key must exist")
     }
 
     /// Increase a i64 key and the return updated value.
@@ -613,7 +781,10 @@ impl StateDB {
     where
         F: FnMut(&K, &T) -> (),
     {
-        self.db.for_each(&self.cols[K::COL], move |key, value| {
+        if let Some(ref view) = self.view {
+            return self.for_each_with_view(&[], view, func);
+        }
+        self.db.for_each(K::COL, move |key, value| {
             if let Some(key) = K::parse_key(key) {
                 func(&key, &K::parse_value(value));
             }
@@ -624,17 +795,68 @@ impl StateDB {
     where
         F: FnMut(&K, &T) -> (),
     {
-        self.db
-            .for_each_by_prefix(&self.cols[K::COL], prefix, move |key, value| {
-                if let Some(key) = K::parse_key(key) {
-                    func(&key, &K::parse_value(value));
+        if let Some(ref view) = self.view {
+            return self.for_each_with_view(prefix, view, func);
+        }
+        self.db.for_each_by_prefix(K::COL, prefix, move |key, value| {
+            if let Some(key) = K::parse_key(key) {
+                func(&key, &K::parse_value(value));
+            }
+        });
+    }
+
+    fn for_each_with_view<T, K: keys::Key<T>, F>(&self, prefix: &[u8], view: &StateView, mut func: F)
+    where
+        F: FnMut(&K, &T) -> (),
+    {
+        // Keys as of the view are a subset of current keys and keys changed after the view.
+        let mut raw_keys = BTreeSet::new();
+        self.db.for_each_by_prefix(K::COL, prefix, |key, _| {
+            raw_keys.insert(key.to_vec());
+        });
+        for layer in &self.db.layers {
+            if let Some(cache) = layer.cache.get(&K::COL) {
+                raw_keys.extend(cache.keys().filter(|key| key.starts_with(prefix)).cloned());
+            }
+        }
+        if view.from_archive {
+            // archive key: <<col: u8, key_len: u32, key, block_number: u64>>
+            for (archive_key, _) in self.db.inner.iter_with_prefix(COL_ARCHIVE, &[K::COL as u8]) {
+                let (raw_key, raw_block_number) = archive_key[5..].split_at(archive_key.len() - 5 - 8);
+                let mut block_number = [0u8; 8];
+                block_number.copy_from_slice(raw_block_number);
+                if u64::from_be_bytes(block_number) as i64 > view.block_number && raw_key.starts_with(prefix) {
+                    raw_keys.insert(raw_key.to_vec());
                 }
-            });
+            }
+        }
+
+        for raw_key in raw_keys {
+            let maybe_raw = self.get_raw_with_view(K::COL, &raw_key, view).expect("corrupted db");
+            if let (Some(key), Some(raw)) = (K::parse_key(&raw_key), maybe_raw) {
+                func(&key, &K::parse_value(&raw));
+            }
+        }
+    }
+
+    /// Keys changed in the top n layers, including deleted ones.
+    pub fn changed_keys<T, K: keys::Key<T>>(&self, n: usize) -> Vec<K> {
+        self.db
+            .changed_keys(n, K::COL)
+            .iter()
+            .filter_map(|key| K::parse_key(key))
+            .collect()
+    }
+
+    /// Raw keys changed in the top n layers of all columns, as `(column, key)`.
+    pub fn touched_keys(&self, n: usize) -> Vec<(usize, Vec<u8>)> {
+        (0..NUM_OF_COLUMNS)
+            .flat_map(|col| self.db.changed_keys(n, col).into_iter().map(move |key| (col, key)))
+            .collect()
     }
 
     pub fn init_genesis(&mut self, genesis: &GenesisConfig, chain: &ChainConfig) -> Result<(), BoxError> {
         if let Some(db_ver) = self.get(&keys::DynamicProperty::DbVersion)? {
-            // TODO: check migration here
             let latest_block_hash = self.must_get(&keys::LatestBlockHash);
             let latest_block_numer = self.must_get(&DynamicProperty::LatestBlockNumber);
             info!(
@@ -723,41 +945,370 @@ impl StateDB {
     }
 }
 
-pub struct ReadOnlySolidStateDB {
-    db: DB,
-    cols: Vec<ColumnFamily>,
+/// Snapshot export and import. Only works on a solidified state-db, i.e. without layers.
+impl StateDB {
+    /// Dump all column families at the current solidified block, returns number of entries written.
+    pub fn export_snapshot<W: io::Write>(&self, writer: W) -> Result<u64, BoxError> {
+        if !self.db.layers.is_empty() {
+            return Err("state-db has unsolidified layers".into());
+        }
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            block_number: self.must_get(&DynamicProperty::LatestBlockNumber),
+            block_hash: self.must_get(&keys::LatestBlockHash),
+            num_of_columns: NUM_OF_COLUMNS as u32,
+        };
+        let mut writer = SnapshotWriter::new(writer, &header)?;
+        for col in 0..NUM_OF_COLUMNS {
+            for (key, value) in self.db.inner.iter(col) {
+                writer.put(col as u32, &key, &value)?;
+            }
+        }
+        Ok(writer.finish()?)
+    }
+
+    /// Replace all data with the snapshot, returns the snapshot header.
+    ///
+    /// The whole snapshot is verified before any data is replaced. An `ImportingSnapshot` marker is written before
+    /// wiping and removed along with writing `DbVersion` last, so a partially imported state-db refuses to open.
+    pub fn import_snapshot<R: io::Read + io::Seek>(&mut self, mut reader: R) -> Result<SnapshotHeader, BoxError> {
+        if !self.db.layers.is_empty() {
+            return Err("state-db has unsolidified layers".into());
+        }
+        let db_version_key = <DynamicProperty as keys::Key<i64>>::key(&DynamicProperty::DbVersion);
+        let marker_key = <keys::ImportingSnapshot as keys::Key<i64>>::key(&keys::ImportingSnapshot);
+
+        // . verify the whole file before touching the state-db
+        let start = reader.seek(io::SeekFrom::Current(0))?;
+        let mut snapshot = SnapshotReader::new(&mut reader)?;
+        let header = snapshot.header().clone();
+        if header.num_of_columns as usize != NUM_OF_COLUMNS {
+            return Err(
+                format!("snapshot has {} columns, state-db has {}", header.num_of_columns, NUM_OF_COLUMNS).into()
+            );
+        }
+        let mut has_db_version = false;
+        while let Some(chunk) = snapshot.next_chunk()? {
+            if chunk.column as usize == COL_DEFAULT {
+                has_db_version |= chunk.entries.iter().any(|(key, _)| key == db_version_key.as_bytes());
+            }
+        }
+        if !has_db_version {
+            return Err("missing db version in snapshot".into());
+        }
+        reader.seek(io::SeekFrom::Start(start))?;
+        let mut reader = SnapshotReader::new(&mut reader)?;
+
+        let mut wb = WriteBatch::new();
+        wb.put(COL_DEFAULT, marker_key.as_bytes(), &header.block_number.to_be_bytes());
+        self.db.inner.write(&wb)?;
+
+        for col in 0..NUM_OF_COLUMNS {
+            let mut wb = WriteBatch::new();
+            for (key, _) in self.db.inner.iter(col) {
+                if col == COL_DEFAULT && &key[..] == marker_key.as_bytes() {
+                    continue;
+                }
+                wb.delete(col, &key);
+            }
+            self.db.inner.write(&wb)?;
+        }
+
+        let mut db_version = None;
+        while let Some(chunk) = reader.next_chunk()? {
+            let col = chunk.column as usize;
+            let mut wb = WriteBatch::with_capacity(chunk.entries.len());
+            for (key, value) in &chunk.entries {
+                if chunk.column as usize == COL_DEFAULT && key.as_slice() == db_version_key.as_bytes() {
+                    db_version = Some(value.clone());
+                    continue;
+                }
+                if chunk.column as usize == COL_DEFAULT && key.as_slice() == marker_key.as_bytes() {
+                    continue;
+                }
+                wb.put(col, key, value);
+            }
+            self.db.inner.write(&wb)?;
+        }
+
+        let db_version = db_version.ok_or("missing db version in snapshot")?;
+        let mut wb = WriteBatch::new();
+        wb.put(COL_DEFAULT, db_version_key.as_bytes(), &db_version);
+        wb.delete(COL_DEFAULT, marker_key.as_bytes());
+        self.db.inner.write(&wb)?;
+
+        Ok(header)
+    }
 }
 
-unsafe impl Send for ReadOnlySolidStateDB {}
-unsafe impl Sync for ReadOnlySolidStateDB {}
+/// Schema migrations, see `state::migration`. Only works on a solidified state-db, i.e. without layers.
+impl StateDB {
+    /// Schema version, `None` for an empty state-db.
+    pub fn db_version(&self) -> Result<Option<i64>, BoxError> {
+        self.get(&DynamicProperty::DbVersion)
+    }
+
+    /// Fails unless the state-db is empty or of the current schema version.
+    pub fn check_db_version(&self) -> Result<(), BoxError> {
+        match self.db_version()? {
+            Some(version) if version != CURRENT_DB_VERSION => Err(format!(
+                "state-db schema version is {}, version {} is required",
+                version, CURRENT_DB_VERSION
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Migrate to the current schema version, returns stats of applied migrations.
+    ///
+    /// With `dry_run`, nothing is written, and each migration is run against the data before any migration.
+    pub fn migrate(&mut self, chain: &ChainConfig, dry_run: bool) -> Result<Vec<MigrationStats>, BoxError> {
+        self.migrate_with(MIGRATIONS, CURRENT_DB_VERSION, chain, dry_run)
+    }
+
+    pub(crate) fn migrate_with(
+        &mut self,
+        migrations: &[Migration],
+        current_version: i64,
+        chain: &ChainConfig,
+        dry_run: bool,
+    ) -> Result<Vec<MigrationStats>, BoxError> {
+        if !self.db.layers.is_empty() {
+            return Err("state-db has unsolidified layers".into());
+        }
+        let version = match self.db_version()? {
+            Some(version) => version,
+            None => return Ok(vec![]),
+        };
+        if version > current_version {
+            return Err(format!(
+                "state-db schema version {} is newer than version {} of this build",
+                version, current_version
+            )
+            .into());
+        }
+
+        let mut all_stats = vec![];
+        for from_version in version..current_version {
+            let migration = migrations
+                .iter()
+                .find(|migration| migration.from_version == from_version)
+                .ok_or_else(|| format!("no migration from state-db schema version {}", from_version))?;
+            info!(
+                "migrating state-db schema version {} => {}, {}{}",
+                from_version,
+                from_version + 1,
+                migration.description,
+                if dry_run { " (dry run)" } else { "" }
+            );
+            let stats = self.apply_migration(migration, chain, dry_run)?;
+            info!(
+                "migrated state-db schema version {} => {}, visited={} rewritten={} deleted={} inserted={}",
+                from_version,
+                from_version + 1,
+                stats.visited,
+                stats.rewritten,
+                stats.deleted,
+                stats.inserted
+            );
+            all_stats.push(stats);
+        }
+        Ok(all_stats)
+    }
+
+    fn apply_migration(
+        &mut self,
+        migration: &Migration,
+        chain: &ChainConfig,
+        dry_run: bool,
+    ) -> Result<MigrationStats, BoxError> {
+        const BATCH_SIZE: usize = 10_000;
+        const PROGRESS_INTERVAL: u64 = 1_000_000;
+
+        let mut stats = MigrationStats {
+            from_version: migration.from_version,
+            ..Default::default()
+        };
+        // Visit a snapshot, so rewritten entries are not visited again.
+        let snapshot = self.db.inner.snapshot();
+        for &col in migration.columns {
+            let mut wb = WriteBatch::new();
+            for (key, value) in snapshot.iter(col) {
+                match (migration.rewrite)(col, &key, &value) {
+                    Rewrite::Keep => {}
+                    Rewrite::Put(new_key, new_value) => {
+                        if new_key[..] != key[..] {
+                            wb.delete(col, &key);
+                        }
+                        wb.put(col, &new_key, &new_value);
+                        stats.rewritten += 1;
+                    }
+                    Rewrite::Delete => {
+                        wb.delete(col, &key);
+                        stats.deleted += 1;
+                    }
+                }
+                stats.visited += 1;
+
+                if wb.ops.len() >= BATCH_SIZE {
+                    if !dry_run {
+                        self.db.inner.write(&wb)?;
+                    }
+                    wb = WriteBatch::new();
+                }
+                if stats.visited % PROGRESS_INTERVAL == 0 {
+                    info!(
+                        "migrating column #{}, visited={} rewritten={} deleted={}",
+                        col, stats.visited, stats.rewritten, stats.deleted
+                    );
+                }
+            }
+            if !dry_run {
+                self.db.inner.write(&wb)?;
+            }
+        }
+
+        let mut wb = WriteBatch::new();
+        for (col, key, value) in (migration.backfill)(chain) {
+            if self.db.inner.get(col, &key)?.is_none() {
+                wb.put(col, &key, &value);
+                stats.inserted += 1;
+            }
+        }
+        // Version is bumped last, in the same batch as backfilled entries, so an interrupted migration is run again.
+        if !dry_run {
+            wb.put(
+                COL_DEFAULT,
+                <DynamicProperty as keys::Key<i64>>::key(&DynamicProperty::DbVersion).as_bytes(),
+                &<DynamicProperty as keys::Key<i64>>::value(&(migration.from_version + 1)),
+            );
+            self.db.inner.write(&wb)?;
+        }
+        Ok(stats)
+    }
+}
+
+fn archive_key_prefix_of(col: usize, key: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(1 + 4 + key.len() + 8);
+    raw.push(col as u8);
+    raw.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    raw.extend_from_slice(key);
+    raw
+}
+
+fn archive_key_of(col: usize, key: &[u8], block_number: i64) -> Vec<u8> {
+    let mut raw = archive_key_prefix_of(col, key);
+    raw.extend_from_slice(&(block_number as u64).to_be_bytes());
+    raw
+}
+
+fn archive_value_of(prev_value: Option<&[u8]>) -> Vec<u8> {
+    match prev_value {
+        Some(val) => [&[1u8][..], val].concat(),
+        None => vec![0],
+    }
+}
+
+fn parse_archive_value(raw: &[u8]) -> Option<Vec<u8>> {
+    if raw[0] == 1 {
+        Some(raw[1..].to_vec())
+    } else {
+        None
+    }
+}
+
+/// Solidified state-db of a running node, opened as a RocksDB secondary instance.
+///
+/// Cached layers of unsolidified blocks live in the node's memory, so only the solid state is visible.
+pub struct ReadOnlySolidStateDB {
+    db: StateDB,
+}
 
 impl ReadOnlySolidStateDB {
-    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(db_path: P1, tmp_path: P2) -> StateDB {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(db_path: P1, tmp_path: P2) -> Self {
         let db_options = DBOptions::default()
             .increase_parallelism(num_cpus::get() as _)
             .allow_mmap_reads(true) // for Cuckoo table
-            .max_open_files(1024);
-
-        let column_families = col_descs_for_state_db();
+            .max_open_files(-1); // required by secondary instances
 
-        let (db, cols) =
-            DB::open_as_secondary_with_column_families(&db_options, db_path, tmp_path, column_families).unwrap();
+        let db = RocksDB::open_as_secondary(&db_options, db_path, tmp_path, col_descs_for_state_db()).unwrap();
 
-        StateDB {
-            db: OverlayDB::new(db),
-            cols,
+        ReadOnlySolidStateDB {
+            db: StateDB::from_kvdb(Box::new(db)),
         }
     }
 
     pub fn get<T, K: keys::Key<T>>(&self, key: &K) -> Result<Option<T>, BoxError> {
-        self.db
-            .get_cf(ReadOptions::default_instance(), &self.cols[K::COL], key.key().as_ref())
-            .map(|raw| Some(K::parse_value(&raw)))
-            .or_else(|e| if e.is_not_found() { Ok(None) } else { Err(e) })
-            .map_err(|e| e.into())
+        self.db.get(key)
     }
 
-    pub fn catch_up_with_primary(&self) {
-        let _ = self.db.try_catch_up_with_primary();
+    pub fn catch_up_with_primary(&self) -> Result<(), BoxError> {
+        Ok(self.db.db.inner.try_catch_up_with_primary()?)
+    }
+
+    /// A read-only state-db on the data caught up so far.
+    pub fn snapshot(&self) -> StateDB {
+        self.db.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::ChainParameter;
+
+    fn params(state_db: &StateDB) -> Vec<(ChainParameter, i64)> {
+        let mut params = vec![];
+        state_db.for_each(|key: &ChainParameter, value| params.push((*key, *value)));
+        params
+    }
+
+    #[test]
+    fn test_for_each_with_view() {
+        let mut state_db = StateDB::new_in_memory();
+        state_db.set_archive_mode(true);
+
+        state_db.new_layer();
+        state_db.put_key(ChainParameter::AllowTvm, 1).unwrap();
+        state_db.solidify_block_layers(1, 1).unwrap();
+
+        state_db.new_layer();
+        state_db.put_key(ChainParameter::AllowTvm, 2).unwrap();
+        state_db.put_key(ChainParameter::AllowMultisig, 3).unwrap();
+        state_db.solidify_block_layers(2, 1).unwrap();
+
+        state_db.new_layer();
+        state_db.delete_key(&ChainParameter::AllowTvm).unwrap();
+        assert_eq!(params(&state_db), vec![(ChainParameter::AllowMultisig, 3)]);
+
+        let view = state_db.view_at(2, 1, false).unwrap();
+        state_db.enter_view(view);
+        let mut expected = vec![(ChainParameter::AllowTvm, 2), (ChainParameter::AllowMultisig, 3)];
+        expected.sort();
+        assert_eq!(params(&state_db), expected);
+
+        let view = state_db.view_at(1, 1, true).unwrap();
+        state_db.enter_view(view);
+        assert_eq!(params(&state_db), vec![(ChainParameter::AllowTvm, 1)]);
+        state_db.leave_view();
+    }
+
+    #[test]
+    fn test_solidify_block_layers() {
+        let mut state_db = StateDB::new_in_memory();
+        state_db.set_archive_mode(true);
+
+        state_db.new_layer();
+        state_db.put_key(ChainParameter::AllowTvm, 1).unwrap();
+        state_db.new_layer();
+        state_db.put_key(ChainParameter::AllowTvm, 2).unwrap();
+        state_db.solidify_block_layers(1, 2).unwrap();
+
+        assert!(state_db.db.layers.is_empty());
+        assert_eq!(state_db.get(&ChainParameter::AllowTvm).unwrap(), Some(2));
+
+        let view = state_db.view_at(0, 0, true).unwrap();
+        assert_eq!(state_db.get_with_view(&ChainParameter::AllowTvm, &view).unwrap(), None);
     }
 }
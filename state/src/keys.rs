@@ -142,6 +142,50 @@ impl Key<H256> for LatestBlockHash {
     }
 }
 
+/// First block whose change set is saved in archive mode.
+#[derive(Debug)]
+pub struct ArchiveStartBlock;
+
+impl Key<i64> for ArchiveStartBlock {
+    type Target = &'static str;
+    const COL: usize = super::db::COL_DEFAULT;
+
+    // Same as DynamicProperty
+    fn key(&self) -> Self::Target {
+        "kArchiveStartBlock"
+    }
+
+    fn value(val: &i64) -> Cow<[u8]> {
+        Cow::Owned(val.to_be_bytes().to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> i64 {
+        BE::read_u64(raw) as _
+    }
+}
+
+/// Block number of a snapshot being imported, set while state-db data is being replaced.
+#[derive(Debug)]
+pub struct ImportingSnapshot;
+
+impl Key<i64> for ImportingSnapshot {
+    type Target = &'static str;
+    const COL: usize = super::db::COL_DEFAULT;
+
+    // Same as DynamicProperty
+    fn key(&self) -> Self::Target {
+        "kImportingSnapshot"
+    }
+
+    fn value(val: &i64) -> Cow<[u8]> {
+        Cow::Owned(val.to_be_bytes().to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> i64 {
+        BE::read_u64(raw) as _
+    }
+}
+
 #[derive(Debug)]
 pub struct BlockFilledSlots;
 
@@ -261,6 +305,10 @@ impl Key<Address> for AccountIndex {
     fn parse_value(raw: &[u8]) -> Address {
         *Address::from_bytes(raw)
     }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        String::from_utf8(raw.to_vec()).ok().map(AccountIndex)
+    }
 }
 
 /// Resource delegation, from_address, to_address.
@@ -284,6 +332,13 @@ impl Key<pb::ResourceDelegation> for ResourceDelegation {
     fn parse_value(raw: &[u8]) -> pb::ResourceDelegation {
         pb::ResourceDelegation::decode(raw).unwrap()
     }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        if raw.len() != 42 {
+            return None;
+        }
+        Some(ResourceDelegation(*Address::from_bytes(&raw[..21]), *Address::from_bytes(&raw[21..])))
+    }
 }
 
 /// Reverse index for resource delegation info, to_address.
@@ -315,6 +370,10 @@ impl Key<Vec<Address>> for ResourceDelegationIndex {
             .collect::<Result<Vec<_>, _>>()
             .unwrap()
     }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        Some(ResourceDelegationIndex(*Address::from_bytes(raw)))
+    }
 }
 
 /// `<<Address>> => Votes { epoch: i64, votes: [Votes] }`
@@ -338,6 +397,10 @@ impl Key<pb::Votes> for Votes {
     fn parse_value(raw: &[u8]) -> pb::Votes {
         pb::Votes::decode(raw).unwrap()
     }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        Some(Votes(*Address::from_bytes(raw)))
+    }
 }
 
 /// `Address => pb::SmartContract`
@@ -361,6 +424,10 @@ impl Key<pb::SmartContract> for Contract {
     fn parse_value(raw: &[u8]) -> pb::SmartContract {
         pb::SmartContract::decode(raw).unwrap()
     }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        Some(Contract(*Address::from_bytes(raw)))
+    }
 }
 
 /// `Address => Vec<u8>`
@@ -491,6 +558,161 @@ impl Key<pb::Exchange> for Exchange {
     }
 }
 
+/// Market order.
+#[derive(Debug)]
+pub struct MarketOrder(pub H256);
+
+impl Key<pb::MarketOrder> for MarketOrder {
+    type Target = Vec<u8>;
+    const COL: usize = super::db::COL_MARKET_ORDER;
+
+    fn key(&self) -> Self::Target {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn value(val: &pb::MarketOrder) -> Cow<[u8]> {
+        let mut buf = BytesMut::with_capacity(val.encoded_len());
+        val.encode(&mut buf).unwrap();
+        Cow::from(buf.to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> pb::MarketOrder {
+        pb::MarketOrder::decode(raw).unwrap()
+    }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        Some(MarketOrder(H256::from_slice(raw)))
+    }
+}
+
+/// Market price level of a token pair, with quantities reduced by their gcd.
+/// `<<sell_token_id: i64, buy_token_id: i64, sell_token_quantity: i64, buy_token_quantity: i64>>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketPriceLevel(pub i64, pub i64, pub i64, pub i64);
+
+impl MarketPriceLevel {
+    /// Key prefix of all price levels of a token pair.
+    pub fn prefix_of(sell_token_id: i64, buy_token_id: i64) -> Vec<u8> {
+        [
+            &(sell_token_id as u64).to_be_bytes()[..],
+            &(buy_token_id as u64).to_be_bytes()[..],
+        ]
+        .concat()
+    }
+}
+
+impl Key<pb::MarketOrderIdList> for MarketPriceLevel {
+    type Target = Vec<u8>;
+    const COL: usize = super::db::COL_MARKET_PRICE_LEVEL;
+
+    fn key(&self) -> Self::Target {
+        let mut raw = Vec::with_capacity(32);
+        raw.extend_from_slice(&(self.0 as u64).to_be_bytes());
+        raw.extend_from_slice(&(self.1 as u64).to_be_bytes());
+        raw.extend_from_slice(&(self.2 as u64).to_be_bytes());
+        raw.extend_from_slice(&(self.3 as u64).to_be_bytes());
+        raw
+    }
+
+    fn value(val: &pb::MarketOrderIdList) -> Cow<[u8]> {
+        let mut buf = BytesMut::with_capacity(val.encoded_len());
+        val.encode(&mut buf).unwrap();
+        Cow::from(buf.to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> pb::MarketOrderIdList {
+        pb::MarketOrderIdList::decode(raw).unwrap()
+    }
+
+    fn parse_key(raw: &[u8]) -> Option<Self> {
+        if raw.len() != 32 {
+            return None;
+        }
+        Some(MarketPriceLevel(
+            BE::read_u64(&raw[..8]) as i64,
+            BE::read_u64(&raw[8..16]) as i64,
+            BE::read_u64(&raw[16..24]) as i64,
+            BE::read_u64(&raw[24..]) as i64,
+        ))
+    }
+}
+
+/// Active market orders of an account.
+#[derive(Debug)]
+pub struct MarketAccountOrders(pub Address);
+
+impl Key<pb::MarketAccountOrders> for MarketAccountOrders {
+    type Target = Vec<u8>;
+    const COL: usize = super::db::COL_MARKET_ACCOUNT_ORDERS;
+
+    fn key(&self) -> Self::Target {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn value(val: &pb::MarketAccountOrders) -> Cow<[u8]> {
+        let mut buf = BytesMut::with_capacity(val.encoded_len());
+        val.encode(&mut buf).unwrap();
+        Cow::from(buf.to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> pb::MarketAccountOrders {
+        pb::MarketAccountOrders::decode(raw).unwrap()
+    }
+}
+
+/// Transaction included in recent blocks, used in duplication check.
+/// `<<b't', txn_hash: H256>> => block_number: i64`
+#[derive(Debug)]
+pub struct RecentTransaction(pub H256);
+
+impl Key<i64> for RecentTransaction {
+    type Target = Vec<u8>;
+    const COL: usize = super::db::COL_RECENT_TRANSACTION;
+
+    fn key(&self) -> Self::Target {
+        [&b"t"[..], self.0.as_bytes()].concat()
+    }
+
+    fn value(val: &i64) -> Cow<[u8]> {
+        Cow::Owned(val.to_be_bytes().to_vec())
+    }
+
+    fn parse_value(raw: &[u8]) -> i64 {
+        BE::read_u64(raw) as _
+    }
+}
+
+/// Transactions of a recent block, used to expire `RecentTransaction`s.
+/// `<<b'b', block_number: i64>> => [txn_hash: H256]`
+#[derive(Debug)]
+pub struct RecentBlockTransactions(pub i64);
+
+impl Key<Vec<H256>> for RecentBlockTransactions {
+    type Target = Vec<u8>;
+    const COL: usize = super::db::COL_RECENT_TRANSACTION;
+
+    fn key(&self) -> Self::Target {
+        let mut raw = [b'b'; 9];
+        BE::write_u64(&mut raw[1..], self.0 as u64);
+        raw.to_vec()
+    }
+
+    fn value(val: &Vec<H256>) -> Cow<[u8]> {
+        val.iter()
+            .map(|hash| hash.as_bytes())
+            .collect::<Vec<_>>()
+            .concat()
+            .into()
+    }
+
+    fn parse_value(raw: &[u8]) -> Vec<H256> {
+        if raw.len() % 32 != 0 {
+            panic!("malformed RecentBlockTransactions");
+        }
+        raw.chunks(32).map(H256::from_slice).collect()
+    }
+}
+
 #[derive(Debug)]
 pub struct TransactionReceipt(pub H256);
 
@@ -514,7 +736,7 @@ impl Key<pb::TransactionReceipt> for TransactionReceipt {
 }
 
 #[derive(Debug)]
-pub struct InternalTransaction(H256);
+pub struct InternalTransaction(pub H256);
 
 impl Key<pb::InternalTransaction> for InternalTransaction {
     type Target = Vec<u8>;
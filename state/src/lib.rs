@@ -3,5 +3,7 @@ pub use property::DynamicProperty;
 
 pub mod db;
 pub mod keys;
+pub mod migration;
 pub mod parameter;
 mod property;
+pub mod snapshot;
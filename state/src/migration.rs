@@ -0,0 +1,196 @@
+//! Schema migrations of the state-db.
+//!
+//! The schema version is saved as `DynamicProperty::DbVersion` in the default column. Any change to the column
+//! layout in `col_descs_for_state_db` or to key and value encodings in `state::keys` must bump
+//! `CURRENT_DB_VERSION`, and add a migration from the previous version to `MIGRATIONS`. So must new chain parameters
+//! and dynamic properties, which are backfilled by the migration.
+
+use config::ChainConfig;
+
+use crate::keys::Key;
+use crate::parameter::default_parameters_from_config;
+use crate::{ChainParameter, DynamicProperty};
+
+/// Schema version of state-dbs created by this build.
+pub const CURRENT_DB_VERSION: i64 = 2;
+
+/// All migrations, in the order of versions. `MIGRATIONS[i]` migrates version `i + 1` to `i + 2`.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from_version: 1,
+    description: "backfill market and TVM upgrade parameters, and other missing properties",
+    columns: &[],
+    rewrite: keep,
+    backfill: default_parameters_and_properties,
+}];
+
+/// A db entry, `(column, key, value)`.
+pub type Entry = (usize, Vec<u8>, Vec<u8>);
+
+/// Rewrite of a db entry.
+#[derive(Debug, PartialEq)]
+pub enum Rewrite {
+    Keep,
+    /// Replace the entry, the old key is deleted if the key changes.
+    Put(Vec<u8>, Vec<u8>),
+    Delete,
+}
+
+/// Migration from `from_version` to `from_version + 1`.
+///
+/// Rewrites must be idempotent, an interrupted migration is run again from the start.
+pub struct Migration {
+    pub from_version: i64,
+    pub description: &'static str,
+    /// Columns to visit, in order.
+    pub columns: &'static [usize],
+    /// Rewrite an entry of a column, `fn(column, key, value)`.
+    pub rewrite: fn(usize, &[u8], &[u8]) -> Rewrite,
+    /// Entries to insert after rewriting, an entry is skipped if its key already exists.
+    pub backfill: fn(&ChainConfig) -> Vec<Entry>,
+}
+
+/// Rewrite of migrations that only backfill.
+pub fn keep(_col: usize, _key: &[u8], _value: &[u8]) -> Rewrite {
+    Rewrite::Keep
+}
+
+/// Backfill of migrations that only rewrite.
+pub fn no_backfill(_chain: &ChainConfig) -> Vec<Entry> {
+    vec![]
+}
+
+/// Chain parameters and dynamic properties of a newly inited state-db.
+///
+/// `TotalIssuedReward` is left out, rewards issued before it was tracked are unknown, and 0 would fail the supply
+/// check.
+fn default_parameters_and_properties(chain: &ChainConfig) -> Vec<Entry> {
+    let parameters = default_parameters_from_config(&chain.parameter)
+        .into_iter()
+        .map(|(k, v)| {
+            (
+                <ChainParameter as Key<i64>>::COL,
+                k.key().as_ref().to_vec(),
+                <ChainParameter as Key<i64>>::value(&v).into_owned(),
+            )
+        });
+    let properties = DynamicProperty::default_properties()
+        .into_iter()
+        .filter(|(k, _)| *k != DynamicProperty::TotalIssuedReward)
+        .map(|(k, v)| {
+            (
+                <DynamicProperty as Key<i64>>::COL,
+                k.key().as_ref().to_vec(),
+                <DynamicProperty as Key<i64>>::value(&v).into_owned(),
+            )
+        });
+    parameters.chain(properties).collect()
+}
+
+/// Entries visited and changed by a migration.
+#[derive(Debug, Default, PartialEq)]
+pub struct MigrationStats {
+    pub from_version: i64,
+    pub visited: u64,
+    pub rewritten: u64,
+    pub deleted: u64,
+    pub inserted: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{StateDB, COL_ACCOUNT_INDEX, COL_DEFAULT, NUM_OF_COLUMNS};
+    use crate::keys;
+    use ::keys::Address;
+    use config::Config;
+    use kvdb::{KeyValueDB, MemoryDB, WriteBatch};
+
+    fn rename_account_index(_col: usize, key: &[u8], value: &[u8]) -> Rewrite {
+        match key {
+            b"old" => Rewrite::Put(b"new".to_vec(), value.to_vec()),
+            b"stale" => Rewrite::Delete,
+            _ => Rewrite::Keep,
+        }
+    }
+
+    fn backfill_account_index(_chain: &ChainConfig) -> Vec<Entry> {
+        ["other", "added"]
+            .iter()
+            .map(|name| (COL_ACCOUNT_INDEX, name.as_bytes().to_vec(), vec![0xff; 21]))
+            .collect()
+    }
+
+    fn version_1_db() -> MemoryDB {
+        let kvdb = MemoryDB::new(NUM_OF_COLUMNS);
+        let mut wb = WriteBatch::new();
+        wb.put(COL_DEFAULT, DynamicProperty::DbVersion.key().as_bytes(), &DynamicProperty::value(&1));
+        kvdb.write(&wb).unwrap();
+        kvdb
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[Migration {
+        from_version: 1,
+        description: "rename account index",
+        columns: &[COL_ACCOUNT_INDEX],
+        rewrite: rename_account_index,
+        backfill: backfill_account_index,
+    }];
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from_version, i as i64 + 1, "{}", migration.description);
+        }
+        assert_eq!(MIGRATIONS.len() as i64 + 1, CURRENT_DB_VERSION);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let chain = Config::load_from_file("../config/conf.nile.toml").unwrap().chain;
+        let addr = Address::default();
+        let kvdb = version_1_db();
+        let mut wb = WriteBatch::new();
+        for name in &["old", "stale", "other"] {
+            wb.put(COL_ACCOUNT_INDEX, name.as_bytes(), addr.as_bytes());
+        }
+        kvdb.write(&wb).unwrap();
+        let mut db = StateDB::from_kvdb(Box::new(kvdb));
+
+        let expected = vec![MigrationStats {
+            from_version: 1,
+            visited: 3,
+            rewritten: 1,
+            deleted: 1,
+            inserted: 1,
+        }];
+        assert_eq!(db.migrate_with(TEST_MIGRATIONS, 2, &chain, true).unwrap(), expected);
+        assert_eq!(db.db_version().unwrap(), Some(1));
+        assert!(db.get(&keys::AccountIndex("old".into())).unwrap().is_some());
+
+        assert_eq!(db.migrate_with(TEST_MIGRATIONS, 2, &chain, false).unwrap(), expected);
+        assert_eq!(db.db_version().unwrap(), Some(2));
+        assert!(db.get(&keys::AccountIndex("old".into())).unwrap().is_none());
+        assert!(db.get(&keys::AccountIndex("stale".into())).unwrap().is_none());
+        assert_eq!(db.get(&keys::AccountIndex("new".into())).unwrap(), Some(addr));
+        assert_eq!(db.get(&keys::AccountIndex("other".into())).unwrap(), Some(addr));
+        assert!(db.get(&keys::AccountIndex("added".into())).unwrap().is_some());
+
+        assert!(db.migrate_with(TEST_MIGRATIONS, 2, &chain, false).unwrap().is_empty());
+        assert!(db.migrate_with(TEST_MIGRATIONS, 1, &chain, false).is_err());
+        assert!(db.migrate_with(&[], 3, &chain, false).is_err());
+    }
+
+    #[test]
+    fn test_backfill_version_1() {
+        let chain = Config::load_from_file("../config/conf.nile.toml").unwrap().chain;
+        let mut db = StateDB::from_kvdb(Box::new(version_1_db()));
+
+        let stats = db.migrate(&chain, false).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert!(stats[0].inserted > 0);
+        assert_eq!(db.db_version().unwrap(), Some(CURRENT_DB_VERSION));
+        assert_eq!(db.get(&ChainParameter::AllowMarketTransaction).unwrap(), Some(0));
+        assert_eq!(db.get(&DynamicProperty::TotalIssuedReward).unwrap(), None);
+        assert!(db.migrate(&chain, false).unwrap().is_empty());
+    }
+}
@@ -28,6 +28,9 @@ pub fn default_parameters() -> impl IntoIterator<Item = (ChainParameter, i64)> {
         (AssetIssueFee, 1024_000_000),
         (ExchangeCreateFee, 1024_000_000),
         (MultisigFee, 1_000_000),
+        (AllowMarketTransaction, 0),
+        (MarketSellFee, 0),
+        (MarketCancelFee, 0),
         (CreateNewAccountFeeInSystemContract, 0),
         (CreateNewAccountBandwidthRate, 1),
         (TotalEnergyLimit, 50_000_000_000),
@@ -44,6 +47,9 @@ pub fn default_parameters() -> impl IntoIterator<Item = (ChainParameter, i64)> {
         (AllowTvmConstantinopleUpgrade, 0),
         (AllowTvmSolidity059Upgrade, 0),
         (AllowTvmShieldedUpgrade, 0),
+        (AllowTvmIstanbulUpgrade, 0),
+        (AllowTvmStakeUpgrade, 0),
+        (AllowTvmAssetIssueUpgrade, 0),
         (AllowProtoFilterNum, 0),
     ];
 }
@@ -73,6 +79,9 @@ pub fn default_parameters_from_config(
         (AssetIssueFee, 1024_000_000),
         (ExchangeCreateFee, 1024_000_000),
         (MultisigFee, 1_000_000),
+        (AllowMarketTransaction, 0),
+        (MarketSellFee, 0),
+        (MarketCancelFee, 0),
         (CreateNewAccountFeeInSystemContract, 0),
         (CreateNewAccountBandwidthRate, 1),
         (TotalEnergyLimit, 50_000_000_000),
@@ -94,6 +103,9 @@ pub fn default_parameters_from_config(
         ),
         (AllowTvmSolidity059Upgrade, config.allow_tvm_solidity_059_upgrade as i64),
         (AllowTvmShieldedUpgrade, config.allow_tvm_shielded_upgrade as i64),
+        (AllowTvmIstanbulUpgrade, config.allow_tvm_istanbul_upgrade as i64),
+        (AllowTvmStakeUpgrade, config.allow_tvm_stake_upgrade as i64),
+        (AllowTvmAssetIssueUpgrade, config.allow_tvm_asset_issue_upgrade as i64),
         (AllowProtoFilterNum, 0),
     ];
 }
@@ -1,14 +1,12 @@
 use std::collections::HashMap;
 
+use super::migration::CURRENT_DB_VERSION;
 use super::ChainParameter;
 
-/// Used for DB migrations. Corresponding key is `DynamicProperty::DbVersion`.
-const CURRENT_DB_VERSION: i64 = 1;
-
 /// Dynamic properties of a living chain.
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum DynamicProperty {
-    /// For migration.
+    /// Schema version, for migration. See `state::migration`.
     DbVersion,
 
     // * Global IDs
@@ -55,6 +53,12 @@ pub enum DynamicProperty {
     // ChainParameter::TotalEnergyCurrentLimit = getTotalEnergyLimit()
     // ChainParameter::TotalEnergyLimit = 90000000000
 
+    // * Supply
+    /// Rewards issued to witnesses and voters since genesis, checked by `check state`.
+    ///
+    /// Missing in state-dbs created before it's tracked.
+    TotalIssuedReward,
+
     // * Global Free Bandwidth ('public' is ambiguous)
     /// Renamed: PublicNetLimit = 14_400_000_000
     GlobalFreeBandwidthLimit,
@@ -113,6 +117,7 @@ impl DynamicProperty {
             (TotalBandwidthWeight, 0),
             (TotalBandwidthLimit, 43_200_000_000),
             (TotalEnergyWeight, 0),
+            (TotalIssuedReward, 0),
             (GlobalFreeBandwidthLimit, 14_400_000_000),
             (GlobalFreeBandwidthUsed, 0),
             (GlobalFreeBandwidthLatestSlot, 0),
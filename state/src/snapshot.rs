@@ -0,0 +1,270 @@
+//! Snapshot file of the state-db, used for fast node bootstrap.
+//!
+//! All integers are big endian.
+//!
+//! - header: `<<magic: b"OTSNAP", version: u32, block_number: i64, block_hash: H256, num_of_columns: u32>>`
+//! - chunk: `<<column: u32, num_of_entries: u32, payload_len: u32, payload, checksum: H256>>`, where payload is
+//!   `[<<key_len: u32, key, value_len: u32, value>>]` and checksum is sha256 of the payload
+//! - trailer: `<<0xffffffff: u32, num_of_entries: u64, checksum: H256>>`, where checksum is sha256 of the header
+//!   and all chunk checksums
+
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use primitive_types::H256;
+
+pub const SNAPSHOT_MAGIC: &[u8; 6] = b"OTSNAP";
+/// Bumped when the file format or the state-db layout changes.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Soft limit of chunk payload size.
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Hard limit of chunk payload size, a chunk only exceeds `MAX_CHUNK_SIZE` when holding a single large entry.
+const MAX_PAYLOAD_SIZE: usize = 16 * 1024 * 1024;
+const END_OF_CHUNKS: u32 = u32::MAX;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    pub block_number: i64,
+    pub block_hash: H256,
+    pub num_of_columns: u32,
+}
+
+impl SnapshotHeader {
+    fn encode(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(6 + 4 + 8 + 32 + 4);
+        raw.extend_from_slice(SNAPSHOT_MAGIC);
+        raw.extend_from_slice(&self.version.to_be_bytes());
+        raw.extend_from_slice(&self.block_number.to_be_bytes());
+        raw.extend_from_slice(self.block_hash.as_bytes());
+        raw.extend_from_slice(&self.num_of_columns.to_be_bytes());
+        raw
+    }
+}
+
+/// A chunk of key-value pairs in one column.
+pub struct SnapshotChunk {
+    pub column: u32,
+    pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+pub struct SnapshotWriter<W: Write> {
+    inner: W,
+    column: u32,
+    payload: Vec<u8>,
+    num_of_chunk_entries: u32,
+    num_of_entries: u64,
+    // header and chunk checksums
+    digest_input: Vec<u8>,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    pub fn new(mut inner: W, header: &SnapshotHeader) -> io::Result<Self> {
+        let raw_header = header.encode();
+        inner.write_all(&raw_header)?;
+        Ok(SnapshotWriter {
+            inner,
+            column: 0,
+            payload: Vec::with_capacity(MAX_CHUNK_SIZE),
+            num_of_chunk_entries: 0,
+            num_of_entries: 0,
+            digest_input: raw_header,
+        })
+    }
+
+    pub fn put(&mut self, column: u32, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let entry_len = 4 + key.len() + 4 + value.len();
+        if entry_len > MAX_PAYLOAD_SIZE {
+            return Err(invalid_data("entry is too large for a snapshot chunk"));
+        }
+        if self.num_of_chunk_entries > 0 && (column != self.column || self.payload.len() + entry_len > MAX_CHUNK_SIZE) {
+            self.flush_chunk()?;
+        }
+        self.column = column;
+        self.payload.write_u32::<BE>(key.len() as u32)?;
+        self.payload.extend_from_slice(key);
+        self.payload.write_u32::<BE>(value.len() as u32)?;
+        self.payload.extend_from_slice(value);
+        self.num_of_chunk_entries += 1;
+        self.num_of_entries += 1;
+        Ok(())
+    }
+
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        let checksum = crypto::sha256(&self.payload);
+        self.inner.write_u32::<BE>(self.column)?;
+        self.inner.write_u32::<BE>(self.num_of_chunk_entries)?;
+        self.inner.write_u32::<BE>(self.payload.len() as u32)?;
+        self.inner.write_all(&self.payload)?;
+        self.inner.write_all(checksum.as_bytes())?;
+        self.digest_input.extend_from_slice(checksum.as_bytes());
+
+        self.payload.clear();
+        self.num_of_chunk_entries = 0;
+        Ok(())
+    }
+
+    /// Write the trailer, returns number of entries written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        if self.num_of_chunk_entries > 0 {
+            self.flush_chunk()?;
+        }
+        self.inner.write_u32::<BE>(END_OF_CHUNKS)?;
+        self.inner.write_u64::<BE>(self.num_of_entries)?;
+        self.inner.write_all(crypto::sha256(&self.digest_input).as_bytes())?;
+        self.inner.flush()?;
+        Ok(self.num_of_entries)
+    }
+}
+
+pub struct SnapshotReader<R: Read> {
+    inner: R,
+    header: SnapshotHeader,
+    num_of_entries: u64,
+    digest_input: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0u8; 6];
+        inner.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(invalid_data("not a snapshot file"));
+        }
+        let version = inner.read_u32::<BE>()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported snapshot version {}, expected {}",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+        let block_number = inner.read_i64::<BE>()?;
+        let mut block_hash = H256::zero();
+        inner.read_exact(block_hash.as_bytes_mut())?;
+        let num_of_columns = inner.read_u32::<BE>()?;
+
+        let header = SnapshotHeader {
+            version,
+            block_number,
+            block_hash,
+            num_of_columns,
+        };
+        Ok(SnapshotReader {
+            inner,
+            digest_input: header.encode(),
+            header,
+            num_of_entries: 0,
+            finished: false,
+        })
+    }
+
+    pub fn header(&self) -> &SnapshotHeader {
+        &self.header
+    }
+
+    /// Read the next chunk, verifying its checksum. Returns `None` after the trailer is read and verified.
+    pub fn next_chunk(&mut self) -> io::Result<Option<SnapshotChunk>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let column = self.inner.read_u32::<BE>()?;
+        if column == END_OF_CHUNKS {
+            let num_of_entries = self.inner.read_u64::<BE>()?;
+            let mut checksum = H256::zero();
+            self.inner.read_exact(checksum.as_bytes_mut())?;
+            if num_of_entries != self.num_of_entries {
+                return Err(invalid_data("number of entries mismatch"));
+            }
+            if checksum != crypto::sha256(&self.digest_input) {
+                return Err(invalid_data("snapshot checksum mismatch"));
+            }
+            self.finished = true;
+            return Ok(None);
+        }
+        if column >= self.header.num_of_columns {
+            return Err(invalid_data("invalid column"));
+        }
+
+        let num_of_chunk_entries = self.inner.read_u32::<BE>()?;
+        let payload_len = self.inner.read_u32::<BE>()? as usize;
+        if payload_len > MAX_PAYLOAD_SIZE {
+            return Err(invalid_data("chunk is too large"));
+        }
+        let mut payload = vec![0u8; payload_len];
+        self.inner.read_exact(&mut payload)?;
+        let mut checksum = H256::zero();
+        self.inner.read_exact(checksum.as_bytes_mut())?;
+        if checksum != crypto::sha256(&payload) {
+            return Err(invalid_data("chunk checksum mismatch"));
+        }
+        self.digest_input.extend_from_slice(checksum.as_bytes());
+
+        // each entry takes at least 8 bytes
+        let mut entries = Vec::with_capacity((num_of_chunk_entries as usize).min(payload_len / 8));
+        let mut rd = &payload[..];
+        for _ in 0..num_of_chunk_entries {
+            let key_len = rd.read_u32::<BE>()? as usize;
+            let mut key = vec![0u8; key_len];
+            rd.read_exact(&mut key)?;
+            let value_len = rd.read_u32::<BE>()? as usize;
+            let mut value = vec![0u8; value_len];
+            rd.read_exact(&mut value)?;
+            entries.push((key, value));
+        }
+        if !rd.is_empty() {
+            return Err(invalid_data("malformed chunk"));
+        }
+        self.num_of_entries += entries.len() as u64;
+
+        Ok(Some(SnapshotChunk { column, entries }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            block_number: 100,
+            block_hash: H256::repeat_byte(0xaa),
+            num_of_columns: 3,
+        };
+        let mut buf = vec![];
+        let mut writer = SnapshotWriter::new(&mut buf, &header).unwrap();
+        writer.put(0, b"k1", b"v1").unwrap();
+        writer.put(0, b"k2", b"").unwrap();
+        writer.put(2, b"k3", &[0u8; 100]).unwrap();
+        assert_eq!(writer.finish().unwrap(), 3);
+
+        let mut reader = SnapshotReader::new(&buf[..]).unwrap();
+        assert_eq!(reader.header(), &header);
+        let chunk = reader.next_chunk().unwrap().unwrap();
+        assert_eq!(chunk.column, 0);
+        assert_eq!(chunk.entries, vec![(b"k1".to_vec(), b"v1".to_vec()), (b"k2".to_vec(), vec![])]);
+        let chunk = reader.next_chunk().unwrap().unwrap();
+        assert_eq!(chunk.column, 2);
+        assert_eq!(chunk.entries.len(), 1);
+        assert!(reader.next_chunk().unwrap().is_none());
+
+        // corrupted payload
+        buf[70] ^= 0xff;
+        let mut reader = SnapshotReader::new(&buf[..]).unwrap();
+        assert!(reader.next_chunk().is_err());
+
+        // forged payload length, rejected before allocating
+        buf[70] ^= 0xff;
+        buf[62..66].copy_from_slice(&u32::MAX.to_be_bytes());
+        let mut reader = SnapshotReader::new(&buf[..]).unwrap();
+        assert_eq!(reader.next_chunk().unwrap_err().to_string(), "chunk is too large");
+    }
+}
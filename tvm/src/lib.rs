@@ -8,6 +8,13 @@ use self::backend::Backend;
 pub mod backend;
 pub mod precompile;
 
+/// Events of TVM execution, from the executor, the interpreter and the gasometer.
+pub mod tracing {
+    pub use evm::gasometer::tracing as gasometer;
+    pub use evm::runtime::tracing as runtime;
+    pub use evm::tracing::*;
+}
+
 /// Handle TVM upgrades.
 #[derive(Debug, Clone, Default)]
 pub struct TvmUpgrade {
@@ -19,11 +26,11 @@ pub struct TvmUpgrade {
     pub solidity059: bool,
     /// AllowTvmShieldedUpgrade, a precompile only upgrade.
     pub shielded: bool,
-    /// AllowTvmStakeUpgrade
+    /// AllowTvmStakeUpgrade, has STAKE, UNSTAKE, WITHDRAWREWARD, REWARDBALANCE, ISSRCANDIDATE.
     pub stake: bool,
-    /// AllowTvmIstanbulUpgrade
+    /// AllowTvmIstanbulUpgrade, has CHAINID, SELFBALANCE, fixed CREATE2 and bn128 energy repricing.
     pub istanbul: bool,
-    /// AllowTvmAssetIssueUpgrade
+    /// AllowTvmAssetIssueUpgrade, has TOKENISSUE, UPDATEASSET.
     pub asset_issue: bool,
     /// AllowMultisig
     pub multisig: bool,
@@ -40,6 +47,9 @@ impl TvmUpgrade {
     pub fn precompile(
         &self,
     ) -> fn(H160, &[u8], Option<usize>, &dyn Backend) -> Option<Result<(ExitSucceed, Vec<u8>, usize), ExitError>> {
+        if self.istanbul {
+            return self::precompile::tron_istanbul_precompile;
+        }
         return self::precompile::tron_precompile;
     }
 
@@ -60,7 +70,17 @@ impl TvmUpgrade {
         if self.solidity059 {
             config.allow_tvm_solidity059();
         }
-        // TODO: handle 4.1 update.
+        if self.istanbul {
+            // NOTE: The CREATE2 address fix is not supported by the evm dependency yet.
+            config.has_chain_id = true;
+            config.has_self_balance = true;
+        }
+        if self.stake {
+            config.allow_tvm_stake();
+        }
+        if self.asset_issue {
+            config.allow_tvm_asset_issue();
+        }
         config
     }
 }
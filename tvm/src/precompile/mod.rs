@@ -49,6 +49,25 @@ pub fn tron_precompile(
     input: &[u8],
     _target_gas: Option<usize>,
     backend: &dyn Backend,
+) -> Option<Result<(ExitSucceed, Vec<u8>, usize), ExitError>> {
+    precompile(address, input, backend, false)
+}
+
+/// Precompiles after AllowTvmIstanbulUpgrade, with bn128 energy costs repriced as in EIP-1108.
+pub fn tron_istanbul_precompile(
+    address: H160,
+    input: &[u8],
+    _target_gas: Option<usize>,
+    backend: &dyn Backend,
+) -> Option<Result<(ExitSucceed, Vec<u8>, usize), ExitError>> {
+    precompile(address, input, backend, true)
+}
+
+fn precompile(
+    address: H160,
+    input: &[u8],
+    backend: &dyn Backend,
+    istanbul: bool,
 ) -> Option<Result<(ExitSucceed, Vec<u8>, usize), ExitError>> {
     if address > H160::from_low_u64_be(0xffffffff) {
         return None;
@@ -132,22 +151,22 @@ pub fn tron_precompile(
             Some(Ok((ExitSucceed::Returned, ret_with_leading_zeros, cost)))
         }
         0x6 => {
-            const COST: usize = 500;
+            let cost = if istanbul { 150 } else { 500 };
 
             let ret = alt_bn128::ecadd(input).unwrap_or_default();
-            Some(Ok((ExitSucceed::Returned, ret, COST)))
+            Some(Ok((ExitSucceed::Returned, ret, cost)))
         }
         0x7 => {
-            const COST: usize = 40000;
+            let cost = if istanbul { 6000 } else { 40000 };
 
             let ret = alt_bn128::ecmul(input).unwrap_or_default();
-            Some(Ok((ExitSucceed::Returned, ret, COST)))
+            Some(Ok((ExitSucceed::Returned, ret, cost)))
         }
         0x8 => {
-            const COST: usize = 100000;
             const PAIR_SIZE: usize = 192;
 
-            let cost = COST + 80000 * (input.len() / PAIR_SIZE);
+            let (base_cost, cost_per_pair) = if istanbul { (45000, 34000) } else { (100000, 80000) };
+            let cost = base_cost + cost_per_pair * (input.len() / PAIR_SIZE);
             let ret = alt_bn128::ecpairing(input).unwrap_or_default();
 
             Some(Ok((ExitSucceed::Returned, ret, cost)))